@@ -1,32 +1,94 @@
-//! Admin and config: init, min_topup, batch_charge.
+//! Admin and config: init, min_topup, token allowlist, batch_charge, upgrade, emergency pause.
 //!
 //! **PRs that only change admin or batch behavior should edit this file only.**
 
 use crate::charge_core::charge_one;
 use crate::types::{
-    BatchChargeResult, DataKey, Error, RecoveryEvent, RecoveryReason, STORAGE_VERSION,
+    BatchChargeResult, ContractConfig, DataKey, DataKeyExt, Error, Limits, RecoveryEvent,
+    RecoveryReason, ReferralTerms, DEFAULT_MAX_AMOUNT, DEFAULT_ORACLE_MAX_AGE,
+    MAX_DUST_SWEEP_THRESHOLD, MAX_INTERVAL_SECONDS, MIN_INTERVAL_SECONDS, STORAGE_VERSION,
 };
-use soroban_sdk::{Address, Env, Symbol, Vec};
+use soroban_sdk::{symbol_short, Address, BytesN, Env, IntoVal, Map, Symbol, Val, Vec};
 
 pub fn do_init(env: &Env, token: Address, admin: Address, min_topup: i128) -> Result<(), Error> {
+    if env.storage().instance().has(&DataKey::Admin) {
+        return Err(Error::AlreadyInitialized);
+    }
     env.storage().instance().set(&DataKey::Token, &token);
     env.storage().instance().set(&DataKey::Admin, &admin);
     env.storage().instance().set(&DataKey::MinTopup, &min_topup);
+    let mut tokens = Vec::new(env);
+    tokens.push_back(token.clone());
+    env.storage()
+        .instance()
+        .set(&DataKey::SupportedTokens, &tokens);
+    let mut min_topups = Map::new(env);
+    min_topups.set(token.clone(), min_topup);
+    env.storage()
+        .instance()
+        .set(&DataKey::TokenMinTopups, &min_topups);
     env.storage()
         .instance()
         .set(&DataKey::SchemaVersion, &STORAGE_VERSION);
+    env.storage()
+        .instance()
+        .set(&DataKey::ContractVersion, &1u32);
     env.events().publish(
         (Symbol::new(env, "initialized"),),
         (token, admin, min_topup),
     );
+    crate::ttl::bump_instance(env);
     Ok(())
 }
 
+/// Config change event: topic `(name,)`, data `(old, new, admin)`.
+fn publish_config_change<T>(env: &Env, name: &str, old: T, new: T, admin: Address)
+where
+    (T, T, Address): IntoVal<Env, Val>,
+{
+    env.events()
+        .publish((Symbol::new(env, name),), (old, new, admin));
+}
+
+/// Every admin-settable parameter in one read.
+pub fn get_config(env: &Env) -> Result<ContractConfig, Error> {
+    let (max_usage_charges_per_window, usage_window_seconds) = get_usage_rate_limit(env);
+    Ok(ContractConfig {
+        admin: require_admin(env)?,
+        token: get_token(env)?,
+        min_topup: get_min_topup(env)?,
+        supported_tokens: get_supported_tokens(env),
+        max_failed_charges: get_max_failed_charges(env),
+        grace_seconds: get_grace_seconds(env),
+        max_usage_charges_per_window,
+        usage_window_seconds,
+        keeper_reward: get_keeper_reward(env),
+        settlement_delay: get_settlement_delay(env),
+        require_initial_periods: get_require_initial_periods(env),
+        staleness_seconds: get_staleness_seconds(env),
+        merchant_allowlist_enabled: is_merchant_allowlist_enabled(env),
+        paused: is_contract_paused(env),
+        limits: get_limits(env),
+        oracle_max_age: get_oracle_max_age(env),
+        charge_tolerance_seconds: get_charge_tolerance(env),
+        referral_terms: get_referral_terms(env),
+        dust_threshold: get_dust_threshold(env),
+        dust_sweep_delay: get_dust_sweep_delay(env),
+        dust_sweep_threshold: get_dust_sweep_threshold(env),
+        treasury: get_treasury(env),
+    })
+}
+
+/// The stored admin. Every admin call passes through here, so it also keeps
+/// the config (instance storage) from being archived.
 pub fn require_admin(env: &Env) -> Result<Address, Error> {
-    env.storage()
+    let admin = env
+        .storage()
         .instance()
         .get(&DataKey::Admin)
-        .ok_or(Error::Unauthorized)
+        .ok_or(Error::NotInitialized)?;
+    crate::ttl::bump_instance(env);
+    Ok(admin)
 }
 
 pub fn do_set_min_topup(env: &Env, admin: Address, min_topup: i128) -> Result<(), Error> {
@@ -35,31 +97,518 @@ pub fn do_set_min_topup(env: &Env, admin: Address, min_topup: i128) -> Result<()
     if admin != stored {
         return Err(Error::Unauthorized);
     }
+    let old = get_min_topup(env)?;
     env.storage().instance().set(&DataKey::MinTopup, &min_topup);
+    let token = get_token(env)?;
+    let mut min_topups = token_min_topups(env);
+    min_topups.set(token, min_topup);
+    env.storage()
+        .instance()
+        .set(&DataKey::TokenMinTopups, &min_topups);
+    publish_config_change(env, "min_topup_updated", old, min_topup, admin);
+    Ok(())
+}
+
+pub fn do_set_max_failed_charges(env: &Env, admin: Address, max: u32) -> Result<(), Error> {
+    admin.require_auth();
+    let stored = require_admin(env)?;
+    if admin != stored {
+        return Err(Error::Unauthorized);
+    }
+    let old = get_max_failed_charges(env);
+    env.storage()
+        .instance()
+        .set(&DataKey::MaxFailedCharges, &max);
+    publish_config_change(env, "max_failed_updated", old, max, admin);
+    Ok(())
+}
+
+pub fn get_max_failed_charges(env: &Env) -> u32 {
+    env.storage()
+        .instance()
+        .get(&DataKey::MaxFailedCharges)
+        .unwrap_or(0)
+}
+
+pub fn do_set_grace_seconds(env: &Env, admin: Address, grace_seconds: u64) -> Result<(), Error> {
+    admin.require_auth();
+    let stored = require_admin(env)?;
+    if admin != stored {
+        return Err(Error::Unauthorized);
+    }
+    let old = get_grace_seconds(env);
+    env.storage()
+        .instance()
+        .set(&DataKey::GraceSeconds, &grace_seconds);
+    publish_config_change(env, "grace_updated", old, grace_seconds, admin);
+    Ok(())
+}
+
+pub fn get_grace_seconds(env: &Env) -> u64 {
+    env.storage()
+        .instance()
+        .get(&DataKey::GraceSeconds)
+        .unwrap_or(0)
+}
+
+pub fn do_set_usage_rate_limit(
+    env: &Env,
+    admin: Address,
+    max_charges_per_window: u32,
+    window_seconds: u64,
+) -> Result<(), Error> {
+    admin.require_auth();
+    let stored = require_admin(env)?;
+    if admin != stored {
+        return Err(Error::Unauthorized);
+    }
+    let old = get_usage_rate_limit(env);
+    let limit = (max_charges_per_window, window_seconds);
+    env.storage()
+        .instance()
+        .set(&DataKey::UsageRateLimit, &limit);
+    publish_config_change(env, "usage_rate_limit_updated", old, limit, admin);
+    Ok(())
+}
+
+/// `(max_usage_charges_per_window, usage_window_seconds)`; either 0 = no limit.
+pub fn get_usage_rate_limit(env: &Env) -> (u32, u64) {
+    env.storage()
+        .instance()
+        .get(&DataKey::UsageRateLimit)
+        .unwrap_or((0, 0))
+}
+
+/// Applied through the timelock (`timelock.rs`), which checks the admin.
+pub(crate) fn set_keeper_reward(env: &Env, reward: i128) -> Result<(), Error> {
+    let admin = require_admin(env)?;
+    if reward < 0 {
+        return Err(Error::InvalidAmount);
+    }
+    let old = get_keeper_reward(env);
+    env.storage()
+        .instance()
+        .set(&DataKey::KeeperReward, &reward);
+    publish_config_change(env, "keeper_reward_updated", old, reward, admin);
+    Ok(())
+}
+
+/// Applied through the timelock (`timelock.rs`), which checks the admin.
+pub(crate) fn set_referral_terms(env: &Env, terms: ReferralTerms) -> Result<(), Error> {
+    let admin = require_admin(env)?;
+    if terms.bps > crate::math::MAX_BPS {
+        return Err(Error::InvalidAmount);
+    }
+    let old = get_referral_terms(env);
+    env.storage()
+        .instance()
+        .set(&DataKeyExt::ReferralTerms, &terms);
+    publish_config_change(env, "referral_terms_updated", old, terms, admin);
+    Ok(())
+}
+
+pub fn get_referral_terms(env: &Env) -> ReferralTerms {
+    env.storage()
+        .instance()
+        .get(&DataKeyExt::ReferralTerms)
+        .unwrap_or_default()
+}
+
+pub fn get_keeper_reward(env: &Env) -> i128 {
+    env.storage()
+        .instance()
+        .get(&DataKey::KeeperReward)
+        .unwrap_or(0)
+}
+
+pub fn do_set_settlement_delay(env: &Env, admin: Address, seconds: u64) -> Result<(), Error> {
+    admin.require_auth();
+    let stored = require_admin(env)?;
+    if admin != stored {
+        return Err(Error::Unauthorized);
+    }
+    let old = get_settlement_delay(env);
+    env.storage()
+        .instance()
+        .set(&DataKey::SettlementDelay, &seconds);
+    publish_config_change(env, "settlement_delay_updated", old, seconds, admin);
+    Ok(())
+}
+
+pub fn get_settlement_delay(env: &Env) -> u64 {
+    env.storage()
+        .instance()
+        .get(&DataKey::SettlementDelay)
+        .unwrap_or(0)
+}
+
+pub fn do_set_require_initial_periods(
+    env: &Env,
+    admin: Address,
+    periods: u32,
+) -> Result<(), Error> {
+    admin.require_auth();
+    let stored = require_admin(env)?;
+    if admin != stored {
+        return Err(Error::Unauthorized);
+    }
+    let old = get_require_initial_periods(env);
+    env.storage()
+        .instance()
+        .set(&DataKey::RequireInitialPeriods, &periods);
+    publish_config_change(env, "initial_periods_updated", old, periods, admin);
+    Ok(())
+}
+
+pub fn get_require_initial_periods(env: &Env) -> u32 {
+    env.storage()
+        .instance()
+        .get(&DataKey::RequireInitialPeriods)
+        .unwrap_or(0)
+}
+
+pub fn do_set_staleness_seconds(env: &Env, admin: Address, seconds: u64) -> Result<(), Error> {
+    admin.require_auth();
+    let stored = require_admin(env)?;
+    if admin != stored {
+        return Err(Error::Unauthorized);
+    }
+    let old = get_staleness_seconds(env);
+    env.storage()
+        .instance()
+        .set(&DataKeyExt::StalenessSeconds, &seconds);
+    publish_config_change(env, "staleness_updated", old, seconds, admin);
+    Ok(())
+}
+
+pub fn get_staleness_seconds(env: &Env) -> u64 {
+    env.storage()
+        .instance()
+        .get(&DataKeyExt::StalenessSeconds)
+        .unwrap_or(0)
+}
+
+/// Replace the bounds on `amount` and `interval_seconds`. Existing
+/// subscriptions keep their terms; the bounds apply to the next create or
+/// change. `InvalidAmount` unless `1 <= min_amount <= max_amount`,
+/// `InvalidInterval` unless `1 <= min_interval <= max_interval`.
+pub fn do_set_limits(env: &Env, admin: Address, limits: Limits) -> Result<(), Error> {
+    admin.require_auth();
+    let stored = require_admin(env)?;
+    if admin != stored {
+        return Err(Error::Unauthorized);
+    }
+    if limits.min_amount < 1 || limits.min_amount > limits.max_amount {
+        return Err(Error::InvalidAmount);
+    }
+    if limits.min_interval < 1 || limits.min_interval > limits.max_interval {
+        return Err(Error::InvalidInterval);
+    }
+    let old = get_limits(env);
+    env.storage().instance().set(&DataKeyExt::Limits, &limits);
+    publish_config_change(env, "limits_updated", old, limits, admin);
+    Ok(())
+}
+
+pub fn get_limits(env: &Env) -> Limits {
+    env.storage()
+        .instance()
+        .get(&DataKeyExt::Limits)
+        .unwrap_or(Limits {
+            min_amount: 1,
+            max_amount: DEFAULT_MAX_AMOUNT,
+            min_interval: MIN_INTERVAL_SECONDS,
+            max_interval: MAX_INTERVAL_SECONDS,
+        })
+}
+
+/// `InvalidAmount` unless `amount` is within [`get_limits`].
+pub fn require_amount_in_limits(env: &Env, amount: i128) -> Result<(), Error> {
+    let limits = get_limits(env);
+    if !(limits.min_amount..=limits.max_amount).contains(&amount) {
+        return Err(Error::InvalidAmount);
+    }
+    Ok(())
+}
+
+/// `InvalidInterval` unless `interval_seconds` is within [`get_limits`].
+pub fn require_interval_in_limits(env: &Env, interval_seconds: u64) -> Result<(), Error> {
+    let limits = get_limits(env);
+    if !(limits.min_interval..=limits.max_interval).contains(&interval_seconds) {
+        return Err(Error::InvalidInterval);
+    }
+    Ok(())
+}
+
+/// Allowlist (`allowed = true`) or drop a price oracle for fiat-priced
+/// subscriptions. Dropping one makes charges of subscriptions that use it fail
+/// with `OracleNotAllowed`. Emits `oracle_added` / `oracle_removed` with the admin.
+/// Applied through the timelock (`timelock.rs`), which checks the admin.
+pub(crate) fn set_price_oracle(env: &Env, oracle: Address, allowed: bool) -> Result<(), Error> {
+    let admin = require_admin(env)?;
+    let key = DataKeyExt::PriceOracle(oracle.clone());
+    let name = if allowed {
+        env.storage().instance().set(&key, &true);
+        "oracle_added"
+    } else {
+        env.storage().instance().remove(&key);
+        "oracle_removed"
+    };
     env.events()
-        .publish((Symbol::new(env, "min_topup_updated"),), min_topup);
+        .publish((Symbol::new(env, name), oracle), admin);
+    Ok(())
+}
+
+pub fn is_price_oracle(env: &Env, oracle: &Address) -> bool {
+    env.storage()
+        .instance()
+        .has(&DataKeyExt::PriceOracle(oracle.clone()))
+}
+
+pub fn do_set_oracle_max_age(env: &Env, admin: Address, seconds: u64) -> Result<(), Error> {
+    admin.require_auth();
+    let stored = require_admin(env)?;
+    if admin != stored {
+        return Err(Error::Unauthorized);
+    }
+    let old = get_oracle_max_age(env);
+    env.storage()
+        .instance()
+        .set(&DataKeyExt::OracleMaxAge, &seconds);
+    publish_config_change(env, "oracle_max_age_updated", old, seconds, admin);
+    Ok(())
+}
+
+pub fn get_oracle_max_age(env: &Env) -> u64 {
+    env.storage()
+        .instance()
+        .get(&DataKeyExt::OracleMaxAge)
+        .unwrap_or(DEFAULT_ORACLE_MAX_AGE)
+}
+
+/// Early-charge tolerance. Each charge caps it at half the subscription's
+/// interval, so a value too large for a short interval cannot double-charge.
+pub fn do_set_charge_tolerance(env: &Env, admin: Address, seconds: u64) -> Result<(), Error> {
+    admin.require_auth();
+    let stored = require_admin(env)?;
+    if admin != stored {
+        return Err(Error::Unauthorized);
+    }
+    let old = get_charge_tolerance(env);
+    env.storage()
+        .instance()
+        .set(&DataKeyExt::ChargeTolerance, &seconds);
+    publish_config_change(env, "charge_tolerance_updated", old, seconds, admin);
+    Ok(())
+}
+
+pub fn get_charge_tolerance(env: &Env) -> u64 {
+    env.storage()
+        .instance()
+        .get(&DataKeyExt::ChargeTolerance)
+        .unwrap_or(0)
+}
+
+pub fn do_set_dust_threshold(env: &Env, admin: Address, threshold: i128) -> Result<(), Error> {
+    admin.require_auth();
+    let stored = require_admin(env)?;
+    if admin != stored {
+        return Err(Error::Unauthorized);
+    }
+    if threshold < 0 {
+        return Err(Error::InvalidAmount);
+    }
+    let old = get_dust_threshold(env);
+    env.storage()
+        .instance()
+        .set(&DataKeyExt::DustThreshold, &threshold);
+    publish_config_change(env, "dust_threshold_updated", old, threshold, admin);
+    Ok(())
+}
+
+pub fn get_dust_threshold(env: &Env) -> i128 {
+    env.storage()
+        .instance()
+        .get(&DataKeyExt::DustThreshold)
+        .unwrap_or(0)
+}
+
+pub fn do_set_dust_sweep_delay(env: &Env, admin: Address, seconds: u64) -> Result<(), Error> {
+    admin.require_auth();
+    let stored = require_admin(env)?;
+    if admin != stored {
+        return Err(Error::Unauthorized);
+    }
+    let old = get_dust_sweep_delay(env);
+    env.storage()
+        .instance()
+        .set(&DataKeyExt::DustSweepDelay, &seconds);
+    publish_config_change(env, "dust_sweep_delay_updated", old, seconds, admin);
     Ok(())
 }
 
+pub fn get_dust_sweep_delay(env: &Env) -> u64 {
+    env.storage()
+        .instance()
+        .get(&DataKeyExt::DustSweepDelay)
+        .unwrap_or(0)
+}
+
+/// Largest balance `sweep_dust` may take, separate from the usage dust
+/// threshold and capped at [`MAX_DUST_SWEEP_THRESHOLD`].
+pub fn do_set_dust_sweep_threshold(
+    env: &Env,
+    admin: Address,
+    threshold: i128,
+) -> Result<(), Error> {
+    admin.require_auth();
+    let stored = require_admin(env)?;
+    if admin != stored {
+        return Err(Error::Unauthorized);
+    }
+    if !(0..=MAX_DUST_SWEEP_THRESHOLD).contains(&threshold) {
+        return Err(Error::InvalidAmount);
+    }
+    let old = get_dust_sweep_threshold(env);
+    env.storage()
+        .instance()
+        .set(&DataKeyExt::DustSweepThreshold, &threshold);
+    publish_config_change(env, "dust_sweep_threshold_updated", old, threshold, admin);
+    Ok(())
+}
+
+pub fn get_dust_sweep_threshold(env: &Env) -> i128 {
+    env.storage()
+        .instance()
+        .get(&DataKeyExt::DustSweepThreshold)
+        .unwrap_or(0)
+}
+
+pub fn do_set_treasury(env: &Env, admin: Address, treasury: Address) -> Result<(), Error> {
+    admin.require_auth();
+    let stored = require_admin(env)?;
+    if admin != stored {
+        return Err(Error::Unauthorized);
+    }
+    let old = get_treasury(env);
+    env.storage()
+        .instance()
+        .set(&DataKeyExt::Treasury, &treasury);
+    publish_config_change(env, "treasury_updated", old, Some(treasury), admin);
+    Ok(())
+}
+
+pub fn get_treasury(env: &Env) -> Option<Address> {
+    env.storage().instance().get(&DataKeyExt::Treasury)
+}
+
 pub fn get_min_topup(env: &Env) -> Result<i128, Error> {
     env.storage()
         .instance()
         .get(&DataKey::MinTopup)
-        .ok_or(Error::NotFound)
+        .ok_or(Error::NotInitialized)
+}
+
+/// The token passed to `init`; used by `create_subscription` when no token is given.
+pub fn get_token(env: &Env) -> Result<Address, Error> {
+    env.storage()
+        .instance()
+        .get(&DataKey::Token)
+        .ok_or(Error::NotInitialized)
+}
+
+/// Allowlisted tokens. Deployments initialized before the allowlist existed
+/// fall back to the single `init` token.
+pub fn get_supported_tokens(env: &Env) -> Vec<Address> {
+    if let Some(tokens) = env.storage().instance().get(&DataKey::SupportedTokens) {
+        return tokens;
+    }
+    let mut tokens = Vec::new(env);
+    if let Ok(token) = get_token(env) {
+        tokens.push_back(token);
+    }
+    tokens
+}
+
+pub fn is_token_supported(env: &Env, token: &Address) -> bool {
+    get_supported_tokens(env).contains(token)
+}
+
+fn token_min_topups(env: &Env) -> Map<Address, i128> {
+    if let Some(map) = env.storage().instance().get(&DataKey::TokenMinTopups) {
+        return map;
+    }
+    let mut map = Map::new(env);
+    if let (Ok(token), Ok(min_topup)) = (get_token(env), get_min_topup(env)) {
+        map.set(token, min_topup);
+    }
+    map
+}
+
+/// Minimum deposit for subscriptions denominated in `token`.
+///
+/// Entries survive `remove_supported_token` so existing subscriptions can
+/// still be topped up.
+pub fn get_token_min_topup(env: &Env, token: &Address) -> Result<i128, Error> {
+    token_min_topups(env)
+        .get(token.clone())
+        .ok_or(Error::TokenNotSupported)
+}
+
+/// Applied through the timelock (`timelock.rs`), which checks the admin.
+pub(crate) fn add_supported_token(env: &Env, token: Address, min_topup: i128) -> Result<(), Error> {
+    let mut tokens = get_supported_tokens(env);
+    if !tokens.contains(&token) {
+        tokens.push_back(token.clone());
+        env.storage()
+            .instance()
+            .set(&DataKey::SupportedTokens, &tokens);
+    }
+    let mut min_topups = token_min_topups(env);
+    min_topups.set(token.clone(), min_topup);
+    env.storage()
+        .instance()
+        .set(&DataKey::TokenMinTopups, &min_topups);
+    if get_token(env)? == token {
+        env.storage().instance().set(&DataKey::MinTopup, &min_topup);
+    }
+
+    env.events()
+        .publish((Symbol::new(env, "token_added"), token), min_topup);
+    Ok(())
+}
+
+/// Remove a token from the allowlist. Existing subscriptions in that token keep
+/// working; only new subscriptions are rejected. Applied through the timelock.
+pub(crate) fn remove_supported_token(env: &Env, token: Address) -> Result<(), Error> {
+    let mut tokens = get_supported_tokens(env);
+    let idx = tokens.first_index_of(&token).ok_or(Error::NotFound)?;
+    tokens.remove(idx);
+    env.storage()
+        .instance()
+        .set(&DataKey::SupportedTokens, &tokens);
+
+    env.events()
+        .publish((Symbol::new(env, "token_removed"), token), ());
+    Ok(())
 }
 
 pub fn do_batch_charge(
     env: &Env,
-    subscription_ids: &Vec<u32>,
+    subscription_ids: &Vec<u64>,
 ) -> Result<Vec<BatchChargeResult>, Error> {
     let auth_admin = require_admin(env)?;
     auth_admin.require_auth();
 
     let mut results = Vec::new(env);
     for id in subscription_ids.iter() {
-        let r = charge_one(env, id, None);
+        let r = charge_one(env, id, None, None);
         let res = match &r {
-            Ok(()) => BatchChargeResult {
+            Ok(receipt) if receipt.failure_code != 0 => BatchChargeResult {
+                success: false,
+                error_code: receipt.failure_code,
+            },
+            Ok(_) => BatchChargeResult {
                 success: true,
                 error_code: 0,
             },
@@ -77,7 +626,7 @@ pub fn do_get_admin(env: &Env) -> Result<Address, Error> {
     env.storage()
         .instance()
         .get(&DataKey::Admin)
-        .ok_or(Error::NotFound)
+        .ok_or(Error::NotInitialized)
 }
 
 pub fn do_rotate_admin(env: &Env, current_admin: Address, new_admin: Address) -> Result<(), Error> {
@@ -87,7 +636,7 @@ pub fn do_rotate_admin(env: &Env, current_admin: Address, new_admin: Address) ->
         .storage()
         .instance()
         .get(&DataKey::Admin)
-        .ok_or(Error::NotFound)?;
+        .ok_or(Error::NotInitialized)?;
 
     if current_admin != stored_admin {
         return Err(Error::Unauthorized);
@@ -103,6 +652,130 @@ pub fn do_rotate_admin(env: &Env, current_admin: Address, new_admin: Address) ->
     Ok(())
 }
 
+pub fn is_contract_paused(env: &Env) -> bool {
+    env.storage()
+        .instance()
+        .get(&DataKey::ContractPaused)
+        .unwrap_or(false)
+}
+
+/// Guard for money-moving entrypoints. Returns `ContractPaused` while the
+/// emergency switch is on.
+pub fn require_not_paused(env: &Env) -> Result<(), Error> {
+    if is_contract_paused(env) {
+        return Err(Error::ContractPaused);
+    }
+    Ok(())
+}
+
+pub fn is_blacklisted(env: &Env, address: &Address) -> bool {
+    env.storage()
+        .instance()
+        .has(&DataKey::Blacklisted(address.clone()))
+}
+
+/// Guard for creating and funding subscriptions and merchant payouts. Returns
+/// `AddressBlocked` for blacklisted addresses. Cancellation and subscriber
+/// withdrawals are deliberately not guarded so funds are never trapped.
+pub fn require_not_blocked(env: &Env, address: &Address) -> Result<(), Error> {
+    if is_blacklisted(env, address) {
+        return Err(Error::AddressBlocked);
+    }
+    Ok(())
+}
+
+pub fn do_set_blacklisted(
+    env: &Env,
+    admin: Address,
+    address: Address,
+    blocked: bool,
+) -> Result<(), Error> {
+    admin.require_auth();
+    let stored = require_admin(env)?;
+    if admin != stored {
+        return Err(Error::Unauthorized);
+    }
+    let key = DataKey::Blacklisted(address.clone());
+    let topic = if blocked {
+        env.storage().instance().set(&key, &true);
+        Symbol::new(env, "blacklisted")
+    } else {
+        env.storage().instance().remove(&key);
+        Symbol::new(env, "unblacklisted")
+    };
+    env.events()
+        .publish((topic, address), (admin, env.ledger().timestamp()));
+    Ok(())
+}
+
+pub fn do_set_merchant_allowlist_enabled(
+    env: &Env,
+    admin: Address,
+    enabled: bool,
+) -> Result<(), Error> {
+    admin.require_auth();
+    let stored = require_admin(env)?;
+    if admin != stored {
+        return Err(Error::Unauthorized);
+    }
+    let old = is_merchant_allowlist_enabled(env);
+    env.storage()
+        .instance()
+        .set(&DataKey::MerchantAllowlistEnabled, &enabled);
+    publish_config_change(env, "merchant_allowlist", old, enabled, admin);
+    Ok(())
+}
+
+pub fn is_merchant_allowlist_enabled(env: &Env) -> bool {
+    env.storage()
+        .instance()
+        .get(&DataKey::MerchantAllowlistEnabled)
+        .unwrap_or(false)
+}
+
+pub fn do_set_contract_paused(env: &Env, admin: Address, paused: bool) -> Result<(), Error> {
+    admin.require_auth();
+    let stored = require_admin(env)?;
+    if admin != stored {
+        return Err(Error::Unauthorized);
+    }
+    let old = is_contract_paused(env);
+    env.storage()
+        .instance()
+        .set(&DataKey::ContractPaused, &paused);
+
+    let topic = if paused {
+        symbol_short!("ctr_pause")
+    } else {
+        symbol_short!("ctr_unpse")
+    };
+    env.events().publish((topic,), (old, paused, admin));
+    Ok(())
+}
+
+pub fn get_version(env: &Env) -> u32 {
+    env.storage()
+        .instance()
+        .get(&DataKey::ContractVersion)
+        .unwrap_or(0)
+}
+
+/// Applied through the timelock (`timelock.rs`), which checks the admin.
+pub(crate) fn upgrade(env: &Env, new_wasm_hash: BytesN<32>) -> Result<(), Error> {
+    let version = get_version(env).checked_add(1).ok_or(Error::Overflow)?;
+    env.storage()
+        .instance()
+        .set(&DataKey::ContractVersion, &version);
+
+    env.events().publish(
+        (Symbol::new(env, "upgraded"),),
+        (new_wasm_hash.clone(), version),
+    );
+
+    env.deployer().update_current_contract_wasm(new_wasm_hash);
+    Ok(())
+}
+
 pub fn do_recover_stranded_funds(
     env: &Env,
     admin: Address,
@@ -116,14 +789,14 @@ pub fn do_recover_stranded_funds(
         .storage()
         .instance()
         .get(&DataKey::Admin)
-        .ok_or(Error::NotFound)?;
+        .ok_or(Error::NotInitialized)?;
 
     if admin != stored_admin {
         return Err(Error::Unauthorized);
     }
 
     if amount <= 0 {
-        return Err(Error::InvalidRecoveryAmount);
+        return Err(Error::InvalidAmount);
     }
 
     let recovery_event = RecoveryEvent {
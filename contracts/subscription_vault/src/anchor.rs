@@ -0,0 +1,121 @@
+//! Billing anchor: charge every subscriber of a merchant on the same dates,
+//! `anchor + k * interval_seconds`, instead of one interval after each signup.
+//!
+//! **PRs that only change billing anchors should edit this file only.**
+//!
+//! # Schedule
+//!
+//! When the billing clock starts (creation, trial end, or activation of a
+//! `Pending` subscription) `last_payment_timestamp` is set one interval before
+//! the first anchor date strictly after the start. Every `last_payment +
+//! interval` reader (next charge, renewal, scheduled cancel) then sees the
+//! anchor schedule without knowing about it. Each charge moves
+//! `last_payment_timestamp` to the latest anchor date at or before the charge,
+//! so a late charge never shifts the schedule.
+//!
+//! # Proration
+//!
+//! The first charge covers only the start-to-anchor stretch:
+//! `list * first_period_seconds / interval_seconds`, rounded down like other
+//! prorations. Starting exactly on an anchor date gives a full first period.
+
+use crate::queries::get_subscription;
+use crate::stored_sub::Optional;
+use crate::types::{BillingAnchor, Error, Subscription};
+use soroban_sdk::Env;
+
+pub fn get(env: &Env, subscription_id: u64) -> Option<BillingAnchor> {
+    crate::stored_sub::get_optional(env, subscription_id, Optional::BillingAnchor)
+}
+
+pub fn remove(env: &Env, subscription_id: u64) {
+    crate::stored_sub::remove_optional(env, subscription_id, Optional::BillingAnchor);
+}
+
+/// `(last_payment_timestamp, first_period_seconds)` for a billing clock
+/// starting at `start`. Unanchored (`anchor == 0`) it is `(start, interval)`.
+pub(crate) fn schedule(anchor: u64, start: u64, interval: u64) -> Result<(u64, u64), Error> {
+    if anchor == 0 {
+        return Ok((start, interval));
+    }
+    let phase = anchor % interval;
+    let first = if start < phase {
+        phase
+    } else {
+        ((start - phase) / interval)
+            .checked_add(1)
+            .and_then(|k| k.checked_mul(interval))
+            .and_then(|t| t.checked_add(phase))
+            .ok_or(Error::Overflow)?
+    };
+    let last_payment = first.checked_sub(interval).ok_or(Error::Overflow)?;
+    Ok((last_payment, first - start))
+}
+
+/// Record the anchor of a new subscription; a no-op for `anchor == 0`.
+pub(crate) fn init(env: &Env, subscription_id: u64, anchor: u64, first_period_seconds: u64) {
+    if anchor == 0 {
+        return;
+    }
+    crate::stored_sub::set_optional(
+        env,
+        subscription_id,
+        Optional::BillingAnchor,
+        &BillingAnchor {
+            anchor,
+            first_period_seconds,
+        },
+    );
+}
+
+/// Restart the billing clock at `start`, keeping the subscription on its anchor.
+pub(crate) fn restart(
+    env: &Env,
+    subscription_id: u64,
+    sub: &mut Subscription,
+    start: u64,
+) -> Result<(), Error> {
+    let anchor = get(env, subscription_id).map_or(0, |a| a.anchor);
+    let (last_payment, first_period_seconds) = schedule(anchor, start, sub.interval_seconds)?;
+    sub.last_payment_timestamp = last_payment;
+    init(env, subscription_id, anchor, first_period_seconds);
+    Ok(())
+}
+
+/// Latest anchor date at or before `now`, for an anchored `sub` that is due.
+pub(crate) fn period_start(sub: &Subscription, now: u64) -> u64 {
+    let last = sub.last_payment_timestamp;
+    if now <= last {
+        return last;
+    }
+    now - (now - last) % sub.interval_seconds
+}
+
+/// Replay-protection period of a charge at `now`. Anchored periods are keyed
+/// by their start so two consecutive anchor dates can never share an index.
+pub(crate) fn period_index(anchored: bool, sub: &Subscription, now: u64) -> u64 {
+    if anchored {
+        period_start(sub, now) / sub.interval_seconds
+    } else {
+        now / sub.interval_seconds
+    }
+}
+
+/// `list` for the period being charged, prorated (rounded down) on the first charge.
+pub(crate) fn prorate(
+    anchor: Option<&BillingAnchor>,
+    sub: &Subscription,
+    list: i128,
+) -> Result<i128, Error> {
+    match anchor {
+        Some(a) if sub.periods_charged == 0 && a.first_period_seconds < sub.interval_seconds => {
+            crate::math::prorate(list, a.first_period_seconds, sub.interval_seconds)
+        }
+        _ => Ok(list),
+    }
+}
+
+pub fn get_billing_anchor(env: &Env, subscription_id: u64) -> Result<Option<BillingAnchor>, Error> {
+    get_subscription(env, subscription_id)?;
+    Ok(get(env, subscription_id))
+}
@@ -0,0 +1,116 @@
+//! Auto top-up: refill `prepaid_balance` from the subscriber's wallet when a
+//! debit leaves it below a floor.
+//!
+//! **PRs that only change auto top-up should edit this file only.**
+//!
+//! The pull uses a standing token allowance to the contract, as direct debit
+//! does. It runs after the debit is stored and never fails it: a refused pull
+//! is undone and reported with `auto_top_failed`, and the usual low-balance
+//! handling goes on as without a top-up.
+
+use crate::queries::get_subscription;
+use crate::state_machine::get_allowed_transitions;
+use crate::stats::{load_subscription, save_subscription};
+use crate::stored_sub::Optional;
+use crate::types::{AutoTopup, Error, FundingMode, Subscription};
+use soroban_sdk::{symbol_short, token, Address, Env, Symbol};
+
+pub fn get(env: &Env, subscription_id: u64) -> Option<AutoTopup> {
+    crate::stored_sub::get_optional(env, subscription_id, Optional::AutoTopup)
+}
+
+pub fn remove(env: &Env, subscription_id: u64) {
+    crate::stored_sub::remove_optional(env, subscription_id, Optional::AutoTopup);
+}
+
+/// A debit that left `sub` in this state should be followed by a top-up.
+pub(crate) fn is_due(env: &Env, subscription_id: u64, sub: &Subscription) -> bool {
+    get(env, subscription_id).is_some_and(|c| c.enabled && sub.prepaid_balance < c.floor)
+}
+
+/// Subscriber configures auto top-up, replacing any earlier config. The
+/// amount must clear the effective minimum top-up when enabled.
+pub fn do_set_auto_topup(
+    env: &Env,
+    subscription_id: u64,
+    subscriber: Address,
+    config: AutoTopup,
+) -> Result<(), Error> {
+    subscriber.require_auth();
+
+    let sub = get_subscription(env, subscription_id)?;
+    if subscriber != sub.subscriber {
+        return Err(Error::Unauthorized);
+    }
+    if get_allowed_transitions(&sub.status).is_empty() {
+        return Err(Error::InvalidStatusTransition);
+    }
+    if sub.funding_mode == FundingMode::DirectDebit {
+        return Err(Error::InvalidFundingMode);
+    }
+    if config.floor < 0 || config.topup_amount < 0 {
+        return Err(Error::InvalidAmount);
+    }
+    if config.enabled && config.topup_amount < crate::merchant::effective_min_topup(env, &sub)? {
+        return Err(Error::BelowMinimumTopup);
+    }
+
+    crate::stored_sub::set_optional(env, subscription_id, Optional::AutoTopup, &config);
+    env.events().publish(
+        (Symbol::new(env, "auto_topup_set"), subscription_id),
+        config,
+    );
+    Ok(())
+}
+
+/// Pull the configured amount from the subscriber into the vault. Runs after
+/// every storage write of the debit; emits `auto_top` or `auto_top_failed`.
+pub(crate) fn run(env: &Env, subscription_id: u64) {
+    let Some(config) = get(env, subscription_id).filter(|c| c.enabled) else {
+        return;
+    };
+    let Ok(mut sub) = load_subscription(env, subscription_id) else {
+        return;
+    };
+    if sub.prepaid_balance >= config.floor
+        || get_allowed_transitions(&sub.status).is_empty()
+        || crate::admin::is_blacklisted(env, &sub.subscriber)
+    {
+        return;
+    }
+    let balance = sub.prepaid_balance;
+    let Some(topped_up) = balance.checked_add(config.topup_amount) else {
+        return;
+    };
+    sub.prepaid_balance = topped_up;
+    if save_subscription(env, subscription_id, &mut sub).is_err() {
+        return;
+    }
+
+    let contract = env.current_contract_address();
+    let result = token::Client::new(env, &sub.token).try_transfer_from(
+        &contract,
+        &sub.subscriber,
+        &contract,
+        &config.topup_amount,
+    );
+    if matches!(result, Ok(Ok(()))) {
+        crate::expiry::record_deposit(env, subscription_id);
+        env.events().publish(
+            (symbol_short!("auto_top"), subscription_id),
+            (config.topup_amount, topped_up),
+        );
+    } else {
+        sub.prepaid_balance = balance;
+        let _ = save_subscription(env, subscription_id, &mut sub);
+        env.events().publish(
+            (Symbol::new(env, "auto_top_failed"), subscription_id),
+            (config.topup_amount, balance),
+        );
+    }
+}
+
+pub fn get_auto_topup(env: &Env, subscription_id: u64) -> Result<Option<AutoTopup>, Error> {
+    get_subscription(env, subscription_id)?;
+    Ok(get(env, subscription_id))
+}
@@ -0,0 +1,45 @@
+//! Bulk creation: a merchant onboards existing customers as `Pending`
+//! subscriptions without each customer signing a creation transaction.
+//!
+//! **PRs that only change bulk creation should edit this file only.**
+//!
+//! Nothing can be charged until the subscriber's own first deposit covers one
+//! period and activates the subscription; that deposit is their consent. A
+//! subscriber who does not want it cancels the `Pending` subscription.
+
+use crate::subscription::create_authorized;
+use crate::types::{BulkEntry, DataKey, Error, SubscriptionOptions, MAX_BULK_CREATE};
+use soroban_sdk::{Address, Env, Vec};
+
+pub fn do_bulk_create_unfunded(
+    env: &Env,
+    merchant: Address,
+    entries: Vec<BulkEntry>,
+) -> Result<Vec<u64>, Error> {
+    if entries.len() > MAX_BULK_CREATE {
+        return Err(Error::BatchTooLarge);
+    }
+    merchant.require_auth();
+    if env
+        .storage()
+        .instance()
+        .has(&DataKey::MerchantRedirect(merchant.clone()))
+    {
+        return Err(Error::InvalidMerchantRotation);
+    }
+
+    let mut ids = Vec::new(env);
+    for entry in entries.iter() {
+        // Default options: prepaid, no trial, no deposit, so always `Pending`.
+        ids.push_back(create_authorized(
+            env,
+            entry.subscriber,
+            merchant.clone(),
+            entry.amount,
+            entry.interval_seconds,
+            entry.usage_enabled,
+            SubscriptionOptions::default(),
+        )?);
+    }
+    Ok(ids)
+}
@@ -7,153 +7,1203 @@
 //! Charges are protected against replay by:
 //! - **Period-based key**: We record the last charged billing period index per subscription.
 //!   A charge for the same period is rejected with [`Error::Replay`].
+//! - **Ledger guard**: We record the ledger sequence of the last periodic charge, so racing
+//!   keepers cannot apply two in one ledger whatever the tolerance or catch-up math says.
+//!   Rejected with [`Error::AlreadyChargedThisLedger`]; usage charges are exempt.
 //! - **Optional idempotency key**: If the caller supplies an idempotency key (e.g. for retries),
-//!   we store one key per subscription. A second call with the same key returns `Ok(())` without
-//!   debiting again (idempotent success). Storage stays bounded (one key and one period per sub).
+//!   we store one key per subscription. A second call with the same key returns a zero-amount
+//!   receipt without debiting again (idempotent success). Storage stays bounded (one key and one period per sub).
+//! - **Usage ids**: every usage charge carries a caller-chosen `usage_id`. Processed ids live in
+//!   temporary storage for [`USAGE_ID_TTL_LEDGERS`]; a repeat returns a receipt without debiting.
+//!
+//! # Funding modes
+//!
+//! Every debit goes through [`available_funds`] and [`take_funds`]: `Prepaid` subscriptions
+//! draw on `prepaid_balance`, `DirectDebit` ones pull from the subscriber's wallet with
+//! `transfer_from`, and `Hybrid` ones use the prepaid balance first and pull only the rest.
+//! A pull the allowance or wallet cannot cover is handled exactly like a short prepaid balance.
+//!
+//! # Checks-effects-interactions
+//!
+//! Every entrypoint validates first, then writes storage, and only then calls out to
+//! another contract (token transfers, the merchant's charge callback). `take_funds` therefore
+//! only debits in memory and hands the wallet part to [`WalletPull`], which the caller executes
+//! after its last write. Soroban rejects re-entry into a contract already on the call stack, so
+//! this ordering is defence in depth: a token or callback never observes half-applied state.
+//! New entrypoints must keep the same order.
 
-use crate::queries::get_subscription;
-use crate::state_machine::validate_status_transition;
-use crate::types::{DataKey, Error, SubscriptionChargedEvent, SubscriptionStatus};
-use soroban_sdk::{symbol_short, Env};
+use crate::admin::{
+    get_charge_tolerance, get_dust_threshold, get_grace_seconds, get_keeper_reward,
+    get_usage_rate_limit, is_contract_paused, require_not_paused,
+};
+use crate::coupon::apply_discount;
+use crate::merchant::{credit_merchant_balance, get_usage_attestation_key};
+use crate::queries::{compute_next_charge_info, get_subscription, loyalty_active, period_amount};
+use crate::state_machine::{apply_transition, can_transition};
+use crate::stats::{load_subscription, save_subscription, LoadedSubscription};
+use crate::stored_sub::{get_entry, set_entry, Optional};
+use crate::subscription::{release_live_slot, stage_quantity};
+use crate::types::{
+    ArrearsSettledEvent, BillingAnchor, ChargeKind, ChargePreview, ChargeReceipt, CouponKind,
+    DataKey, DataKeyExt, DunningExhaustedEvent, Error, FundingMode, LowBalanceEvent,
+    PartialChargeEvent, StatusChangedEvent, Subscription, SubscriptionCancelledEvent,
+    SubscriptionChargedEvent, SubscriptionCompletedEvent, SubscriptionStats, SubscriptionStatus,
+    UsageChargeReceipt, UsageChargedEvent, UsageTier, USAGE_ID_TTL_LEDGERS,
+};
+use soroban_sdk::{
+    panic_with_error, symbol_short, token, xdr::ToXdr, Address, BytesN, Env, IntoVal, InvokeError,
+    Symbol, Val, Vec,
+};
 
 /// Performs a single interval-based charge with optional replay protection.
 ///
 /// # Idempotency
 ///
 /// - If `idempotency_key` is `Some(k)` and we already processed this subscription with key `k`,
-///   returns `Ok(())` without changing state (idempotent success).
+///   returns a zero-amount receipt without changing state (idempotent success).
 /// - Otherwise we derive a period from `now / interval_seconds`. If this period was already
 ///   charged, returns `Err(Error::Replay)`.
+/// - A second periodic charge in the same ledger returns `Err(Error::AlreadyChargedThisLedger)`.
 ///
 /// # Storage
 ///
 /// Bounded: one `u64` (last charged period) and optionally one idempotency key per subscription.
 pub fn charge_one(
     env: &Env,
-    subscription_id: u32,
+    subscription_id: u64,
     idempotency_key: Option<soroban_sdk::BytesN<32>>,
-) -> Result<(), Error> {
-    let mut sub = get_subscription(env, subscription_id)?;
+    keeper: Option<Address>,
+) -> Result<ChargeReceipt, Error> {
+    require_not_paused(env)?;
+    let mut sub = load_subscription(env, subscription_id)?;
 
-    if sub.status != SubscriptionStatus::Active {
+    let now = env.ledger().timestamp();
+    // Scheduled cancellation: the subscriber already paid through `cancel_at`.
+    // Checked before the status gate so a paused subscription is cancelled too.
+    if sub.cancel_at > 0
+        && now >= sub.cancel_at
+        && can_transition(&sub.status, &SubscriptionStatus::Cancelled)
+    {
+        let previous_status = apply_transition(&mut sub, SubscriptionStatus::Cancelled, now)?;
+        save_subscription(env, subscription_id, &mut sub)?;
+        release_live_slot(env, subscription_id, &sub);
+        crate::trial_bond::settle(env, subscription_id, &sub)?;
+        env.events().publish(
+            (symbol_short!("cancelled"), subscription_id),
+            SubscriptionCancelledEvent {
+                subscription_id,
+                authorizer: sub.subscriber.clone(),
+                reason: sub.reason,
+                refund_amount: sub.prepaid_balance,
+                previous_status,
+                new_status: sub.status.clone(),
+                timestamp: now,
+            },
+        );
+        return Ok(charge_receipt(&sub, 0, 0, 0));
+    }
+
+    // `InsufficientBalance` subscriptions are still retried so dunning can count failures.
+    if sub.status != SubscriptionStatus::Active
+        && sub.status != SubscriptionStatus::InsufficientBalance
+    {
         return Err(Error::NotActive);
     }
+    if now < sub.trial_until {
+        return Err(Error::InTrialPeriod);
+    }
 
-    let now = env.ledger().timestamp();
-    let period_index = now / sub.interval_seconds;
+    let billed_at = billed_at(env, &sub, now);
+    let anchor = crate::anchor::get(env, subscription_id);
+    let mut period_index = crate::anchor::period_index(anchor.is_some(), &sub, billed_at);
 
     if let Some(ref k) = idempotency_key {
-        if let Some(stored) = env
-            .storage()
-            .instance()
-            .get::<_, soroban_sdk::BytesN<32>>(&DataKey::IdemKey(subscription_id))
-        {
+        if let Some(stored) = crate::stored_sub::get_entry::<_, soroban_sdk::BytesN<32>>(
+            env,
+            &DataKey::IdemKey(subscription_id),
+        ) {
             if stored == *k {
-                return Ok(());
+                return Ok(charge_receipt(&sub, 0, 0, 0));
             }
         }
     }
 
-    if let Some(stored_period) = env
-        .storage()
-        .instance()
-        .get::<_, u64>(&DataKey::ChargedPeriod(subscription_id))
-    {
-        if period_index <= stored_period {
-            return Err(Error::Replay);
+    if period_charged(env, subscription_id, period_index) {
+        return Err(Error::Replay);
+    }
+    if charged_this_ledger(env, subscription_id) {
+        return Err(Error::AlreadyChargedThisLedger);
+    }
+    // A fiat-priced subscription is re-quoted only once the period is due.
+    let period_due = sub
+        .last_payment_timestamp
+        .saturating_add(sub.interval_seconds);
+    if billed_at >= period_due {
+        crate::oracle::reprice(env, subscription_id, &mut sub)?;
+    }
+
+    let ChargeQuote {
+        next_allowed,
+        grace_deadline,
+        in_grace,
+        partial,
+        covered,
+        list,
+        due,
+        amount: charged,
+    } = quote_charge(env, &sub, anchor.as_ref(), billed_at)?;
+    if billed_at < next_allowed {
+        return Err(Error::IntervalNotElapsed);
+    }
+    if !crate::renewal::is_approved(env, subscription_id, &sub) {
+        return Err(Error::RenewalNotApproved);
+    }
+
+    if !covered {
+        if in_grace && sub.status == SubscriptionStatus::Active {
+            sub.failed_charge_count = sub.failed_charge_count.saturating_add(1);
+            sub.loyalty.streak = 0;
+            save_subscription(env, subscription_id, &mut sub)?;
+            env.events().publish(
+                crate::notify::topics(
+                    env,
+                    subscription_id,
+                    (symbol_short!("grace"), subscription_id),
+                ),
+                grace_deadline,
+            );
+            return Ok(failed_receipt(&sub, Error::InsufficientBalance));
         }
+        return record_failed_charge(env, subscription_id, sub, now);
+    }
+    if sub.status != SubscriptionStatus::Active {
+        // Funded again but not yet resumed.
+        return Err(Error::NotActive);
+    }
+
+    crate::lifetime_cap::check(env, subscription_id, due)?;
+
+    let shortfall = due.checked_sub(charged).ok_or(Error::Overflow)?;
+    let pull = take_funds(env, subscription_id, &mut sub, charged)?;
+    sub.arrears = sub.arrears.checked_add(shortfall).ok_or(Error::Overflow)?;
+    // A trial always ends at the first charge.
+    let ends_trial = sub.trial_until > 0 && sub.periods_charged == 0;
+    // A late charge within grace pays for the original period, so the anchor does not drift.
+    // An anchored subscription never drifts: it pays for the anchor period it falls in.
+    // An early charge within the tolerance is billed at `next_allowed` for the same reason.
+    sub.last_payment_timestamp = if anchor.is_some() {
+        crate::anchor::period_start(&sub, billed_at)
+    } else if in_grace {
+        next_allowed
+    } else {
+        billed_at
+    };
+    let old_interval = sub.interval_seconds;
+    if sub.next_interval_seconds > 0 {
+        // The period just paid for runs at the new length; re-key replay protection to match.
+        sub.interval_seconds = sub.next_interval_seconds;
+        sub.next_interval_seconds = 0;
+        period_index = sub.last_payment_timestamp / sub.interval_seconds;
+    }
+    let old_quantity = sub.quantity;
+    if sub.pending_quantity > 0 {
+        sub.quantity = sub.pending_quantity;
+        sub.pending_quantity = 0;
+    }
+    if sub.discount_periods_left > 0 {
+        sub.discount_periods_left -= 1;
+        if sub.discount_periods_left == 0 {
+            sub.discount = CouponKind::Percent(0);
+        }
+    }
+    sub.period_units_used = 0;
+    sub.usage_charged_this_period = 0;
+    sub.failed_charge_count = 0;
+    // A partial charge resets this again through the `InsufficientBalance` transition.
+    sub.loyalty.streak = sub.loyalty.streak.saturating_add(1);
+    sub.periods_charged = sub.periods_charged.checked_add(1).ok_or(Error::Overflow)?;
+    let completes = sub.max_periods > 0 && sub.periods_charged >= sub.max_periods;
+    let previous_status = sub.status.clone();
+    if completes {
+        apply_transition(&mut sub, SubscriptionStatus::Completed, now)?;
+    } else if partial {
+        apply_transition(&mut sub, SubscriptionStatus::InsufficientBalance, now)?;
+    }
+    let keeper_reward = match &keeper {
+        Some(_) => keeper_fee(env, charged),
+        None => 0,
+    };
+    let after_keeper = charged.checked_sub(keeper_reward).ok_or(Error::Overflow)?;
+    let referral_fee = crate::referral::take(env, subscription_id, &sub.token, after_keeper)?;
+    let merchant_share = after_keeper
+        .checked_sub(referral_fee)
+        .ok_or(Error::Overflow)?;
+    credit_merchant_balance(env, &sub.merchant, &sub.token, merchant_share)?;
+    crate::trial_bond::convert(env, subscription_id, &mut sub)?;
+    save_subscription(env, subscription_id, &mut sub)?;
+    if completes {
+        release_live_slot(env, subscription_id, &sub);
+    }
+
+    set_entry(env, &DataKey::ChargedPeriod(subscription_id), &period_index);
+    env.storage().temporary().set(
+        &DataKeyExt::LastChargeLedger(subscription_id),
+        &env.ledger().sequence(),
+    );
+    if let Some(k) = idempotency_key {
+        crate::stored_sub::set_entry(env, &DataKey::IdemKey(subscription_id), &k);
+    }
+    let charge_seq = record_charge(env, subscription_id, charged, ChargeKind::Periodic);
+    crate::renewal::consume(env, subscription_id);
+    crate::dispute::record_charge(env, subscription_id, sub.periods_charged, merchant_share);
+
+    pull.execute(env);
+    if let Some(keeper) = keeper.as_ref().filter(|_| keeper_reward > 0) {
+        token::Client::new(env, &sub.token).transfer(
+            &env.current_contract_address(),
+            keeper,
+            &keeper_reward,
+        );
+    }
+
+    env.events().publish(
+        crate::notify::topics(
+            env,
+            subscription_id,
+            (symbol_short!("charged"), subscription_id, charge_seq),
+        ),
+        SubscriptionChargedEvent {
+            subscription_id,
+            merchant: sub.merchant.clone(),
+            amount: charged,
+            prepaid_balance: sub.prepaid_balance,
+            period_seq: sub.periods_charged,
+            keeper_reward,
+            quantity: sub.quantity,
+            list_amount: list,
+            referral_fee,
+        },
+    );
+    if sub.interval_seconds != old_interval {
+        env.events().publish(
+            (symbol_short!("interval"), subscription_id),
+            (old_interval, sub.interval_seconds),
+        );
+    }
+    if sub.quantity != old_quantity {
+        env.events().publish(
+            (Symbol::new(env, "quantity_applied"), subscription_id),
+            (old_quantity, sub.quantity),
+        );
+    }
+    if partial {
+        env.events().publish(
+            (symbol_short!("partial"), subscription_id),
+            PartialChargeEvent {
+                subscription_id,
+                amount: charged,
+                shortfall,
+                arrears: sub.arrears,
+                prepaid_balance: sub.prepaid_balance,
+                previous_status: previous_status.clone(),
+                new_status: sub.status.clone(),
+            },
+        );
+    }
+    if ends_trial {
+        env.events().publish(
+            (symbol_short!("trial_end"), subscription_id),
+            (sub.trial_until, now),
+        );
+    }
+    if completes {
+        env.events().publish(
+            (symbol_short!("completed"), subscription_id),
+            SubscriptionCompletedEvent {
+                subscription_id,
+                periods_charged: sub.periods_charged,
+                prepaid_balance: sub.prepaid_balance,
+                previous_status,
+                new_status: sub.status.clone(),
+            },
+        );
+    }
+
+    // Last, after every write: the callback sees the charge fully applied.
+    notify_charge_callback(env, subscription_id, &sub, charged);
+
+    Ok(charge_receipt(&sub, charged, 1, charge_seq))
+}
+
+/// Time an interval charge at `now` is billed at: the due time when `now` falls
+/// within the early-charge tolerance before it, otherwise `now`. The tolerance
+/// is capped at half the interval so the next period is never already in reach.
+fn billed_at(env: &Env, sub: &Subscription, now: u64) -> u64 {
+    let due = sub
+        .last_payment_timestamp
+        .saturating_add(sub.interval_seconds);
+    let tolerance = get_charge_tolerance(env).min(sub.interval_seconds / 2);
+    if now < due && now >= due.saturating_sub(tolerance) {
+        due
+    } else {
+        now
+    }
+}
+
+/// Invoke the merchant's `on_charge(subscription_id, subscriber, amount)`.
+///
+/// A failing optional callback is reported with `cb_failed` and ignored. A
+/// failing required one aborts the whole invocation with `CallbackFailed`
+/// rather than returning it, since `batch_charge` does not roll back the
+/// writes of an item that errors.
+fn notify_charge_callback(env: &Env, subscription_id: u64, sub: &Subscription, amount: i128) {
+    let Some(callback) = sub.charge_callback.as_ref() else {
+        return;
+    };
+    let args = (subscription_id, sub.subscriber.clone(), amount).into_val(env);
+    let result =
+        env.try_invoke_contract::<Val, InvokeError>(callback, &Symbol::new(env, "on_charge"), args);
+    if matches!(result, Ok(Ok(_))) {
+        return;
+    }
+    if sub.callback_required {
+        panic_with_error!(env, Error::CallbackFailed);
     }
+    env.events().publish(
+        (symbol_short!("cb_failed"), subscription_id),
+        callback.clone(),
+    );
+}
+
+/// Interval-charge arithmetic for `sub` at `now`, shared by [`charge_one`] and
+/// [`preview_charge`] so the preview cannot drift from the real charge.
+struct ChargeQuote {
+    next_allowed: u64,
+    grace_deadline: u64,
+    in_grace: bool,
+    /// Partial mode takes whatever is left from an Active subscription.
+    partial: bool,
+    /// The charge can be collected, in full or (with `partial`) in part.
+    covered: bool,
+    /// Full price of the period: `amount` times the quantity being billed.
+    list: i128,
+    /// `list` after any coupon discount.
+    due: i128,
+    /// What would be collected when `covered`; otherwise `due`.
+    amount: i128,
+}
 
+fn quote_charge(
+    env: &Env,
+    sub: &Subscription,
+    anchor: Option<&BillingAnchor>,
+    now: u64,
+) -> Result<ChargeQuote, Error> {
     let next_allowed = sub
         .last_payment_timestamp
         .checked_add(sub.interval_seconds)
         .ok_or(Error::Overflow)?;
-    if now < next_allowed {
-        return Err(Error::IntervalNotElapsed);
+    let grace_seconds = get_grace_seconds(env);
+    let grace_deadline = next_allowed.saturating_add(grace_seconds);
+    let list = crate::anchor::prorate(anchor, sub, period_amount(sub)?)?;
+    let mut due = if sub.discount_periods_left > 0 {
+        apply_discount(&sub.discount, list)?
+    } else {
+        list
+    };
+    if loyalty_active(sub) {
+        due = apply_discount(&CouponKind::Percent(sub.loyalty.discount_bps), due)?;
+    }
+    let available = available_funds(env, sub);
+    let partial = available < due
+        && sub.allow_partial_charge
+        && sub.funding_mode != FundingMode::Hybrid
+        && available > 0
+        && sub.status == SubscriptionStatus::Active;
+    let covered = available >= due || partial;
+    Ok(ChargeQuote {
+        next_allowed,
+        grace_deadline,
+        in_grace: grace_seconds > 0 && now <= grace_deadline,
+        partial,
+        covered,
+        list,
+        due,
+        amount: if covered { due.min(available) } else { due },
+    })
+}
+
+/// The keeper is paid out of the charge, never more than was collected.
+fn keeper_fee(env: &Env, charged: i128) -> i128 {
+    get_keeper_reward(env).min(charged)
+}
+
+fn period_charged(env: &Env, subscription_id: u64, period_index: u64) -> bool {
+    get_entry::<_, u64>(env, &DataKey::ChargedPeriod(subscription_id))
+        .is_some_and(|stored| period_index <= stored)
+}
+
+/// A periodic charge of this subscription already ran in the current ledger.
+fn charged_this_ledger(env: &Env, subscription_id: u64) -> bool {
+    env.storage()
+        .temporary()
+        .get::<_, u32>(&DataKeyExt::LastChargeLedger(subscription_id))
+        == Some(env.ledger().sequence())
+}
+
+/// Dry run of `charge_subscription` (with a keeper) at the current ledger
+/// time. Reads only; writes nothing and emits nothing.
+pub fn preview_charge(env: &Env, subscription_id: u64) -> Result<ChargePreview, Error> {
+    let mut sub = get_subscription(env, subscription_id)?;
+    let now = env.ledger().timestamp();
+    let billed_at = billed_at(env, &sub, now);
+    // A fiat-priced subscription is re-quoted only once the period is due.
+    let period_due = sub
+        .last_payment_timestamp
+        .saturating_add(sub.interval_seconds);
+    if billed_at >= period_due {
+        crate::oracle::reprice(env, subscription_id, &mut sub)?;
     }
+    let anchor = crate::anchor::get(env, subscription_id);
+    let quote = quote_charge(env, &sub, anchor.as_ref(), billed_at)?;
+    let interval_elapsed = billed_at >= quote.next_allowed;
+    let due = matches!(
+        sub.status,
+        SubscriptionStatus::Active | SubscriptionStatus::InsufficientBalance
+    ) && now >= sub.trial_until
+        && !(sub.cancel_at > 0 && now >= sub.cancel_at)
+        && interval_elapsed
+        && !period_charged(
+            env,
+            subscription_id,
+            crate::anchor::period_index(anchor.is_some(), &sub, billed_at),
+        );
+    let fee = if due && quote.covered {
+        keeper_fee(env, quote.amount)
+    } else {
+        0
+    };
+    Ok(ChargePreview {
+        interval_elapsed,
+        periods_due: u32::from(due),
+        amount: if due { quote.amount } else { 0 },
+        fee,
+        balance_sufficient: quote.covered,
+        partial: due && quote.partial,
+        will_succeed: due
+            && quote.covered
+            && crate::renewal::is_approved(env, subscription_id, &sub)
+            && crate::lifetime_cap::check(env, subscription_id, quote.due).is_ok()
+            && sub.status == SubscriptionStatus::Active
+            && !is_contract_paused(env),
+    })
+}
+
+/// Change the seat count, billing an increase for the rest of the current
+/// period right away. Subscriber only.
+///
+/// An increase is charged [`prorated_difference`] from the subscription's
+/// funds and applies immediately; the billing anchor does not move. If the
+/// funds cannot cover it, nothing changes. A decrease credits nothing and is
+/// staged like `update_quantity`. Returns the amount charged.
+pub fn update_quantity_prorated_one(
+    env: &Env,
+    subscription_id: u64,
+    subscriber: Address,
+    new_quantity: u32,
+) -> Result<i128, Error> {
+    subscriber.require_auth();
 
-    if sub.prepaid_balance < sub.amount {
-        validate_status_transition(&sub.status, &SubscriptionStatus::InsufficientBalance)?;
-        sub.status = SubscriptionStatus::InsufficientBalance;
-        env.storage()
-            .instance()
-            .set(&DataKey::Sub(subscription_id), &sub);
+    let mut sub = load_subscription(env, subscription_id)?;
+    if subscriber != sub.subscriber {
+        return Err(Error::Unauthorized);
+    }
+    if new_quantity <= sub.quantity {
+        stage_quantity(env, subscription_id, &mut sub, new_quantity)?;
+        return Ok(0);
+    }
+    require_not_paused(env)?;
+    if sub.status != SubscriptionStatus::Active {
+        return Err(Error::NotActive);
+    }
+
+    let prorated = prorated_difference(&sub, new_quantity, env.ledger().timestamp())?;
+    if prorated > available_funds(env, &sub) {
         return Err(Error::InsufficientBalance);
     }
+    crate::lifetime_cap::check(env, subscription_id, prorated)?;
+    let pull = take_funds(env, subscription_id, &mut sub, prorated)?;
+    if prorated > 0 {
+        credit_merchant_balance(env, &sub.merchant, &sub.token, prorated)?;
+    }
+    let old_quantity = sub.quantity;
+    sub.quantity = new_quantity;
+    sub.pending_quantity = 0;
+    save_subscription(env, subscription_id, &mut sub)?;
+    let charge_seq = if prorated > 0 {
+        record_charge(env, subscription_id, prorated, ChargeKind::Proration)
+    } else {
+        0
+    };
+    pull.execute(env);
 
-    sub.prepaid_balance = sub
-        .prepaid_balance
-        .checked_sub(sub.amount)
+    env.events().publish(
+        (symbol_short!("prorated"), subscription_id, charge_seq),
+        (old_quantity, new_quantity, prorated),
+    );
+    Ok(prorated)
+}
+
+/// `(new_total - old_total) * remaining_seconds / interval_seconds`, rounded
+/// toward zero, where the totals are `amount` times the current and new quantity and
+/// `remaining_seconds` is what is left of the paid-for period. Zero before the
+/// first period starts (trial) and once it has ended.
+pub(crate) fn prorated_difference(
+    sub: &Subscription,
+    new_quantity: u32,
+    now: u64,
+) -> Result<i128, Error> {
+    let old_total = sub
+        .amount
+        .checked_mul(i128::from(sub.quantity))
         .ok_or(Error::Overflow)?;
-    sub.last_payment_timestamp = now;
-    env.storage()
-        .instance()
-        .set(&DataKey::Sub(subscription_id), &sub);
+    let new_total = sub
+        .amount
+        .checked_mul(i128::from(new_quantity))
+        .ok_or(Error::Overflow)?;
+    let period_end = sub
+        .last_payment_timestamp
+        .checked_add(sub.interval_seconds)
+        .ok_or(Error::Overflow)?;
+    let remaining = if now < sub.last_payment_timestamp {
+        0
+    } else {
+        period_end.saturating_sub(now)
+    };
+    let diff = new_total.checked_sub(old_total).ok_or(Error::Overflow)?;
+    crate::math::prorate(diff, remaining, sub.interval_seconds)
+}
 
-    env.storage()
-        .instance()
-        .set(&DataKey::ChargedPeriod(subscription_id), &period_index);
-    if let Some(k) = idempotency_key {
-        env.storage()
-            .instance()
-            .set(&DataKey::IdemKey(subscription_id), &k);
+fn charge_receipt(
+    sub: &Subscription,
+    amount_charged: i128,
+    periods_settled: u32,
+    charge_seq: u32,
+) -> ChargeReceipt {
+    ChargeReceipt {
+        amount_charged,
+        new_prepaid_balance: sub.prepaid_balance,
+        next_charge_at: compute_next_charge_info(sub).next_charge_timestamp,
+        periods_settled,
+        quantity: sub.quantity,
+        status: sub.status.clone(),
+        charge_seq,
+        failure_code: 0,
+    }
+}
+
+/// Receipt for a charge that failed with `error`, whose effects must persist.
+fn failed_receipt(sub: &Subscription, error: Error) -> ChargeReceipt {
+    ChargeReceipt {
+        failure_code: error.to_code(),
+        ..charge_receipt(sub, 0, 0, 0)
+    }
+}
+
+/// Pay down arrears from the prepaid balance (or wallet, for direct debit). Subscriber only.
+///
+/// Settles `min(arrears, available funds)` to the merchant and returns the
+/// amount settled. Once arrears reach zero the subscription can be resumed.
+pub fn settle_arrears_one(
+    env: &Env,
+    subscription_id: u64,
+    subscriber: soroban_sdk::Address,
+) -> Result<i128, Error> {
+    subscriber.require_auth();
+    require_not_paused(env)?;
+
+    let mut sub = load_subscription(env, subscription_id)?;
+    if subscriber != sub.subscriber {
+        return Err(Error::Unauthorized);
+    }
+    if sub.arrears <= 0 {
+        return Err(Error::InvalidAmount);
+    }
+    let available = available_funds(env, &sub);
+    if available <= 0 {
+        return Err(Error::InsufficientBalance);
     }
 
+    let settled = sub.arrears.min(available);
+    crate::lifetime_cap::check(env, subscription_id, settled)?;
+    let pull = take_funds(env, subscription_id, &mut sub, settled)?;
+    sub.arrears = sub.arrears.checked_sub(settled).ok_or(Error::Overflow)?;
+    credit_merchant_balance(env, &sub.merchant, &sub.token, settled)?;
+    save_subscription(env, subscription_id, &mut sub)?;
+    let charge_seq = record_charge(env, subscription_id, settled, ChargeKind::Periodic);
+    pull.execute(env);
+
     env.events().publish(
-        (symbol_short!("charged"),),
-        SubscriptionChargedEvent {
+        (symbol_short!("settled"), subscription_id, charge_seq),
+        ArrearsSettledEvent {
             subscription_id,
-            merchant: sub.merchant.clone(),
-            amount: sub.amount,
+            amount: settled,
+            arrears: sub.arrears,
+            prepaid_balance: sub.prepaid_balance,
         },
     );
+    Ok(settled)
+}
 
-    Ok(())
+/// Dunning: count a charge that failed for lack of balance.
+///
+/// Below the configured `MaxFailedCharges` the subscription moves to (or stays
+/// in) `InsufficientBalance`. On reaching it the subscription is cancelled,
+/// `dunn_end` is emitted with `(failed_charge_count, now)`, and the billing
+/// engine should stop retrying. Either way the failure comes back as a receipt
+/// with `failure_code` set rather than an error, which Soroban would roll back.
+fn record_failed_charge(
+    env: &Env,
+    subscription_id: u64,
+    mut sub: LoadedSubscription,
+    now: u64,
+) -> Result<ChargeReceipt, Error> {
+    sub.failed_charge_count = sub.failed_charge_count.saturating_add(1);
+    sub.loyalty.streak = 0;
+    let max_failed: u32 = env
+        .storage()
+        .instance()
+        .get(&DataKey::MaxFailedCharges)
+        .unwrap_or(0);
+
+    if max_failed > 0 && sub.failed_charge_count >= max_failed {
+        let previous_status = apply_transition(&mut sub, SubscriptionStatus::Cancelled, now)?;
+        save_subscription(env, subscription_id, &mut sub)?;
+        release_live_slot(env, subscription_id, &sub);
+        crate::trial_bond::settle(env, subscription_id, &sub)?;
+        env.events().publish(
+            crate::notify::topics(
+                env,
+                subscription_id,
+                (symbol_short!("dunn_end"), subscription_id),
+            ),
+            DunningExhaustedEvent {
+                subscription_id,
+                failed_charge_count: sub.failed_charge_count,
+                prepaid_balance: sub.prepaid_balance,
+                previous_status,
+                new_status: sub.status.clone(),
+                timestamp: now,
+            },
+        );
+        return Ok(failed_receipt(&sub, Error::InsufficientBalance));
+    }
+
+    let previous_status = apply_transition(&mut sub, SubscriptionStatus::InsufficientBalance, now)?;
+    save_subscription(env, subscription_id, &mut sub)?;
+    if previous_status != sub.status {
+        publish_insufficient_balance(env, subscription_id, &sub, previous_status, now);
+    }
+    Ok(failed_receipt(&sub, Error::InsufficientBalance))
 }
 
 /// Debit a metered `usage_amount` from a subscription's prepaid balance.
 ///
 /// Shared safety checks:
 /// * Subscription must exist (`NotFound`).
-/// * Subscription must be `Active` (`NotActive`).
+/// * Subscription must be `Active`, or `Paused` with usage while paused
+///   agreed (`NotActive`).
 /// * `usage_enabled` must be `true` (`UsageNotEnabled`).
 /// * `usage_amount` must be positive (`InvalidAmount`).
-/// * `prepaid_balance >= usage_amount` (`InsufficientPrepaidBalance`).
+/// * This period's usage must stay within `usage_cap_per_period` (`UsageCapExceeded`).
+/// * `prepaid_balance >= usage_amount` (`InsufficientBalance`).
 ///
-/// On success the prepaid balance is reduced.  If the balance reaches zero
-/// the subscription transitions to `InsufficientBalance`, blocking further
+/// On success the prepaid balance is reduced.  If the balance reaches zero,
+/// or falls below the admin dust threshold and one period's amount, the
+/// subscription transitions to `InsufficientBalance`, blocking further
 /// charges until the subscriber tops up.
-pub fn charge_usage_one(env: &Env, subscription_id: u32, usage_amount: i128) -> Result<(), Error> {
-    let mut sub = get_subscription(env, subscription_id)?;
+///
+/// A repeated `usage_id` does not debit again; its receipt reports the
+/// originally charged amount against the current subscription state.
+pub fn charge_usage_one(
+    env: &Env,
+    subscription_id: u64,
+    usage_amount: i128,
+    usage_id: BytesN<32>,
+) -> Result<UsageChargeReceipt, Error> {
+    require_not_paused(env)?;
+    let key = DataKey::UsageId(subscription_id, usage_id);
+    if let Some(charged) = env.storage().temporary().get::<_, i128>(&key) {
+        let sub = get_subscription(env, subscription_id)?;
+        return Ok(usage_receipt(&sub, charged, 0));
+    }
+    let sub = load_subscription(env, subscription_id)?;
+    require_unattested(env, &sub)?;
+    let (receipt, pull) = debit_usage(env, subscription_id, sub, usage_amount, false)?;
+    remember_usage_id(env, &key, usage_amount);
+    pull.execute(env);
+    env.events().publish(
+        (symbol_short!("usage"), subscription_id, receipt.charge_seq),
+        UsageChargedEvent {
+            subscription_id,
+            units: 0,
+            amount: usage_amount,
+            prepaid_balance: receipt.new_prepaid_balance,
+        },
+    );
+    Ok(receipt)
+}
 
-    if sub.status != SubscriptionStatus::Active {
-        return Err(Error::NotActive);
+fn usage_receipt(sub: &Subscription, amount_charged: i128, charge_seq: u32) -> UsageChargeReceipt {
+    UsageChargeReceipt {
+        amount_charged,
+        new_prepaid_balance: sub.prepaid_balance,
+        usage_charged_this_period: sub.usage_charged_this_period,
+        status: sub.status.clone(),
+        charge_seq,
     }
+}
+
+/// Debit `units` of metered usage from a subscription's prepaid balance.
+///
+/// Priced at the flat `usage_rate`, or walked across the subscription's
+/// [`UsageTier`]s when any are set. Tiers are applied to the units already
+/// used this period, so a call may straddle a breakpoint.
+///
+/// Same checks as [`charge_usage_one`], plus:
+/// * `units` must be positive and a price must be set (`InvalidAmount`).
+/// * The computed amount must fit in `i128` (`Overflow`).
+///
+/// Emits `usage_units` with both the unit count and the computed amount so
+/// invoices reconcile exactly. Returns a receipt with the computed amount; a
+/// repeated `usage_id` reports the originally charged amount without
+/// debiting again, as [`charge_usage_one`] does.
+pub fn charge_usage_units_one(
+    env: &Env,
+    subscription_id: u64,
+    units: u64,
+    usage_id: BytesN<32>,
+) -> Result<UsageChargeReceipt, Error> {
+    require_not_paused(env)?;
+    let key = DataKey::UsageId(subscription_id, usage_id);
+    if let Some(charged) = env.storage().temporary().get::<_, i128>(&key) {
+        let sub = get_subscription(env, subscription_id)?;
+        return Ok(usage_receipt(&sub, charged, 0));
+    }
+    let mut sub = load_subscription(env, subscription_id)?;
+    require_unattested(env, &sub)?;
+    if units == 0 {
+        return Err(Error::InvalidAmount);
+    }
+
+    let tiers: Vec<UsageTier> =
+        crate::stored_sub::get_optional(env, subscription_id, Optional::UsageTiers)
+            .unwrap_or(Vec::new(env));
+    let usage_amount = if tiers.is_empty() {
+        if sub.usage_rate <= 0 {
+            return Err(Error::InvalidAmount);
+        }
+        sub.usage_rate
+            .checked_mul(i128::from(units))
+            .ok_or(Error::Overflow)?
+    } else {
+        tiered_amount(&tiers, sub.period_units_used, units)?
+    };
+    sub.period_units_used = sub
+        .period_units_used
+        .checked_add(units)
+        .ok_or(Error::Overflow)?;
+
+    let (receipt, pull) = debit_usage(env, subscription_id, sub, usage_amount, false)?;
+    remember_usage_id(env, &key, usage_amount);
+    pull.execute(env);
+    let prepaid_balance = receipt.new_prepaid_balance;
+    env.events().publish(
+        (
+            Symbol::new(env, "usage_units"),
+            subscription_id,
+            receipt.charge_seq,
+        ),
+        UsageChargedEvent {
+            subscription_id,
+            units,
+            amount: usage_amount,
+            prepaid_balance,
+        },
+    );
+    Ok(receipt)
+}
+
+fn remember_usage_id(env: &Env, key: &DataKey, amount: i128) {
+    let storage = env.storage().temporary();
+    storage.set(key, &amount);
+    storage.extend_ttl(key, USAGE_ID_TTL_LEDGERS, USAGE_ID_TTL_LEDGERS);
+}
+
+/// Price units `(used, used + units]` against sorted tiers. Units past the
+/// last breakpoint are billed at the last tier's rate.
+fn tiered_amount(tiers: &Vec<UsageTier>, used: u64, units: u64) -> Result<i128, Error> {
+    let end = used.checked_add(units).ok_or(Error::Overflow)?;
+    let mut cursor = used;
+    let mut total: i128 = 0;
+    let last = tiers.len() - 1;
+    for (i, tier) in tiers.iter().enumerate() {
+        if cursor >= end {
+            break;
+        }
+        let ceiling = if i as u32 == last {
+            end
+        } else {
+            tier.up_to.min(end)
+        };
+        if ceiling <= cursor {
+            continue;
+        }
+        let cost = tier
+            .rate
+            .checked_mul(i128::from(ceiling - cursor))
+            .ok_or(Error::Overflow)?;
+        total = total.checked_add(cost).ok_or(Error::Overflow)?;
+        cursor = ceiling;
+    }
+    Ok(total)
+}
+
+/// Debit metered usage. The receipt's `amount_charged` is what was actually
+/// collected.
+///
+/// Direct usage calls (`settled = false`) count against the rate limit and
+/// must be covered in full. Leaves of a posted usage root (`settled = true`)
+/// were already rate-limited off-chain and follow the partial-charge rules
+/// instead: with `allow_partial_charge` whatever is available is collected,
+/// the rest goes to `arrears`, and the subscription drops to
+/// `InsufficientBalance`.
+///
+/// The returned [`WalletPull`] must be executed once the caller's own writes
+/// (usage id, leaf marker) are done.
+pub(crate) fn debit_usage(
+    env: &Env,
+    subscription_id: u64,
+    mut sub: LoadedSubscription,
+    usage_amount: i128,
+    settled: bool,
+) -> Result<(UsageChargeReceipt, WalletPull), Error> {
+    crate::paused_usage::require_usage_status(env, subscription_id, &sub)?;
 
     if !sub.usage_enabled {
         return Err(Error::UsageNotEnabled);
     }
 
+    if !sub.usage_during_trial && env.ledger().timestamp() < sub.trial_until {
+        return Err(Error::InTrialPeriod);
+    }
+
     if usage_amount <= 0 {
         return Err(Error::InvalidAmount);
     }
+    if !settled {
+        count_usage_call(env, &mut sub)?;
+    }
+
+    let period_total = sub
+        .usage_charged_this_period
+        .checked_add(usage_amount)
+        .ok_or(Error::Overflow)?;
+    if sub.usage_cap_per_period > 0 && period_total > sub.usage_cap_per_period {
+        return Err(Error::UsageCapExceeded);
+    }
+
+    let available = available_funds(env, &sub);
+    let partial = settled
+        && sub.allow_partial_charge
+        && sub.funding_mode != FundingMode::Hybrid
+        && available > 0;
+    if available < usage_amount && !partial {
+        return Err(Error::InsufficientBalance);
+    }
+    crate::lifetime_cap::check(env, subscription_id, usage_amount)?;
+
+    let charged = usage_amount.min(available);
+    let shortfall = usage_amount.checked_sub(charged).ok_or(Error::Overflow)?;
+    let pull = take_funds(env, subscription_id, &mut sub, charged)?;
+    sub.usage_charged_this_period = period_total;
+    sub.arrears = sub.arrears.checked_add(shortfall).ok_or(Error::Overflow)?;
+
+    let drained = shortfall > 0 || is_dust(env, &sub)?;
+    let now = env.ledger().timestamp();
+    let previous_status = sub.status.clone();
+    if drained {
+        apply_transition(&mut sub, SubscriptionStatus::InsufficientBalance, now)?;
+    }
+
+    credit_merchant_balance(env, &sub.merchant, &sub.token, charged)?;
+    save_subscription(env, subscription_id, &mut sub)?;
+    let charge_seq = record_charge(env, subscription_id, charged, ChargeKind::Usage);
+    if shortfall > 0 {
+        env.events().publish(
+            (symbol_short!("partial"), subscription_id),
+            PartialChargeEvent {
+                subscription_id,
+                amount: charged,
+                shortfall,
+                arrears: sub.arrears,
+                prepaid_balance: sub.prepaid_balance,
+                previous_status,
+                new_status: sub.status.clone(),
+            },
+        );
+    } else if drained {
+        publish_insufficient_balance(env, subscription_id, &sub, previous_status, now);
+    }
+    Ok((usage_receipt(&sub, charged, charge_seq), pull))
+}
+
+/// A prepaid balance too small to be useful: empty, or below both the admin
+/// dust threshold and one period's amount.
+fn is_dust(env: &Env, sub: &Subscription) -> Result<bool, Error> {
+    if sub.funding_mode != FundingMode::Prepaid {
+        return Ok(false);
+    }
+    let balance = sub.prepaid_balance;
+    Ok(balance == 0 || (balance < get_dust_threshold(env) && balance < period_amount(sub)?))
+}
+
+/// Count one usage charge against the admin rate limit. Windows are fixed and
+/// aligned to `usage_window_seconds`; once the current one has ended the start
+/// jumps forward by whole windows and the count resets.
+fn count_usage_call(env: &Env, sub: &mut Subscription) -> Result<(), Error> {
+    let (max_charges, window) = get_usage_rate_limit(env);
+    if max_charges == 0 || window == 0 {
+        return Ok(());
+    }
+    let now = env.ledger().timestamp();
+    let elapsed = now.saturating_sub(sub.usage_window_start);
+    if elapsed >= window {
+        sub.usage_window_start = now - elapsed % window;
+        sub.usage_window_count = 0;
+    }
+    if sub.usage_window_count >= max_charges {
+        return Err(Error::RateLimited);
+    }
+    sub.usage_window_count += 1;
+    Ok(())
+}
+
+/// Unsigned usage is refused once the merchant has registered an attestation key.
+fn require_unattested(env: &Env, sub: &Subscription) -> Result<(), Error> {
+    if get_usage_attestation_key(env, &sub.merchant).is_some() {
+        return Err(Error::InvalidAttestation);
+    }
+    Ok(())
+}
 
-    if sub.prepaid_balance < usage_amount {
-        return Err(Error::InsufficientPrepaidBalance);
+/// Charge usage reported and signed by the merchant's attestation key.
+///
+/// The signature covers the XDR of `(contract_id, subscription_id,
+/// usage_amount, nonce)`. `nonce` must exceed the last accepted one, which
+/// also makes resubmissions harmless. Missing key or stale nonce fail with
+/// `InvalidAttestation`. A signature that does not verify cannot: the SDK's
+/// `ed25519_verify` returns nothing and traps the host instead, so the call
+/// fails with an invoke error and no contract code.
+pub fn charge_usage_attested_one(
+    env: &Env,
+    subscription_id: u64,
+    usage_amount: i128,
+    nonce: u64,
+    signature: BytesN<64>,
+) -> Result<UsageChargeReceipt, Error> {
+    require_not_paused(env)?;
+    let mut sub = load_subscription(env, subscription_id)?;
+    let public_key =
+        get_usage_attestation_key(env, &sub.merchant).ok_or(Error::InvalidAttestation)?;
+    if nonce <= sub.last_usage_nonce {
+        return Err(Error::InvalidAttestation);
     }
+    let message = (
+        env.current_contract_address(),
+        subscription_id,
+        usage_amount,
+        nonce,
+    )
+        .to_xdr(env);
+    env.crypto()
+        .ed25519_verify(&public_key, &message, &signature);
+    sub.last_usage_nonce = nonce;
+
+    let (receipt, pull) = debit_usage(env, subscription_id, sub, usage_amount, false)?;
+    pull.execute(env);
+    let prepaid_balance = receipt.new_prepaid_balance;
+    env.events().publish(
+        (symbol_short!("usage"), subscription_id, receipt.charge_seq),
+        UsageChargedEvent {
+            subscription_id,
+            units: 0,
+            amount: usage_amount,
+            prepaid_balance,
+        },
+    );
+    Ok(receipt)
+}
 
+/// What a charge can collect right now: the prepaid balance, the smaller of
+/// the subscriber's allowance to this contract and their wallet balance, or
+/// (for hybrid) both added together.
+fn available_funds(env: &Env, sub: &Subscription) -> i128 {
+    match sub.funding_mode {
+        FundingMode::Prepaid => sub.prepaid_balance,
+        FundingMode::DirectDebit => wallet_funds(env, sub),
+        FundingMode::Hybrid => sub.prepaid_balance.saturating_add(wallet_funds(env, sub)),
+    }
+}
+
+fn wallet_funds(env: &Env, sub: &Subscription) -> i128 {
+    let token_client = token::Client::new(env, &sub.token);
+    let contract = env.current_contract_address();
+    token_client
+        .allowance(&sub.subscriber, &contract)
+        .min(token_client.balance(&sub.subscriber))
+}
+
+/// Debit `amount` (at most [`available_funds`]) from `sub` in memory.
+///
+/// Returns the part that must still come from the subscriber's wallet as a
+/// [`WalletPull`] for the caller to execute after its storage writes.
+/// Emits `low_bal` when the prepaid balance crosses below the subscriber's
+/// threshold. Hybrid subscriptions emit `charge_sources` with `(from_prepaid, from_wallet)`.
+fn take_funds(
+    env: &Env,
+    subscription_id: u64,
+    sub: &mut Subscription,
+    amount: i128,
+) -> Result<WalletPull, Error> {
+    let from_prepaid = match sub.funding_mode {
+        FundingMode::Prepaid | FundingMode::Hybrid => amount.min(sub.prepaid_balance),
+        FundingMode::DirectDebit => 0,
+    };
+    let from_wallet = amount.checked_sub(from_prepaid).ok_or(Error::Overflow)?;
+    if from_wallet > 0 && sub.funding_mode == FundingMode::Prepaid {
+        return Err(Error::InsufficientBalance);
+    }
+    let balance_before = sub.prepaid_balance;
     sub.prepaid_balance = sub
         .prepaid_balance
-        .checked_sub(usage_amount)
+        .checked_sub(from_prepaid)
         .ok_or(Error::Overflow)?;
+    let threshold = sub.low_balance_threshold;
+    if threshold > 0 && balance_before >= threshold && sub.prepaid_balance < threshold {
+        env.events().publish(
+            crate::notify::topics(
+                env,
+                subscription_id,
+                (symbol_short!("low_bal"), subscription_id),
+            ),
+            LowBalanceEvent {
+                subscription_id,
+                prepaid_balance: sub.prepaid_balance,
+                threshold,
+            },
+        );
+    }
+    if sub.funding_mode == FundingMode::Hybrid {
+        env.events().publish(
+            (Symbol::new(env, "charge_sources"), subscription_id),
+            (from_prepaid, from_wallet),
+        );
+    }
+    Ok(WalletPull {
+        token: sub.token.clone(),
+        subscriber: sub.subscriber.clone(),
+        merchant: sub.merchant.clone(),
+        amount: from_wallet,
+        subscription_id,
+        auto_topup: from_prepaid > 0 && crate::auto_topup::is_due(env, subscription_id, sub),
+    })
+}
+
+/// Interaction half of [`take_funds`]: the `transfer_from` still owed from the
+/// subscriber's wallet, followed by the merchant's automatic payout, which
+/// needs those funds in the vault, and the subscriber's auto top-up. Execute
+/// it only after every storage write.
+#[must_use]
+pub(crate) struct WalletPull {
+    token: Address,
+    subscriber: Address,
+    merchant: Address,
+    amount: i128,
+    subscription_id: u64,
+    auto_topup: bool,
+}
 
-    if sub.prepaid_balance == 0 {
-        validate_status_transition(&sub.status, &SubscriptionStatus::InsufficientBalance)?;
-        sub.status = SubscriptionStatus::InsufficientBalance;
+impl WalletPull {
+    pub(crate) fn execute(self, env: &Env) {
+        if self.amount > 0 {
+            let contract = env.current_contract_address();
+            token::Client::new(env, &self.token).transfer_from(
+                &contract,
+                &self.subscriber,
+                &contract,
+                &self.amount,
+            );
+        }
+        crate::merchant::auto_payout(env, &self.merchant, &self.token);
+        if self.auto_topup {
+            crate::auto_topup::run(env, self.subscription_id);
+        }
     }
+}
 
-    env.storage()
-        .instance()
-        .set(&DataKey::Sub(subscription_id), &sub);
-    Ok(())
+fn publish_insufficient_balance(
+    env: &Env,
+    subscription_id: u64,
+    sub: &Subscription,
+    previous_status: SubscriptionStatus,
+    now: u64,
+) {
+    env.events().publish(
+        crate::notify::topics(
+            env,
+            subscription_id,
+            (Symbol::new(env, "insufficient_balance"), subscription_id),
+        ),
+        StatusChangedEvent {
+            subscription_id,
+            prepaid_balance: sub.prepaid_balance,
+            previous_status,
+            new_status: sub.status.clone(),
+            timestamp: now,
+        },
+    );
+}
+
+/// Fold a successful charge into the subscription's lifetime stats and
+/// history. Returns its charge sequence number.
+fn record_charge(env: &Env, subscription_id: u64, amount: i128, kind: ChargeKind) -> u32 {
+    let key = DataKey::SubStats(subscription_id);
+    let mut stats: SubscriptionStats = get_entry(env, &key).unwrap_or_default();
+    if kind == ChargeKind::Usage {
+        stats.total_usage_charged = stats.total_usage_charged.saturating_add(amount);
+    } else {
+        stats.total_charged = stats.total_charged.saturating_add(amount);
+    }
+    stats.charge_count = stats.charge_count.saturating_add(1);
+    stats.last_charge_amount = amount;
+    stats.last_charge_at = env.ledger().timestamp();
+    set_entry(env, &key, &stats);
+    crate::history::push(env, subscription_id, amount, kind, stats.charge_count);
+    stats.charge_count
+}
+
+/// Fold a merchant credit into the subscription's lifetime stats and history.
+pub(crate) fn record_credit(env: &Env, subscription_id: u64, amount: i128) {
+    let key = DataKey::SubStats(subscription_id);
+    let mut stats: SubscriptionStats = get_entry(env, &key).unwrap_or_default();
+    stats.total_credited = stats.total_credited.saturating_add(amount);
+    set_entry(env, &key, &stats);
+    crate::history::push(
+        env,
+        subscription_id,
+        amount.saturating_neg(),
+        ChargeKind::Credit,
+        stats.charge_count,
+    );
 }
@@ -0,0 +1,90 @@
+//! Merchant coupons: a percentage or fixed discount on a subscription's first
+//! interval charges.
+//!
+//! **PRs that only change coupon management should edit this file only.**
+
+use crate::math::{mul_bps_floor, MAX_BPS};
+use crate::ttl::bump_persistent;
+use crate::types::{Coupon, CouponKind, DataKey, Error};
+use soroban_sdk::{symbol_short, Address, BytesN, Env};
+
+pub fn get_coupon(env: &Env, merchant: &Address, code: &BytesN<16>) -> Result<Coupon, Error> {
+    env.storage()
+        .persistent()
+        .get(&DataKey::Coupon(merchant.clone(), code.clone()))
+        .ok_or(Error::NotFound)
+}
+
+pub fn do_create_coupon(
+    env: &Env,
+    merchant: Address,
+    code: BytesN<16>,
+    kind: CouponKind,
+    duration_periods: u32,
+    max_redemptions: u32,
+    expires_at: u64,
+) -> Result<(), Error> {
+    merchant.require_auth();
+    if env
+        .storage()
+        .instance()
+        .has(&DataKey::MerchantRedirect(merchant.clone()))
+    {
+        return Err(Error::InvalidMerchantRotation);
+    }
+    let valid_discount = match kind {
+        CouponKind::Percent(bps) => bps > 0 && bps <= MAX_BPS,
+        CouponKind::Fixed(amount) => amount > 0,
+    };
+    if !valid_discount || duration_periods == 0 {
+        return Err(Error::InvalidAmount);
+    }
+    let key = DataKey::Coupon(merchant.clone(), code.clone());
+    if env.storage().persistent().has(&key) {
+        return Err(Error::CouponInvalid);
+    }
+
+    let coupon = Coupon {
+        kind,
+        duration_periods,
+        max_redemptions,
+        redemptions: 0,
+        expires_at,
+    };
+    env.storage().persistent().set(&key, &coupon);
+    bump_persistent(env, &key);
+    env.events()
+        .publish((symbol_short!("coupon"), merchant), (code, coupon));
+    Ok(())
+}
+
+/// Count one redemption of `code` and return its discount terms.
+///
+/// Fails with `CouponInvalid` for an unknown, expired or exhausted coupon.
+pub fn redeem(env: &Env, merchant: &Address, code: &BytesN<16>, now: u64) -> Result<Coupon, Error> {
+    let key = DataKey::Coupon(merchant.clone(), code.clone());
+    let mut coupon: Coupon = env
+        .storage()
+        .persistent()
+        .get(&key)
+        .ok_or(Error::CouponInvalid)?;
+    let expired = coupon.expires_at > 0 && now >= coupon.expires_at;
+    let exhausted = coupon.max_redemptions > 0 && coupon.redemptions >= coupon.max_redemptions;
+    if expired || exhausted {
+        return Err(Error::CouponInvalid);
+    }
+    coupon.redemptions += 1;
+    env.storage().persistent().set(&key, &coupon);
+    bump_persistent(env, &key);
+    Ok(coupon)
+}
+
+/// `list` after the discount, never below zero. A percentage discount is
+/// rounded down, so the merchant keeps any fraction.
+pub fn apply_discount(kind: &CouponKind, list: i128) -> Result<i128, Error> {
+    let off = match kind {
+        CouponKind::Percent(bps) => mul_bps_floor(list, *bps)?,
+        CouponKind::Fixed(amount) => *amount,
+    };
+    Ok(list.saturating_sub(off).max(0))
+}
@@ -0,0 +1,177 @@
+//! Disputes: open_dispute, resolve_dispute, per-charge records.
+//!
+//! **PRs that only change dispute handling should edit this file only.**
+//!
+//! While a settlement delay is configured, every interval charge is recorded
+//! under its `period_seq` (the subscription's `periods_charged` after the
+//! charge) until its funds are released to the merchant. A subscriber can
+//! contest a recorded charge once, inside that window; the amount is moved
+//! out of the merchant's pending earnings and held until the admin decides.
+
+use crate::admin::{get_settlement_delay, require_admin};
+use crate::merchant::{freeze_merchant_funds, unfreeze_merchant_funds};
+use crate::queries::get_subscription;
+use crate::stats::{load_subscription, save_subscription};
+use crate::types::{
+    ChargeRecord, DataKey, Dispute, DisputeOpenedEvent, DisputeResolvedEvent, DisputeStatus, Error,
+};
+use soroban_sdk::{Address, Env, Symbol};
+
+/// Remember a charge so it can be disputed until its funds are released.
+/// Records live in temporary storage for about the settlement delay.
+pub fn record_charge(env: &Env, subscription_id: u64, period_seq: u32, amount: i128) {
+    let delay = get_settlement_delay(env);
+    if delay == 0 {
+        return;
+    }
+    let key = DataKey::ChargeRecord(subscription_id, period_seq);
+    let record = ChargeRecord {
+        amount,
+        release_at: env.ledger().timestamp().saturating_add(delay),
+        disputed: false,
+    };
+    let storage = env.storage().temporary();
+    storage.set(&key, &record);
+    // ~5s ledgers; the timestamp check, not the TTL, closes the window.
+    let ledgers = u32::try_from(delay / 5 + 1)
+        .unwrap_or(u32::MAX)
+        .min(env.storage().max_ttl());
+    storage.extend_ttl(&key, ledgers, ledgers);
+}
+
+/// Whether charge `period_seq` can still be disputed, or is under dispute.
+pub fn charge_disputable(env: &Env, subscription_id: u64, period_seq: u32) -> bool {
+    env.storage()
+        .temporary()
+        .get::<_, ChargeRecord>(&DataKey::ChargeRecord(subscription_id, period_seq))
+        .is_some_and(|record| record.disputed || env.ledger().timestamp() < record.release_at)
+}
+
+pub fn do_open_dispute(
+    env: &Env,
+    subscription_id: u64,
+    subscriber: Address,
+    period_seq: u32,
+) -> Result<u32, Error> {
+    subscriber.require_auth();
+    let sub = get_subscription(env, subscription_id)?;
+    if subscriber != sub.subscriber {
+        return Err(Error::Unauthorized);
+    }
+
+    let key = DataKey::ChargeRecord(subscription_id, period_seq);
+    let mut record: ChargeRecord = env
+        .storage()
+        .temporary()
+        .get(&key)
+        .ok_or(Error::DisputeWindowClosed)?;
+    if record.disputed {
+        return Err(Error::ChargeAlreadyDisputed);
+    }
+    let now = env.ledger().timestamp();
+    if now >= record.release_at {
+        return Err(Error::DisputeWindowClosed);
+    }
+
+    freeze_merchant_funds(
+        env,
+        &sub.merchant,
+        &sub.token,
+        record.release_at,
+        record.amount,
+    )?;
+    record.disputed = true;
+    env.storage().temporary().set(&key, &record);
+
+    let dispute_id: u32 = env
+        .storage()
+        .instance()
+        .get(&DataKey::NextDisputeId)
+        .unwrap_or(0);
+    let next = dispute_id.checked_add(1).ok_or(Error::Overflow)?;
+    env.storage().instance().set(&DataKey::NextDisputeId, &next);
+    env.storage().instance().set(
+        &DataKey::Dispute(dispute_id),
+        &Dispute {
+            subscription_id,
+            period_seq,
+            amount: record.amount,
+            opened_at: now,
+            status: DisputeStatus::Open,
+        },
+    );
+
+    env.events().publish(
+        (Symbol::new(env, "dispute_opened"), subscription_id),
+        DisputeOpenedEvent {
+            dispute_id,
+            subscription_id,
+            period_seq,
+            merchant: sub.merchant,
+            amount: record.amount,
+        },
+    );
+    Ok(dispute_id)
+}
+
+/// Admin settles an open dispute: `refund` returns the frozen amount to the
+/// subscription's prepaid balance, otherwise it becomes withdrawable by the
+/// merchant straight away.
+pub fn do_resolve_dispute(
+    env: &Env,
+    admin: Address,
+    dispute_id: u32,
+    refund: bool,
+) -> Result<(), Error> {
+    admin.require_auth();
+    let stored = require_admin(env)?;
+    if admin != stored {
+        return Err(Error::Unauthorized);
+    }
+
+    let mut dispute = get_dispute(env, dispute_id)?;
+    if dispute.status != DisputeStatus::Open {
+        return Err(Error::DisputeNotOpen);
+    }
+    let mut sub = load_subscription(env, dispute.subscription_id)?;
+    if refund {
+        sub.prepaid_balance = sub
+            .prepaid_balance
+            .checked_add(dispute.amount)
+            .ok_or(Error::Overflow)?;
+        sub.total_refunded = sub
+            .total_refunded
+            .checked_add(dispute.amount)
+            .ok_or(Error::Overflow)?;
+        save_subscription(env, dispute.subscription_id, &mut sub)?;
+        dispute.status = DisputeStatus::Refunded;
+    } else {
+        unfreeze_merchant_funds(env, &sub.merchant, &sub.token, dispute.amount)?;
+        dispute.status = DisputeStatus::Released;
+    }
+    env.storage()
+        .instance()
+        .set(&DataKey::Dispute(dispute_id), &dispute);
+
+    env.events().publish(
+        (
+            Symbol::new(env, "dispute_resolved"),
+            dispute.subscription_id,
+        ),
+        DisputeResolvedEvent {
+            dispute_id,
+            subscription_id: dispute.subscription_id,
+            amount: dispute.amount,
+            refunded: refund,
+            prepaid_balance: sub.prepaid_balance,
+        },
+    );
+    Ok(())
+}
+
+pub fn get_dispute(env: &Env, dispute_id: u32) -> Result<Dispute, Error> {
+    env.storage()
+        .instance()
+        .get(&DataKey::Dispute(dispute_id))
+        .ok_or(Error::NotFound)
+}
@@ -0,0 +1,57 @@
+//! Dust sweep: the admin moves a few stroops left on an abandoned terminal
+//! subscription to the treasury, so the subscription can be purged.
+//!
+//! **PRs that only change dust sweeping should edit this file only.**
+//!
+//! Only a subscription that has been terminal for longer than the admin's
+//! `dust_sweep_delay` can be swept, and only while its `prepaid_balance` is
+//! at most the sweep threshold, which is capped at
+//! [`MAX_DUST_SWEEP_THRESHOLD`](crate::types::MAX_DUST_SWEEP_THRESHOLD). Until
+//! then the subscriber can still withdraw it. A delay of 0 disables sweeping.
+
+use crate::admin::{get_dust_sweep_delay, get_dust_sweep_threshold, get_treasury, require_admin};
+use crate::state_machine::get_allowed_transitions;
+use crate::stats::{load_subscription, save_subscription};
+use crate::types::Error;
+use soroban_sdk::{symbol_short, token, Address, Env};
+
+/// Zero the balance of `subscription_id` and transfer it to the treasury.
+/// Returns the amount swept.
+pub fn do_sweep_dust(env: &Env, admin: Address, subscription_id: u64) -> Result<i128, Error> {
+    admin.require_auth();
+    if admin != require_admin(env)? {
+        return Err(Error::Unauthorized);
+    }
+    let delay = get_dust_sweep_delay(env);
+    let treasury = get_treasury(env).filter(|_| delay > 0);
+    let Some(treasury) = treasury else {
+        return Err(Error::NotSweepable);
+    };
+
+    let mut sub = load_subscription(env, subscription_id)?;
+    let amount = sub.prepaid_balance;
+    let terminal_for = env
+        .ledger()
+        .timestamp()
+        .saturating_sub(sub.status_changed_at);
+    if !get_allowed_transitions(&sub.status).is_empty()
+        || terminal_for <= delay
+        || amount <= 0
+        || amount > get_dust_sweep_threshold(env)
+    {
+        return Err(Error::NotSweepable);
+    }
+
+    sub.prepaid_balance = 0;
+    save_subscription(env, subscription_id, &mut sub)?;
+    token::Client::new(env, &sub.token).transfer(
+        &env.current_contract_address(),
+        &treasury,
+        &amount,
+    );
+    env.events().publish(
+        (symbol_short!("swept"), subscription_id),
+        (amount, treasury),
+    );
+    Ok(amount)
+}
@@ -0,0 +1,73 @@
+//! Staleness expiry: subscriptions with no deposit or successful charge for
+//! the admin's `staleness_seconds` can be moved to the terminal `Expired`
+//! status by anyone.
+//!
+//! **PRs that only change staleness expiry should edit this file only.**
+
+use crate::queries::{get_subscription, get_subscription_stats};
+use crate::state_machine::apply_transition;
+use crate::stats::{load_subscription, save_subscription};
+use crate::types::{DataKeyExt, Error, StatusChangedEvent, Subscription, SubscriptionStatus};
+use soroban_sdk::{symbol_short, Env};
+
+/// Stamp the subscription's last deposit with the current ledger time.
+pub fn record_deposit(env: &Env, subscription_id: u64) {
+    let key = DataKeyExt::LastDeposit(subscription_id);
+    env.storage()
+        .persistent()
+        .set(&key, &env.ledger().timestamp());
+    crate::ttl::bump_persistent(env, &key);
+}
+
+/// Latest of creation, the last deposit and the last successful charge. The
+/// charge time comes from the lifetime stats the charge path already writes.
+fn last_activity(env: &Env, subscription_id: u64, sub: &Subscription) -> Result<u64, Error> {
+    let last_deposit = env
+        .storage()
+        .persistent()
+        .get(&DataKeyExt::LastDeposit(subscription_id))
+        .unwrap_or(0);
+    let last_charge = get_subscription_stats(env, subscription_id)?.last_charge_at;
+    Ok(sub.created_at.max(last_deposit).max(last_charge))
+}
+
+pub fn remove(env: &Env, subscription_id: u64) {
+    env.storage()
+        .persistent()
+        .remove(&DataKeyExt::LastDeposit(subscription_id));
+}
+
+pub fn get_last_activity(env: &Env, subscription_id: u64) -> Result<u64, Error> {
+    let sub = get_subscription(env, subscription_id)?;
+    last_activity(env, subscription_id, &sub)
+}
+
+/// Permissionless. `NotStale` while expiry is disabled or the subscription had
+/// activity within the window; terminal subscriptions fail the transition.
+/// The prepaid balance is left in place for `withdraw_subscriber_funds`.
+/// Emits `expired`.
+pub fn do_expire_stale(env: &Env, subscription_id: u64) -> Result<(), Error> {
+    let mut sub = load_subscription(env, subscription_id)?;
+    let staleness = crate::admin::get_staleness_seconds(env);
+    let now = env.ledger().timestamp();
+    let stale_at = last_activity(env, subscription_id, &sub)?.saturating_add(staleness);
+    if staleness == 0 || now < stale_at {
+        return Err(Error::NotStale);
+    }
+
+    let previous_status = apply_transition(&mut sub, SubscriptionStatus::Expired, now)?;
+    save_subscription(env, subscription_id, &mut sub)?;
+    crate::subscription::release_live_slot(env, subscription_id, &sub);
+    crate::trial_bond::settle(env, subscription_id, &sub)?;
+    env.events().publish(
+        (symbol_short!("expired"), subscription_id),
+        StatusChangedEvent {
+            subscription_id,
+            prepaid_balance: sub.prepaid_balance,
+            previous_status,
+            new_status: sub.status.clone(),
+            timestamp: now,
+        },
+    );
+    Ok(())
+}
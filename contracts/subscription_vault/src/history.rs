@@ -0,0 +1,53 @@
+//! Charge history: the last [`CHARGE_HISTORY_LEN`] charges per subscription.
+//!
+//! **PRs that only change charge history should edit this file only.**
+//!
+//! Each successful charge, and each merchant credit, appends a
+//! [`ChargeHistoryEntry`] under its own persistent key, so the `Subscription`
+//! record stays small. `seq` is the subscription's lifetime charge count after
+//! the charge, shared by all charge kinds; credits do not advance it. Once full, the oldest entry rolls off; events keep the full
+//! history.
+
+use crate::ttl::bump_persistent;
+use crate::types::{ChargeHistoryEntry, ChargeKind, DataKey, CHARGE_HISTORY_LEN};
+use soroban_sdk::{Env, Vec};
+
+/// Append a charge, evicting the oldest entry when the buffer is full.
+pub fn push(env: &Env, subscription_id: u64, amount: i128, kind: ChargeKind, seq: u32) {
+    let key = DataKey::ChargeHistory(subscription_id);
+    let mut history = get_charge_history(env, subscription_id);
+    while history.len() >= CHARGE_HISTORY_LEN {
+        history.pop_front();
+    }
+    history.push_back(ChargeHistoryEntry {
+        timestamp: env.ledger().timestamp(),
+        amount,
+        kind,
+        seq,
+    });
+    env.storage().persistent().set(&key, &history);
+    bump_persistent(env, &key);
+}
+
+/// Recent charges, oldest first. Empty for unknown or never-charged ids.
+pub fn get_charge_history(env: &Env, subscription_id: u64) -> Vec<ChargeHistoryEntry> {
+    env.storage()
+        .persistent()
+        .get(&DataKey::ChargeHistory(subscription_id))
+        .unwrap_or(Vec::new(env))
+}
+
+/// Keep the history alive alongside its subscription.
+pub fn bump(env: &Env, subscription_id: u64) {
+    let key = DataKey::ChargeHistory(subscription_id);
+    if env.storage().persistent().has(&key) {
+        bump_persistent(env, &key);
+    }
+}
+
+/// Drop the history of a purged subscription.
+pub fn remove(env: &Env, subscription_id: u64) {
+    env.storage()
+        .persistent()
+        .remove(&DataKey::ChargeHistory(subscription_id));
+}
@@ -0,0 +1,98 @@
+//! Subscription import: moving live subscriptions over from an earlier
+//! deployment of the vault without every customer re-subscribing.
+//!
+//! **PRs that only change subscription import should edit this file only.**
+//!
+//! The admin writes each record with its historical fields, so billing picks
+//! up where the old contract left off, and funds the prepaid balance from its
+//! own address. `migration_complete` closes imports permanently.
+
+use crate::admin::require_admin;
+use crate::stats::{save_subscription, LoadedSubscription};
+use crate::subscription::{next_id, require_no_live_subscription, validate_metadata};
+use crate::types::{DataKey, DataKeyExt, Error, Subscription};
+use soroban_sdk::{Address, Env, Symbol, Vec};
+
+pub fn is_complete(env: &Env) -> bool {
+    env.storage().instance().has(&DataKeyExt::MigrationComplete)
+}
+
+fn require_import_admin(env: &Env, admin: &Address) -> Result<(), Error> {
+    admin.require_auth();
+    if *admin != require_admin(env)? {
+        return Err(Error::Unauthorized);
+    }
+    if is_complete(env) {
+        return Err(Error::MigrationComplete);
+    }
+    Ok(())
+}
+
+/// Import `sub` as given, except that its balance becomes exactly
+/// `prepaid_topup`, pulled from `admin`, and `plan_id` is cleared because
+/// plans do not carry over between deployments.
+pub fn do_import_subscription(
+    env: &Env,
+    admin: Address,
+    mut sub: Subscription,
+    prepaid_topup: i128,
+) -> Result<u64, Error> {
+    require_import_admin(env, &admin)?;
+
+    // Same checks as `create_subscription`.
+    crate::admin::require_not_blocked(env, &sub.subscriber)?;
+    crate::admin::require_not_blocked(env, &sub.merchant)?;
+    crate::merchant::require_merchant_accepting(env, &sub.merchant)?;
+    if !crate::admin::is_token_supported(env, &sub.token) {
+        return Err(Error::TokenNotSupported);
+    }
+    crate::admin::require_amount_in_limits(env, sub.amount)?;
+    crate::admin::require_interval_in_limits(env, sub.interval_seconds)?;
+    validate_metadata(&sub.metadata)?;
+    if prepaid_topup < 0 {
+        return Err(Error::InvalidAmount);
+    }
+    // Only live subscriptions are worth moving.
+    if crate::state_machine::get_allowed_transitions(&sub.status).is_empty() {
+        return Err(Error::InvalidStatusTransition);
+    }
+    let pair_key = DataKey::ActivePair(sub.subscriber.clone(), sub.merchant.clone());
+    require_no_live_subscription(env, &pair_key)?;
+
+    sub.prepaid_balance = prepaid_topup;
+    sub.plan_id = 0;
+    let mut sub = LoadedSubscription::new(sub);
+    let id = next_id(env)?;
+    save_subscription(env, id, &mut sub)?;
+    env.storage().instance().set(&pair_key, &id);
+    let key = DataKey::MerchantSubs(sub.merchant.clone());
+    let mut ids: Vec<u64> = env.storage().instance().get(&key).unwrap_or(Vec::new(env));
+    ids.push_back(id);
+    env.storage().instance().set(&key, &ids);
+    // The import counts as activity, so a long-lived record is not stale on arrival.
+    crate::expiry::record_deposit(env, id);
+
+    if prepaid_topup > 0 {
+        soroban_sdk::token::Client::new(env, &sub.token).transfer(
+            &admin,
+            &env.current_contract_address(),
+            &prepaid_topup,
+        );
+    }
+    env.events().publish(
+        (Symbol::new(env, "imported"), id),
+        (sub.subscriber.clone(), sub.merchant.clone(), prepaid_topup),
+    );
+    Ok(id)
+}
+
+/// Permanently close imports. Admin only; a second call fails with `MigrationComplete`.
+pub fn do_migration_complete(env: &Env, admin: Address) -> Result<(), Error> {
+    require_import_admin(env, &admin)?;
+    env.storage()
+        .instance()
+        .set(&DataKeyExt::MigrationComplete, &true);
+    env.events()
+        .publish((Symbol::new(env, "migration_complete"),), admin);
+    Ok(())
+}
@@ -0,0 +1,119 @@
+//! Merchant-initiated invites: the merchant proposes terms, the subscriber
+//! accepts them to create the subscription.
+//!
+//! **PRs that only change invite handling should edit this file only.**
+
+use crate::subscription::do_create_subscription;
+use crate::ttl::bump_persistent;
+use crate::types::{DataKey, DataKeyExt, Error, PendingInvite, SubscriptionOptions};
+use soroban_sdk::{symbol_short, Address, Env};
+
+pub fn get_invite(env: &Env, invite_id: u32) -> Result<PendingInvite, Error> {
+    env.storage()
+        .persistent()
+        .get(&DataKeyExt::Invite(invite_id))
+        .ok_or(Error::NotFound)
+}
+
+pub fn do_create_invite(
+    env: &Env,
+    merchant: Address,
+    subscriber: Address,
+    amount: i128,
+    interval_seconds: u64,
+    usage_enabled: bool,
+    expires_at: u64,
+) -> Result<u32, Error> {
+    merchant.require_auth();
+    if env
+        .storage()
+        .instance()
+        .has(&DataKey::MerchantRedirect(merchant.clone()))
+    {
+        return Err(Error::InvalidMerchantRotation);
+    }
+    crate::admin::require_amount_in_limits(env, amount)?;
+    crate::admin::require_interval_in_limits(env, interval_seconds)?;
+    if expires_at <= env.ledger().timestamp() {
+        return Err(Error::InviteExpired);
+    }
+
+    let invite_id: u32 = env
+        .storage()
+        .instance()
+        .get(&DataKeyExt::NextInviteId)
+        .unwrap_or(1);
+    let next = invite_id.checked_add(1).ok_or(Error::Overflow)?;
+    env.storage()
+        .instance()
+        .set(&DataKeyExt::NextInviteId, &next);
+
+    let invite = PendingInvite {
+        merchant,
+        subscriber,
+        amount,
+        interval_seconds,
+        usage_enabled,
+        expires_at,
+    };
+    let key = DataKeyExt::Invite(invite_id);
+    env.storage().persistent().set(&key, &invite);
+    bump_persistent(env, &key);
+    env.events()
+        .publish((symbol_short!("invite"), invite_id), invite);
+    Ok(invite_id)
+}
+
+/// Turn the invite into a subscription on its terms, funded with
+/// `initial_deposit` under the usual creation rules. The invite is consumed.
+pub fn do_accept_invite(
+    env: &Env,
+    subscriber: Address,
+    invite_id: u32,
+    initial_deposit: i128,
+) -> Result<u64, Error> {
+    let invite = get_invite(env, invite_id)?;
+    if subscriber != invite.subscriber {
+        return Err(Error::Unauthorized);
+    }
+    if env.ledger().timestamp() >= invite.expires_at {
+        return Err(Error::InviteExpired);
+    }
+
+    env.storage()
+        .persistent()
+        .remove(&DataKeyExt::Invite(invite_id));
+    let options = SubscriptionOptions {
+        initial_deposit,
+        ..Default::default()
+    };
+    let id = do_create_subscription(
+        env,
+        subscriber,
+        invite.merchant,
+        invite.amount,
+        invite.interval_seconds,
+        invite.usage_enabled,
+        options,
+    )?;
+    env.events()
+        .publish((symbol_short!("inv_acc"), invite_id), id);
+    Ok(id)
+}
+
+/// Withdraw an invite that has not been accepted. Merchant only.
+pub fn do_revoke_invite(env: &Env, merchant: Address, invite_id: u32) -> Result<(), Error> {
+    merchant.require_auth();
+
+    let invite = get_invite(env, invite_id)?;
+    if merchant != invite.merchant {
+        return Err(Error::Unauthorized);
+    }
+
+    env.storage()
+        .persistent()
+        .remove(&DataKeyExt::Invite(invite_id));
+    env.events()
+        .publish((symbol_short!("inv_rev"), invite_id), ());
+    Ok(())
+}
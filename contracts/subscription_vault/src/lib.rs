@@ -2,20 +2,54 @@
 
 // ── Modules ──────────────────────────────────────────────────────────────────
 mod admin;
+mod anchor;
+mod auto_topup;
+mod bulk_create;
 mod charge_core;
+mod coupon;
+mod delegate;
+mod dispute;
+mod dust_sweep;
+mod expiry;
+mod history;
+mod import;
+mod invite;
+mod lifetime_cap;
+pub mod math;
 mod merchant;
+mod merchant_stats;
+mod multicall;
+mod multisig;
+mod notify;
+mod oracle;
+mod paused_usage;
+mod plan;
 mod queries;
+mod referral;
+mod renewal;
 mod safe_math;
+mod snapshot;
 mod state_machine;
+mod stats;
+mod stored_sub;
 mod subscription;
+mod timelock;
+mod trial_bond;
+mod ttl;
 pub mod types;
+mod usage_batch;
+mod usage_toggle;
+mod viewer;
 
 // ── Re-exports (used by tests and external consumers) ────────────────────────
-pub use state_machine::{can_transition, get_allowed_transitions, validate_status_transition};
+pub use state_machine::{
+    can_transition, get_allowed_transitions, validate_status_transition, ALL_STATUSES,
+};
 pub use types::*;
 
 pub use queries::compute_next_charge_info;
-use soroban_sdk::{contract, contractimpl, Address, Env, Vec};
+use soroban_sdk::{contract, contractimpl, Address, Bytes, BytesN, Env, Symbol, Vec};
+use stats::{save_subscription, LoadedSubscription};
 
 // ── Contract ─────────────────────────────────────────────────────────────────
 
@@ -27,6 +61,7 @@ impl SubscriptionVault {
     // ── Admin / Config ───────────────────────────────────────────────────
 
     /// Initialize the contract: set token address, admin, and minimum top-up.
+    /// Fails with `AlreadyInitialized` if called a second time.
     pub fn init(env: Env, token: Address, admin: Address, min_topup: i128) -> Result<(), Error> {
         admin::do_init(&env, token, admin, min_topup)
     }
@@ -41,185 +76,1898 @@ impl SubscriptionVault {
         admin::get_min_topup(&env)
     }
 
+    /// Total value locked and subscription counts per status. Kept up to date
+    /// on every subscription write, so this is a constant-cost read.
+    pub fn get_stats(env: Env) -> VaultStats {
+        stats::get_stats(&env)
+    }
+
+    /// All admin-settable parameters in one struct, for reconciling config
+    /// event history (`(old, new, admin)` payloads) against live state.
+    pub fn get_config(env: Env) -> Result<ContractConfig, Error> {
+        admin::get_config(&env)
+    }
+
+    /// **ADMIN ONLY**: Set how many consecutive failed balance charges cancel a
+    /// subscription (dunning). 0 disables auto-cancellation.
+    pub fn set_max_failed_charges(env: Env, admin: Address, max: u32) -> Result<(), Error> {
+        admin::do_set_max_failed_charges(&env, admin, max)
+    }
+
+    /// Dunning threshold; 0 when auto-cancellation is disabled.
+    pub fn get_max_failed_charges(env: Env) -> u32 {
+        admin::get_max_failed_charges(&env)
+    }
+
+    /// **ADMIN ONLY**: Set the grace period after a missed due date during
+    /// which an underfunded subscription stays `Active`. 0 disables grace.
+    pub fn set_grace_seconds(env: Env, admin: Address, grace_seconds: u64) -> Result<(), Error> {
+        admin::do_set_grace_seconds(&env, admin, grace_seconds)
+    }
+
+    /// Current grace period in seconds; 0 when disabled.
+    pub fn get_grace_seconds(env: Env) -> u64 {
+        admin::get_grace_seconds(&env)
+    }
+
+    /// **ADMIN ONLY**: Allow at most `max_usage_charges_per_window` usage
+    /// charges per subscription in each `usage_window_seconds` window; excess
+    /// calls fail with `RateLimited`. Either value 0 disables the limit. Works
+    /// alongside the per-period spending cap.
+    pub fn set_usage_rate_limit(
+        env: Env,
+        admin: Address,
+        max_usage_charges_per_window: u32,
+        usage_window_seconds: u64,
+    ) -> Result<(), Error> {
+        admin::do_set_usage_rate_limit(
+            &env,
+            admin,
+            max_usage_charges_per_window,
+            usage_window_seconds,
+        )
+    }
+
+    /// `(max_usage_charges_per_window, usage_window_seconds)`.
+    pub fn get_usage_rate_limit(env: Env) -> (u32, u64) {
+        admin::get_usage_rate_limit(&env)
+    }
+
+    /// Current keeper reward; 0 when disabled. Flat reward, in the
+    /// subscription's token, paid to the caller of each successful
+    /// `charge_subscription`, deducted from the charged amount and capped at
+    /// it. Changed through the timelock (`AdminAction::SetKeeperReward`).
+    pub fn get_keeper_reward(env: Env) -> i128 {
+        admin::get_keeper_reward(&env)
+    }
+
+    /// Referral fee in basis points and number of interval charges it applies
+    /// to, copied onto subscriptions created with a referrer. Changed through
+    /// the timelock (`AdminAction::SetReferralTerms`).
+    pub fn get_referral_terms(env: Env) -> ReferralTerms {
+        admin::get_referral_terms(&env)
+    }
+
+    /// **ADMIN ONLY**: Hold charged funds for `seconds` before the merchant can
+    /// withdraw them, leaving room for disputes and refunds. 0 disables.
+    /// Applies to charges made after the change.
+    pub fn set_settlement_delay(env: Env, admin: Address, seconds: u64) -> Result<(), Error> {
+        admin::do_set_settlement_delay(&env, admin, seconds)
+    }
+
+    /// Current settlement delay in seconds; 0 when disabled.
+    pub fn get_settlement_delay(env: Env) -> u64 {
+        admin::get_settlement_delay(&env)
+    }
+
+    /// **ADMIN ONLY**: Require new subscriptions to be funded for this many
+    /// billing periods at creation (via `SubscriptionOptions::initial_deposit`).
+    /// 0 disables the requirement. Waived for subscriptions with a trial.
+    pub fn set_require_initial_periods(
+        env: Env,
+        admin: Address,
+        periods: u32,
+    ) -> Result<(), Error> {
+        admin::do_set_require_initial_periods(&env, admin, periods)
+    }
+
+    /// Billing periods new subscriptions must be funded for; 0 when disabled.
+    pub fn get_require_initial_periods(env: Env) -> u32 {
+        admin::get_require_initial_periods(&env)
+    }
+
+    /// **ADMIN ONLY**: Seconds without a deposit or successful charge after
+    /// which anyone may call `expire_stale`. 0 disables expiry.
+    pub fn set_staleness_seconds(env: Env, admin: Address, seconds: u64) -> Result<(), Error> {
+        admin::do_set_staleness_seconds(&env, admin, seconds)
+    }
+
+    /// Current staleness window in seconds; 0 when disabled.
+    pub fn get_staleness_seconds(env: Env) -> u64 {
+        admin::get_staleness_seconds(&env)
+    }
+
+    /// **ADMIN ONLY**: Inclusive bounds on `amount` and `interval_seconds`,
+    /// enforced when subscriptions, plans and invites are created and when an
+    /// amount or interval change is proposed or accepted. Violations fail with
+    /// `InvalidAmount` / `InvalidInterval`. Emits `limits_updated`.
+    pub fn set_limits(
+        env: Env,
+        admin: Address,
+        min_amount: i128,
+        max_amount: i128,
+        min_interval: u64,
+        max_interval: u64,
+    ) -> Result<(), Error> {
+        let limits = Limits {
+            min_amount,
+            max_amount,
+            min_interval,
+            max_interval,
+        };
+        admin::do_set_limits(&env, admin, limits)
+    }
+
+    /// Current bounds on `amount` and `interval_seconds`.
+    pub fn get_limits(env: Env) -> Limits {
+        admin::get_limits(&env)
+    }
+
+    /// Whether `oracle` is an allowlisted SEP-40 price oracle. Oracles are
+    /// added and removed through the timelock (`AdminAction::AddPriceOracle`,
+    /// `RemovePriceOracle`, emitting `oracle_added` / `oracle_removed`);
+    /// charges priced through a removed oracle fail with `OracleNotAllowed`.
+    pub fn is_price_oracle(env: Env, oracle: Address) -> bool {
+        admin::is_price_oracle(&env, &oracle)
+    }
+
+    /// **ADMIN ONLY**: Oldest oracle price, in seconds, a charge of a
+    /// fiat-priced subscription may use; older prices fail the charge with
+    /// `StaleOraclePrice`. Emits `oracle_max_age_updated`.
+    pub fn set_oracle_max_age(env: Env, admin: Address, seconds: u64) -> Result<(), Error> {
+        admin::do_set_oracle_max_age(&env, admin, seconds)
+    }
+
+    /// Current oracle max age in seconds.
+    pub fn get_oracle_max_age(env: Env) -> u64 {
+        admin::get_oracle_max_age(&env)
+    }
+
+    /// **ADMIN ONLY**: Let interval charges run up to `seconds` before they are
+    /// due, for keepers that run on a fixed schedule. An early charge is billed
+    /// as if made on its due date, so the schedule does not drift. Capped at
+    /// half of each subscription's interval; 0 (default) disables it. Emits
+    /// `charge_tolerance_updated`.
+    pub fn set_charge_tolerance(env: Env, admin: Address, seconds: u64) -> Result<(), Error> {
+        admin::do_set_charge_tolerance(&env, admin, seconds)
+    }
+
+    /// **ADMIN ONLY**: After a usage debit, a prepaid subscription left with
+    /// less than `threshold` and less than one period's amount moves to
+    /// `InsufficientBalance` as if drained to zero. 0 (default) flags only an
+    /// empty balance. `InvalidAmount` if negative. Emits `dust_threshold_updated`.
+    pub fn set_dust_threshold(env: Env, admin: Address, threshold: i128) -> Result<(), Error> {
+        admin::do_set_dust_threshold(&env, admin, threshold)
+    }
+
+    /// Current dust threshold for usage debits.
+    pub fn get_dust_threshold(env: Env) -> i128 {
+        admin::get_dust_threshold(&env)
+    }
+
+    /// **ADMIN ONLY**: Seconds a subscription must have been terminal before
+    /// `sweep_dust` may take its remaining balance. 0 (default) disables
+    /// sweeping. Emits `dust_sweep_delay_updated`.
+    pub fn set_dust_sweep_delay(env: Env, admin: Address, seconds: u64) -> Result<(), Error> {
+        admin::do_set_dust_sweep_delay(&env, admin, seconds)
+    }
+
+    /// Current dust sweep delay in seconds; 0 when disabled.
+    pub fn get_dust_sweep_delay(env: Env) -> u64 {
+        admin::get_dust_sweep_delay(&env)
+    }
+
+    /// **ADMIN ONLY**: Largest balance `sweep_dust` may take, at most
+    /// `MAX_DUST_SWEEP_THRESHOLD`. Independent of the usage dust threshold.
+    /// Emits `dust_sweep_threshold_updated`.
+    pub fn set_dust_sweep_threshold(
+        env: Env,
+        admin: Address,
+        threshold: i128,
+    ) -> Result<(), Error> {
+        admin::do_set_dust_sweep_threshold(&env, admin, threshold)
+    }
+
+    /// Current dust sweep threshold; 0 (default) sweeps nothing.
+    pub fn get_dust_sweep_threshold(env: Env) -> i128 {
+        admin::get_dust_sweep_threshold(&env)
+    }
+
+    /// **ADMIN ONLY**: Address that receives swept dust. Emits `treasury_updated`.
+    pub fn set_treasury(env: Env, admin: Address, treasury: Address) -> Result<(), Error> {
+        admin::do_set_treasury(&env, admin, treasury)
+    }
+
+    /// Current treasury, if set.
+    pub fn get_treasury(env: Env) -> Option<Address> {
+        admin::get_treasury(&env)
+    }
+
+    /// **ADMIN ONLY**: Move the remaining `prepaid_balance` of a subscription
+    /// that has been terminal for longer than `get_dust_sweep_delay`, and holds
+    /// at most `get_dust_sweep_threshold`, to the treasury. Emits `swept` with the
+    /// amount. Fails with `NotSweepable` otherwise.
+    pub fn sweep_dust(env: Env, admin: Address, subscription_id: u64) -> Result<i128, Error> {
+        dust_sweep::do_sweep_dust(&env, admin, subscription_id)
+    }
+
+    /// Current early-charge tolerance in seconds.
+    pub fn get_charge_tolerance(env: Env) -> u64 {
+        admin::get_charge_tolerance(&env)
+    }
+
+    /// List the allowlisted token contracts.
+    pub fn get_supported_tokens(env: Env) -> Vec<Address> {
+        admin::get_supported_tokens(&env)
+    }
+
+    /// Get the minimum top-up for subscriptions denominated in `token`.
+    pub fn get_token_min_topup(env: Env, token: Address) -> Result<i128, Error> {
+        admin::get_token_min_topup(&env, &token)
+    }
+
     /// Get the current admin address.
     pub fn get_admin(env: Env) -> Result<Address, Error> {
         admin::do_get_admin(&env)
     }
 
-    /// Rotate admin to a new address. Only callable by current admin.
+    /// Rotate admin to a new address. Only callable by current admin.
+    ///
+    /// # Security
+    ///
+    /// - Immediate effect — old admin loses access instantly.
+    /// - Irreversible without the new admin's cooperation.
+    /// - Emits an `admin_rotation` event for audit trail.
+    pub fn rotate_admin(env: Env, current_admin: Address, new_admin: Address) -> Result<(), Error> {
+        admin::do_rotate_admin(&env, current_admin, new_admin)
+    }
+
+    /// **ADMIN ONLY**: Block `address` from creating subscriptions (as
+    /// subscriber or merchant), depositing, and withdrawing merchant earnings.
+    /// Existing subscriptions can still be cancelled by either party and the
+    /// subscriber can still withdraw after cancellation. Emits `blacklisted`.
+    pub fn add_to_blacklist(env: Env, admin: Address, address: Address) -> Result<(), Error> {
+        admin::do_set_blacklisted(&env, admin, address, true)
+    }
+
+    /// **ADMIN ONLY**: Lift a block set by `add_to_blacklist`. Emits `unblacklisted`.
+    pub fn remove_from_blacklist(env: Env, admin: Address, address: Address) -> Result<(), Error> {
+        admin::do_set_blacklisted(&env, admin, address, false)
+    }
+
+    /// Whether `address` is on the admin blacklist.
+    pub fn is_blacklisted(env: Env, address: Address) -> bool {
+        admin::is_blacklisted(&env, &address)
+    }
+
+    /// **ADMIN ONLY**: When enabled, `create_subscription` and
+    /// `subscribe_to_plan` require a registered, active merchant and fail with
+    /// `MerchantNotRegistered` otherwise. Off by default.
+    pub fn set_merchant_allowlist_enabled(
+        env: Env,
+        admin: Address,
+        enabled: bool,
+    ) -> Result<(), Error> {
+        admin::do_set_merchant_allowlist_enabled(&env, admin, enabled)
+    }
+
+    /// Whether merchant allowlist mode is on.
+    pub fn is_merchant_allowlist_enabled(env: Env) -> bool {
+        admin::is_merchant_allowlist_enabled(&env)
+    }
+
+    /// **ADMIN ONLY**: Turn the contract-wide emergency pause on or off.
+    ///
+    /// While paused, `charge_subscription`, `charge_usage`, `batch_charge`,
+    /// `deposit_funds`, and `withdraw_merchant_funds` fail with `ContractPaused`.
+    /// Views and `cancel_subscription` keep working so users are never trapped.
+    pub fn set_contract_paused(env: Env, admin: Address, paused: bool) -> Result<(), Error> {
+        admin::do_set_contract_paused(&env, admin, paused)
+    }
+
+    /// Whether the contract-wide emergency pause is active.
+    pub fn is_contract_paused(env: Env) -> bool {
+        admin::is_contract_paused(&env)
+    }
+
+    /// **ADMIN ONLY** (single admin, no admin set yet): Queue a sensitive change (keeper reward, token or oracle
+    /// allowlist, contract upgrade) to take effect at `eta`, which must be at
+    /// least `MIN_ADMIN_DELAY_SECONDS` (48h) away, else `InvalidInterval`. Returns the action id and
+    /// emits `admin_queued`.
+    pub fn queue_admin_action(
+        env: Env,
+        admin: Address,
+        action: AdminAction,
+        eta: u64,
+    ) -> Result<u64, Error> {
+        timelock::do_queue_admin_action(&env, admin, action, eta)
+    }
+
+    /// Apply a queued admin action; fails with `IntervalNotElapsed` before its
+    /// eta. Callable by anyone. Emits `admin_executed`, then the action's own
+    /// event.
+    ///
+    /// An `Upgrade` keeps storage, bumps the contract version, emits `upgraded`
+    /// with the new WASM hash, and takes effect after this call returns.
+    pub fn execute_admin_action(env: Env, action_id: u64) -> Result<(), Error> {
+        timelock::do_execute_admin_action(&env, action_id)
+    }
+
+    /// **ADMIN ONLY** (single admin, no admin set yet): Drop a queued admin
+    /// action. Emits `admin_cancelled`.
+    pub fn cancel_admin_action(env: Env, admin: Address, action_id: u64) -> Result<(), Error> {
+        timelock::do_cancel_admin_action(&env, admin, action_id)
+    }
+
+    /// Admin actions waiting in the timelock, oldest first, so UIs can warn
+    /// users of pending changes.
+    pub fn get_queued_admin_actions(env: Env) -> Vec<QueuedAdminAction> {
+        timelock::get_queued_admin_actions(&env)
+    }
+
+    /// **ADMIN ONLY**: Install an M-of-N admin set. Only the single admin can
+    /// call this, and only once; after that, `queue_admin_action`,
+    /// `cancel_admin_action` and changes to the set need `threshold`
+    /// approvals through `propose_admin_action` / `approve_action`. Emits
+    /// `admins_set`.
+    pub fn set_admins(
+        env: Env,
+        admin: Address,
+        admins: Vec<Address>,
+        threshold: u32,
+    ) -> Result<(), Error> {
+        multisig::do_set_admins(&env, admin, admins, threshold)
+    }
+
+    /// **ADMIN SET ONLY**: Propose `action` with the caller's approval and
+    /// return its hash for the other admins to approve. Proposing an action
+    /// that already has a live proposal approves it instead. Emits
+    /// `admin_proposed` and `admin_approved`.
+    pub fn propose_admin_action(
+        env: Env,
+        admin: Address,
+        action: MultisigAction,
+    ) -> Result<BytesN<32>, Error> {
+        multisig::do_propose_admin_action(&env, admin, action)
+    }
+
+    /// **ADMIN SET ONLY**: Approve a proposal; the approval that meets the
+    /// threshold executes it and returns true. Fails with `NotFound` once the
+    /// approval window has passed and `Replay` for a repeated approval.
+    pub fn approve_action(
+        env: Env,
+        admin: Address,
+        action_hash: BytesN<32>,
+    ) -> Result<bool, Error> {
+        multisig::do_approve_action(&env, admin, action_hash)
+    }
+
+    /// The M-of-N admin set, if one is installed.
+    pub fn get_admin_set(env: Env) -> Option<AdminSet> {
+        multisig::get_admin_set(&env)
+    }
+
+    /// A multisig proposal and its approvals so far, by action hash.
+    pub fn get_admin_proposal(env: Env, action_hash: BytesN<32>) -> Option<AdminProposal> {
+        multisig::get_admin_proposal(&env, &action_hash)
+    }
+
+    /// Hash under which `action` is proposed and approved.
+    pub fn get_action_hash(env: Env, action: MultisigAction) -> BytesN<32> {
+        multisig::action_hash(&env, &action)
+    }
+
+    /// Get the contract code version (1 after `init`, incremented by each executed `AdminAction::Upgrade`).
+    pub fn get_version(env: Env) -> u32 {
+        admin::get_version(&env)
+    }
+
+    /// **ADMIN ONLY**: Recover stranded funds from the contract.
+    ///
+    /// Tightly-scoped mechanism for recovering funds that have become
+    /// inaccessible through normal operations. Each recovery emits a
+    /// `RecoveryEvent` with full audit details.
+    pub fn recover_stranded_funds(
+        env: Env,
+        admin: Address,
+        recipient: Address,
+        amount: i128,
+        reason: RecoveryReason,
+    ) -> Result<(), Error> {
+        admin::do_recover_stranded_funds(&env, admin, recipient, amount, reason)
+    }
+
+    /// Import a live subscription from an earlier deployment. Admin only.
+    ///
+    /// `sub` is written as given, including its historical
+    /// `last_payment_timestamp`, so billing continues on the old schedule.
+    /// Its `prepaid_balance` is replaced by `prepaid_topup`, pulled from
+    /// `admin`, and `plan_id` is cleared. Runs the creation checks (limits,
+    /// supported token, blacklist, duplicates) and rejects terminal statuses
+    /// with `InvalidStatusTransition`. Emits `imported` with
+    /// `(subscriber, merchant, prepaid_topup)`, never `sub_new`. Fails with
+    /// `MigrationComplete` once imports are closed. Returns the new id.
+    pub fn import_subscription(
+        env: Env,
+        admin: Address,
+        sub: Subscription,
+        prepaid_topup: i128,
+    ) -> Result<u64, Error> {
+        import::do_import_subscription(&env, admin, sub, prepaid_topup)
+    }
+
+    /// Permanently disable `import_subscription`. Admin only. Emits
+    /// `migration_complete`.
+    pub fn migration_complete(env: Env, admin: Address) -> Result<(), Error> {
+        import::do_migration_complete(&env, admin)
+    }
+
+    /// Whether `migration_complete` has been called.
+    pub fn is_migration_complete(env: Env) -> bool {
+        import::is_complete(&env)
+    }
+
+    /// Charge a batch of subscriptions in one transaction. Admin only.
+    ///
+    /// Returns a per-subscription result vector so callers can identify
+    /// which charges succeeded and which failed (with error codes).
+    pub fn batch_charge(
+        env: Env,
+        subscription_ids: Vec<u64>,
+    ) -> Result<Vec<BatchChargeResult>, Error> {
+        admin::do_batch_charge(&env, &subscription_ids)
+    }
+
+    // ── Subscription lifecycle ───────────────────────────────────────────
+
+    /// Create a new subscription. Caller deposits initial USDC; contract stores agreement.
+    ///
+    /// Billed in the token passed to `init`. Use `create_subscription_with_token`
+    /// for other allowlisted assets.
+    pub fn create_subscription(
+        env: Env,
+        subscriber: Address,
+        merchant: Address,
+        amount: i128,
+        interval_seconds: u64,
+        usage_enabled: bool,
+    ) -> Result<u64, Error> {
+        subscription::do_create_subscription(
+            &env,
+            subscriber,
+            merchant,
+            amount,
+            interval_seconds,
+            usage_enabled,
+            SubscriptionOptions::default(),
+        )
+    }
+
+    /// Create a new subscription billed in `token`, which must be allowlisted.
+    pub fn create_subscription_with_token(
+        env: Env,
+        subscriber: Address,
+        merchant: Address,
+        token: Address,
+        amount: i128,
+        interval_seconds: u64,
+        usage_enabled: bool,
+    ) -> Result<u64, Error> {
+        subscription::do_create_subscription(
+            &env,
+            subscriber,
+            merchant,
+            amount,
+            interval_seconds,
+            usage_enabled,
+            SubscriptionOptions {
+                token: Some(token),
+                ..SubscriptionOptions::default()
+            },
+        )
+    }
+
+    /// Create a new subscription with optional terms (token, free trial, fixed length).
+    ///
+    /// With `options.trial_until > 0`, interval charges fail with `InTrialPeriod`
+    /// until that timestamp and the first billing interval starts at
+    /// `trial_until` instead of creation time. With `options.max_periods > 0`,
+    /// the charge for the last period moves the subscription to `Completed`.
+    /// With `options.coupon`, the merchant's coupon is redeemed (or creation
+    /// fails with `CouponInvalid`) and discounts the first interval charges.
+    /// With a trial, the merchant's trial bond (if set) is pulled as well.
+    /// With `options.referrer`, the referrer earns the admin's referral fee on
+    /// the first interval charges.
+    pub fn create_subscription_with_options(
+        env: Env,
+        subscriber: Address,
+        merchant: Address,
+        amount: i128,
+        interval_seconds: u64,
+        usage_enabled: bool,
+        options: SubscriptionOptions,
+    ) -> Result<u64, Error> {
+        subscription::do_create_subscription(
+            &env,
+            subscriber,
+            merchant,
+            amount,
+            interval_seconds,
+            usage_enabled,
+            options,
+        )
+    }
+
+    /// Merchant onboards up to `MAX_BULK_CREATE` (20) existing customers at
+    /// once, signed by the merchant only. Each entry becomes a `Pending`
+    /// subscription with default options and emits its own `sub_new`; it
+    /// activates when the subscriber's first deposit covers one period, and
+    /// cannot be charged before. Any failing entry (limits, duplicates,
+    /// blacklist) fails the whole batch. Returns the new ids in entry order.
+    /// `BatchTooLarge` above the cap; `InvalidMerchantRotation` for a
+    /// merchant address that was rotated away.
+    pub fn bulk_create_unfunded(
+        env: Env,
+        merchant: Address,
+        entries: Vec<BulkEntry>,
+    ) -> Result<Vec<u64>, Error> {
+        bulk_create::do_bulk_create_unfunded(&env, merchant, entries)
+    }
+
+    /// Gift a subscription: `payer` signs and funds it with `initial_deposit`,
+    /// while `recipient` becomes the subscriber with the usual rights to pause,
+    /// cancel and withdraw. The recipient does not sign. The payer is recorded
+    /// (see `get_gift_payer`) and can only top up further, via `deposit_on_behalf`.
+    /// Creation rules are those of `create_subscription`, in the default token.
+    pub fn create_gift_subscription(
+        env: Env,
+        payer: Address,
+        recipient: Address,
+        merchant: Address,
+        amount: i128,
+        interval_seconds: u64,
+        initial_deposit: i128,
+    ) -> Result<u64, Error> {
+        subscription::do_create_gift_subscription(
+            &env,
+            payer,
+            recipient,
+            merchant,
+            amount,
+            interval_seconds,
+            initial_deposit,
+        )
+    }
+
+    /// Require (or stop requiring) the subscriber's approval before each
+    /// interval charge. Subscriber or merchant. While required,
+    /// `charge_subscription` fails with `RenewalNotApproved` unless the
+    /// subscriber called `approve_next_renewal` since the last charge.
+    pub fn set_renewal_approval_required(
+        env: Env,
+        subscription_id: u64,
+        authorizer: Address,
+        required: bool,
+    ) -> Result<(), Error> {
+        renewal::do_set_renewal_approval_required(&env, subscription_id, authorizer, required)
+    }
+
+    /// Subscriber approves the next interval charge, and only that one; the
+    /// approval is used up by the charge. Deposits do not count as approval.
+    /// Emits `approved` with `(renewal, due_at)`: the charge number it covers
+    /// and when that charge is due.
+    pub fn approve_next_renewal(
+        env: Env,
+        subscription_id: u64,
+        subscriber: Address,
+    ) -> Result<(), Error> {
+        renewal::do_approve_next_renewal(&env, subscription_id, subscriber)
+    }
+
+    /// Whether renewal approval is required and whether the next charge is approved.
+    pub fn get_renewal_approval(env: Env, subscription_id: u64) -> Result<RenewalApproval, Error> {
+        renewal::get_renewal_approval(&env, subscription_id)
+    }
+
+    /// Subscriber sets the most this subscription may ever charge, interval
+    /// and usage combined. 0 removes the cap; otherwise it may not be below
+    /// what has already been charged (`InvalidAmount`). Charges that would
+    /// pass it fail with `LifetimeCapExceeded`. Emits `life_cap` with `(old, new)`.
+    pub fn set_lifetime_cap(
+        env: Env,
+        subscription_id: u64,
+        subscriber: Address,
+        cap: i128,
+    ) -> Result<(), Error> {
+        lifetime_cap::do_set_lifetime_cap(&env, subscription_id, subscriber, cap)
+    }
+
+    /// Lifetime charge ceiling; 0 when unlimited.
+    pub fn get_lifetime_cap(env: Env, subscription_id: u64) -> Result<i128, Error> {
+        lifetime_cap::get_lifetime_cap(&env, subscription_id)
+    }
+
+    /// Fiat price and oracle of a fiat-priced subscription; `None` when it is
+    /// priced in token units.
+    pub fn get_fiat_pricing(env: Env, subscription_id: u64) -> Result<Option<FiatPricing>, Error> {
+        oracle::get_fiat_pricing(&env, subscription_id)
+    }
+
+    /// Charge schedule of a subscription created with a `billing_anchor`;
+    /// `None` when it bills one interval after each charge.
+    pub fn get_billing_anchor(
+        env: Env,
+        subscription_id: u64,
+    ) -> Result<Option<BillingAnchor>, Error> {
+        anchor::get_billing_anchor(&env, subscription_id)
+    }
+
+    /// Merchant proposes allowing (or no longer allowing) usage charges while
+    /// the subscription is `Paused`. Takes effect once the subscriber calls
+    /// `accept_usage_while_paused`. Emits `paused_usage_proposed` with
+    /// `(current, proposed)`.
+    pub fn propose_usage_while_paused(
+        env: Env,
+        subscription_id: u64,
+        merchant: Address,
+        enabled: bool,
+    ) -> Result<(), Error> {
+        paused_usage::do_propose_usage_while_paused(&env, subscription_id, merchant, enabled)
+    }
+
+    /// Subscriber accepts the pending usage-while-paused proposal. Emits
+    /// `paused_usage_accepted` with `(old, new)`.
+    pub fn accept_usage_while_paused(
+        env: Env,
+        subscription_id: u64,
+        subscriber: Address,
+    ) -> Result<(), Error> {
+        paused_usage::do_accept_usage_while_paused(&env, subscription_id, subscriber)
+    }
+
+    /// `(enabled, pending)`: whether usage charges run while `Paused`, and the
+    /// merchant's unaccepted proposal, if any.
+    pub fn get_usage_while_paused(
+        env: Env,
+        subscription_id: u64,
+    ) -> Result<(bool, Option<bool>), Error> {
+        paused_usage::get_usage_while_paused(&env, subscription_id)
+    }
+
+    /// Move a subscription with no deposit or successful charge for
+    /// `get_staleness_seconds` to the terminal `Expired` status. Anyone may
+    /// call it; the prepaid balance stays withdrawable by the subscriber.
+    /// Fails with `NotStale` while expiry is disabled or activity is recent.
+    pub fn expire_stale(env: Env, subscription_id: u64) -> Result<(), Error> {
+        expiry::do_expire_stale(&env, subscription_id)
+    }
+
+    /// Timestamp of the last deposit or successful charge (creation time if none).
+    pub fn get_last_activity(env: Env, subscription_id: u64) -> Result<u64, Error> {
+        expiry::get_last_activity(&env, subscription_id)
+    }
+
+    /// Payer of a gift subscription, for provenance; `None` when the
+    /// subscriber created it. The payer has no rights over the subscription.
+    pub fn get_gift_payer(env: Env, subscription_id: u64) -> Result<Option<Address>, Error> {
+        queries::get_gift_payer(&env, subscription_id)
+    }
+
+    /// Subscriber deposits more USDC into their prepaid vault.
+    ///
+    /// `subscriber` may also be the delegate payer (see `set_delegate_payer`);
+    /// anyone else gets `Unauthorized`. Rejects deposits below the configured
+    /// minimum threshold. Returns the new `prepaid_balance`.
+    pub fn deposit_funds(
+        env: Env,
+        subscription_id: u64,
+        subscriber: Address,
+        amount: i128,
+    ) -> Result<i128, Error> {
+        subscription::do_deposit_funds(&env, subscription_id, subscriber, amount)
+    }
+
+    /// Subscriber configures auto top-up: whenever a charge or usage debit
+    /// leaves `prepaid_balance` below `floor`, `topup_amount` is pulled from
+    /// the subscriber's wallet through a standing token allowance to this
+    /// contract. A refused pull never fails the debit. `topup_amount` must
+    /// clear the effective minimum top-up when enabled. Emits `auto_topup_set`.
+    pub fn set_auto_topup(
+        env: Env,
+        subscription_id: u64,
+        subscriber: Address,
+        config: AutoTopup,
+    ) -> Result<(), Error> {
+        auto_topup::do_set_auto_topup(&env, subscription_id, subscriber, config)
+    }
+
+    /// Auto top-up config, if the subscriber set one.
+    pub fn get_auto_topup(env: Env, subscription_id: u64) -> Result<Option<AutoTopup>, Error> {
+        auto_topup::get_auto_topup(&env, subscription_id)
+    }
+
+    /// Subscriber lets `payer` top up this subscription through
+    /// `deposit_funds`, replacing any earlier delegate. The delegate gets no
+    /// other rights. Emits `delegate_set`.
+    pub fn set_delegate_payer(
+        env: Env,
+        subscriber: Address,
+        subscription_id: u64,
+        payer: Address,
+    ) -> Result<(), Error> {
+        delegate::do_set_delegate_payer(&env, subscriber, subscription_id, payer)
+    }
+
+    /// Delegate stops funding the subscription. Balance it already deposited
+    /// stays with the subscription. Emits `delegate_revoked`.
+    pub fn revoke_delegate(env: Env, payer: Address, subscription_id: u64) -> Result<(), Error> {
+        delegate::do_revoke_delegate(&env, payer, subscription_id)
+    }
+
+    /// Current delegate payer, if any.
+    pub fn get_delegate(env: Env, subscription_id: u64) -> Result<Option<Address>, Error> {
+        delegate::get_delegate(&env, subscription_id)
+    }
+
+    /// Subscriber names a watch address that is appended as a topic to
+    /// `low_bal`, charge-failed and `charged` events, or clears it with `None`.
+    /// No funds move to or from it. Emits `notify_set`.
+    pub fn set_notify_address(
+        env: Env,
+        subscriber: Address,
+        subscription_id: u64,
+        notify_address: Option<Address>,
+    ) -> Result<(), Error> {
+        notify::do_set_notify_address(&env, subscriber, subscription_id, notify_address)
+    }
+
+    /// Current notification address, if any.
+    pub fn get_notify_address(env: Env, subscription_id: u64) -> Result<Option<Address>, Error> {
+        notify::get_notify_address(&env, subscription_id)
+    }
+
+    /// Run up to `MAX_MULTICALL_OPS` (8) operations as `caller` under one
+    /// signature, in order: create, deposit, cancel, or withdraw the full
+    /// refund. Each op checks `caller` the way its own entrypoint does (the
+    /// subscriber, or for cancel also the merchant) and emits its usual events.
+    /// The first failing op aborts the call and undoes the earlier ones.
+    /// An op `subscription_id` of `MULTICALL_LAST_CREATED` refers to the
+    /// subscription created by the latest `CreateSubscription` op before it.
+    /// Returns one [`VaultOpResult`] per op; `BatchTooLarge` above the cap.
+    pub fn multicall(
+        env: Env,
+        caller: Address,
+        ops: Vec<VaultOp>,
+    ) -> Result<Vec<VaultOpResult>, Error> {
+        multicall::do_multicall(&env, caller, ops)
+    }
+
+    /// Fund someone else's subscription (e.g. an employer or parent).
+    ///
+    /// `payer` authorizes and pays; the subscriber's consent is not needed.
+    /// Only `prepaid_balance` changes, and withdrawals still go to the
+    /// subscriber. `min_topup` applies as for `deposit_funds`. Returns the
+    /// new `prepaid_balance`.
+    pub fn deposit_on_behalf(
+        env: Env,
+        subscription_id: u64,
+        payer: Address,
+        amount: i128,
+    ) -> Result<i128, Error> {
+        subscription::do_deposit_on_behalf(&env, subscription_id, payer, amount)
+    }
+
+    /// Cancel the subscription. Allowed from Active, Paused, or InsufficientBalance.
+    /// Transitions to the terminal `Cancelled` state. `reason` is a `REASON_*`
+    /// code (0 = unspecified); it is stored on the subscription and emitted.
+    pub fn cancel_subscription(
+        env: Env,
+        subscription_id: u64,
+        authorizer: Address,
+        reason: u32,
+    ) -> Result<(), Error> {
+        subscription::do_cancel_subscription(&env, subscription_id, authorizer, reason)
+    }
+
+    /// Delete the storage of a finished subscription. Anyone may call it once
+    /// the subscription is `Cancelled`, `Completed` or `Expired` with zero
+    /// `prepaid_balance`, zero `arrears`, and its last charge past the dispute
+    /// window. Removes the record, its per-subscription keys and its merchant
+    /// index entry, and emits `purged` with the final record and stats.
+    /// Afterwards `get_subscription` returns `NotFound`.
+    ///
+    /// # Errors
+    ///
+    /// `NotPurgeable` if any of the conditions above does not hold.
+    pub fn purge_subscription(env: Env, subscription_id: u64) -> Result<(), Error> {
+        subscription::do_purge_subscription(&env, subscription_id)
+    }
+
+    /// Admin force-cancels a subscription for compliance or abuse, recording
+    /// `reason_code` on it. The prepaid balance remains withdrawable by the
+    /// subscriber only, via `withdraw_subscriber_funds`.
+    pub fn admin_cancel_subscription(
+        env: Env,
+        admin: Address,
+        subscription_id: u64,
+        reason_code: u32,
+    ) -> Result<(), Error> {
+        subscription::do_admin_cancel_subscription(&env, admin, subscription_id, reason_code)
+    }
+
+    /// Merchant proposes a new per-interval amount. Takes effect only once the
+    /// subscriber calls `accept_amount_change`, and then from the next charge.
+    /// Emits `amount_proposed` with `(current_amount, new_amount)`.
+    pub fn propose_amount_change(
+        env: Env,
+        subscription_id: u64,
+        merchant: Address,
+        new_amount: i128,
+    ) -> Result<(), Error> {
+        subscription::do_propose_amount_change(&env, subscription_id, merchant, new_amount)
+    }
+
+    /// Subscriber accepts the pending amount change. Emits `amount_accepted`.
+    pub fn accept_amount_change(
+        env: Env,
+        subscription_id: u64,
+        subscriber: Address,
+    ) -> Result<(), Error> {
+        subscription::do_accept_amount_change(&env, subscription_id, subscriber)
+    }
+
+    /// Subscriber rejects the pending amount change. Emits `amount_rejected`.
+    pub fn reject_amount_change(
+        env: Env,
+        subscription_id: u64,
+        subscriber: Address,
+    ) -> Result<(), Error> {
+        subscription::do_clear_amount_change(&env, subscription_id, subscriber, true)
+    }
+
+    /// Merchant withdraws its pending amount change. Emits `amount_withdrawn`.
+    pub fn cancel_amount_change(
+        env: Env,
+        subscription_id: u64,
+        merchant: Address,
+    ) -> Result<(), Error> {
+        subscription::do_clear_amount_change(&env, subscription_id, merchant, false)
+    }
+
+    /// Merchant proposes turning usage billing on at `usage_rate`, or off
+    /// (`usage_rate` is then ignored). Takes effect once the subscriber calls
+    /// `accept_usage_toggle`; until then usage charges follow the current flag.
+    /// Emits `usage_toggle_proposed` with the [`UsageToggle`].
+    pub fn propose_usage_toggle(
+        env: Env,
+        subscription_id: u64,
+        merchant: Address,
+        enabled: bool,
+        usage_rate: i128,
+    ) -> Result<(), Error> {
+        usage_toggle::do_propose_usage_toggle(&env, subscription_id, merchant, enabled, usage_rate)
+    }
+
+    /// Subscriber accepts the pending usage toggle. Disabling clears this
+    /// period's usage counters. Emits `usage_toggle_accepted`.
+    pub fn accept_usage_toggle(
+        env: Env,
+        subscription_id: u64,
+        subscriber: Address,
+    ) -> Result<(), Error> {
+        usage_toggle::do_accept_usage_toggle(&env, subscription_id, subscriber)
+    }
+
+    /// Subscriber rejects the pending usage toggle. Emits `usage_toggle_rejected`.
+    pub fn reject_usage_toggle(
+        env: Env,
+        subscription_id: u64,
+        subscriber: Address,
+    ) -> Result<(), Error> {
+        usage_toggle::do_clear_usage_toggle(&env, subscription_id, subscriber, true)
+    }
+
+    /// Merchant withdraws its pending usage toggle. Emits `usage_toggle_withdrawn`.
+    pub fn cancel_usage_toggle(
+        env: Env,
+        subscription_id: u64,
+        merchant: Address,
+    ) -> Result<(), Error> {
+        usage_toggle::do_clear_usage_toggle(&env, subscription_id, merchant, false)
+    }
+
+    /// Subscriber proposes moving the subscription to a new address (e.g. a
+    /// rotated wallet). Takes effect once `new_subscriber` calls
+    /// `accept_transfer`. Rejected for `Cancelled`/`Completed` subscriptions.
+    /// Emits `transfer_proposed`.
+    pub fn propose_transfer(
+        env: Env,
+        subscription_id: u64,
+        current_subscriber: Address,
+        new_subscriber: Address,
+    ) -> Result<(), Error> {
+        subscription::do_propose_transfer(&env, subscription_id, current_subscriber, new_subscriber)
+    }
+
+    /// Proposed subscriber accepts the transfer and takes over the
+    /// subscription, including its prepaid balance. Emits `transferred`.
+    pub fn accept_transfer(
+        env: Env,
+        subscription_id: u64,
+        new_subscriber: Address,
+    ) -> Result<(), Error> {
+        subscription::do_accept_transfer(&env, subscription_id, new_subscriber)
+    }
+
+    /// Subscriber withdraws a pending transfer. Emits `transfer_cancelled`.
+    pub fn cancel_transfer(
+        env: Env,
+        subscription_id: u64,
+        subscriber: Address,
+    ) -> Result<(), Error> {
+        subscription::do_cancel_transfer(&env, subscription_id, subscriber)
+    }
+
+    /// Pending transfer recipient, if any.
+    pub fn get_pending_transfer(env: Env, subscription_id: u64) -> Result<Option<Address>, Error> {
+        queries::get_pending_transfer(&env, subscription_id)
+    }
+
+    /// Subscriber changes the billing interval (e.g. annual to monthly).
+    ///
+    /// With `merchant_signed = true` the merchant must co-sign and the change is
+    /// scheduled immediately (`interval_scheduled`); otherwise it is proposed
+    /// (`interval_proposed`) until the merchant calls
+    /// `acknowledge_interval_change`. A scheduled change is stored in
+    /// `next_interval_seconds` and takes effect after the next successful
+    /// charge, so the current paid-for period keeps its length.
+    ///
+    /// # Errors
+    ///
+    /// `InvalidInterval` outside the interval bounds of `get_limits`;
+    /// `InvalidStatusTransition` for `Cancelled` or `Completed` subscriptions.
+    pub fn update_interval(
+        env: Env,
+        subscription_id: u64,
+        subscriber: Address,
+        merchant_signed: bool,
+        new_interval: u64,
+    ) -> Result<(), Error> {
+        subscription::do_update_interval(
+            &env,
+            subscription_id,
+            subscriber,
+            merchant_signed,
+            new_interval,
+        )
+    }
+
+    /// Merchant acknowledges a proposed interval change, scheduling it.
+    pub fn acknowledge_interval_change(
+        env: Env,
+        subscription_id: u64,
+        merchant: Address,
+    ) -> Result<(), Error> {
+        subscription::do_acknowledge_interval_change(&env, subscription_id, merchant)
+    }
+
+    /// Subscriber schedules cancellation at the end of the current billing period.
+    ///
+    /// Sets `cancel_at` to the next charge timestamp and returns it. Until then
+    /// the subscription keeps running; the first `charge_subscription` at or
+    /// after `cancel_at` cancels it without charging.
+    pub fn schedule_cancellation(
+        env: Env,
+        subscription_id: u64,
+        subscriber: Address,
+    ) -> Result<u64, Error> {
+        subscription::do_schedule_cancellation(&env, subscription_id, subscriber)
+    }
+
+    /// Subscriber withdraws a pending scheduled cancellation.
+    pub fn undo_cancellation(
+        env: Env,
+        subscription_id: u64,
+        subscriber: Address,
+    ) -> Result<(), Error> {
+        subscription::do_undo_cancellation(&env, subscription_id, subscriber)
+    }
+
+    /// Subscriber withdraws their remaining prepaid_balance after
+    /// cancellation, completion or expiry. Returns the remaining
+    /// `prepaid_balance`, which is always 0.
+    pub fn withdraw_subscriber_funds(
+        env: Env,
+        subscription_id: u64,
+        subscriber: Address,
+    ) -> Result<i128, Error> {
+        subscription::do_withdraw_subscriber_funds(&env, subscription_id, subscriber)
+    }
+
+    /// Subscriber withdraws part of their prepaid balance.
+    ///
+    /// While `Active`, `Paused` or `Pending` one interval's `amount * quantity`
+    /// stays reserved, so a charge right after resume or activation still
+    /// succeeds. `InsufficientBalance` and terminal subscriptions keep no
+    /// reserve. Emits `subscriber_withdrawn` with `(amount, remaining)`.
+    ///
+    /// # Errors
+    ///
+    /// `ReserveRequired` if the withdrawal would breach the reserve,
+    /// `InsufficientBalance` if it exceeds the balance, `InvalidAmount`
+    /// for non-positive amounts.
+    pub fn withdraw_subscriber_excess(
+        env: Env,
+        subscription_id: u64,
+        subscriber: Address,
+        amount: i128,
+    ) -> Result<(), Error> {
+        subscription::do_withdraw_subscriber_excess(&env, subscription_id, subscriber, amount)
+    }
+
+    /// Pause subscription (no charges until resumed). Allowed from Active.
+    /// `reason` is a `REASON_*` code (0 = unspecified), stored and emitted.
+    pub fn pause_subscription(
+        env: Env,
+        subscription_id: u64,
+        authorizer: Address,
+        reason: u32,
+    ) -> Result<(), Error> {
+        subscription::do_pause_subscription(&env, subscription_id, authorizer, reason)
+    }
+
+    /// Resume a subscription to Active. Allowed from Paused or InsufficientBalance.
+    pub fn resume_subscription(
+        env: Env,
+        subscription_id: u64,
+        authorizer: Address,
+    ) -> Result<(), Error> {
+        subscription::do_resume_subscription(&env, subscription_id, authorizer)
+    }
+
+    // ── Plans ────────────────────────────────────────────────────────────
+
+    /// Merchant publishes a reusable plan. Returns the plan id (starting at 1).
+    /// Emits `plan_new` with `(merchant, amount, interval_seconds)`.
+    pub fn create_plan(
+        env: Env,
+        merchant: Address,
+        amount: i128,
+        interval_seconds: u64,
+        usage_enabled: bool,
+        usage_rate: i128,
+    ) -> Result<u32, Error> {
+        plan::do_create_plan(
+            &env,
+            merchant,
+            amount,
+            interval_seconds,
+            usage_enabled,
+            usage_rate,
+        )
+    }
+
+    /// Merchant stops new sign-ups to a plan. Existing subscriptions keep running.
+    pub fn deactivate_plan(env: Env, merchant: Address, plan_id: u32) -> Result<(), Error> {
+        plan::do_deactivate_plan(&env, merchant, plan_id)
+    }
+
+    /// Subscribe with a plan's current terms, recording `plan_id` on the new
+    /// subscription. Deposits `initial_deposit` when positive (subject to the
+    /// minimum top-up). Fails with `NotFound` or `PlanInactive`.
+    pub fn subscribe_to_plan(
+        env: Env,
+        subscriber: Address,
+        plan_id: u32,
+        initial_deposit: i128,
+    ) -> Result<u64, Error> {
+        plan::do_subscribe_to_plan(&env, subscriber, plan_id, initial_deposit)
+    }
+
+    /// Read a plan by id.
+    pub fn get_plan(env: Env, plan_id: u32) -> Result<Plan, Error> {
+        plan::get_plan(&env, plan_id)
+    }
+
+    /// Ids of all plans created by a merchant, oldest first.
+    pub fn get_merchant_plans(env: Env, merchant: Address) -> Vec<u32> {
+        plan::get_merchant_plans(&env, merchant)
+    }
+
+    /// Merchant creates a coupon redeemable through
+    /// `SubscriptionOptions::coupon`. It discounts the first `duration_periods`
+    /// interval charges of each subscription that redeems it. `max_redemptions`
+    /// and `expires_at` use 0 for no limit. Fails with `InvalidAmount` for a
+    /// zero discount or duration or a percentage above 10_000 bps, and with
+    /// `CouponInvalid` if the merchant already has a coupon with this `code`.
+    pub fn create_coupon(
+        env: Env,
+        merchant: Address,
+        code: BytesN<16>,
+        kind: CouponKind,
+        duration_periods: u32,
+        max_redemptions: u32,
+        expires_at: u64,
+    ) -> Result<(), Error> {
+        coupon::do_create_coupon(
+            &env,
+            merchant,
+            code,
+            kind,
+            duration_periods,
+            max_redemptions,
+            expires_at,
+        )
+    }
+
+    /// Merchant offers `subscriber` a subscription on these terms. Nothing is
+    /// created until the subscriber calls `accept_invite`; until then the
+    /// invite appears in no subscription index. `expires_at` must be in the
+    /// future (`InviteExpired`). Returns the invite id.
+    pub fn create_invite(
+        env: Env,
+        merchant: Address,
+        subscriber: Address,
+        amount: i128,
+        interval_seconds: u64,
+        usage_enabled: bool,
+        expires_at: u64,
+    ) -> Result<u32, Error> {
+        invite::do_create_invite(
+            &env,
+            merchant,
+            subscriber,
+            amount,
+            interval_seconds,
+            usage_enabled,
+            expires_at,
+        )
+    }
+
+    /// Invited subscriber accepts: the subscription is created on the invite's
+    /// terms with the subscriber's signature and funded with
+    /// `initial_deposit`, and the invite is consumed. Fails with
+    /// `InviteExpired` at or after `expires_at`, `NotFound` once accepted or
+    /// revoked, and otherwise as `create_subscription_with_options`.
+    pub fn accept_invite(
+        env: Env,
+        subscriber: Address,
+        invite_id: u32,
+        initial_deposit: i128,
+    ) -> Result<u64, Error> {
+        invite::do_accept_invite(&env, subscriber, invite_id, initial_deposit)
+    }
+
+    /// Merchant withdraws an invite that has not been accepted.
+    pub fn revoke_invite(env: Env, merchant: Address, invite_id: u32) -> Result<(), Error> {
+        invite::do_revoke_invite(&env, merchant, invite_id)
+    }
+
+    /// Read a pending invite.
+    pub fn get_invite(env: Env, invite_id: u32) -> Result<PendingInvite, Error> {
+        invite::get_invite(&env, invite_id)
+    }
+
+    /// Read a merchant's coupon, including how often it has been redeemed.
+    pub fn get_coupon(env: Env, merchant: Address, code: BytesN<16>) -> Result<Coupon, Error> {
+        coupon::get_coupon(&env, &merchant, &code)
+    }
+
+    /// Number of subscriptions created from a plan.
+    pub fn get_plan_subscriber_count(env: Env, plan_id: u32) -> Result<u32, Error> {
+        plan::get_plan_subscriber_count(&env, plan_id)
+    }
+
+    // ── Charging ─────────────────────────────────────────────────────────
+
+    /// Charge one interval. Permissionless: anyone (our billing engine or a
+    /// third-party keeper) may call it, and on a successful charge `caller`
+    /// receives the configured keeper reward out of the charged amount.
+    ///
+    /// Enforces strict interval timing and replay protection. Returns a
+    /// [`ChargeReceipt`]; `periods_settled` is 0 when the call only applied a
+    /// scheduled cancellation or recorded a failed charge. A charge that fails
+    /// for lack of funds is reported through `failure_code` instead of an
+    /// error, so the failure count and status change persist for dunning.
+    pub fn charge_subscription(
+        env: Env,
+        subscription_id: u64,
+        caller: Address,
+    ) -> Result<ChargeReceipt, Error> {
+        charge_core::charge_one(&env, subscription_id, None, Some(caller))
+    }
+
+    /// Dry run of `charge_subscription` at the current ledger time: whether a
+    /// period is due, what it would debit, the keeper fee out of it, and
+    /// whether funds cover it. Uses the same arithmetic as the real charge;
+    /// writes nothing and emits no events.
+    pub fn preview_charge(env: Env, subscription_id: u64) -> Result<ChargePreview, Error> {
+        charge_core::preview_charge(&env, subscription_id)
+    }
+
+    /// Charge a metered usage amount against the subscription's prepaid balance.
+    ///
+    /// Designed for integration with an **off-chain usage metering service**:
+    /// the service measures consumption, then calls this entrypoint with the
+    /// computed `usage_amount` to debit the subscriber's vault.
+    ///
+    /// # Requirements
+    ///
+    /// * The subscription must be `Active`.
+    /// * `usage_enabled` must be `true` on the subscription.
+    /// * `usage_amount` must be positive (`> 0`).
+    /// * `prepaid_balance` must be >= `usage_amount`.
+    ///
+    /// # Behaviour
+    ///
+    /// On success, `prepaid_balance` is reduced by `usage_amount`.  If the
+    /// debit drains the balance to zero the subscription transitions to
+    /// `InsufficientBalance` status, signalling that no further charges
+    /// (interval or usage) can proceed until the subscriber tops up.
+    ///
+    /// # Idempotency
+    ///
+    /// `usage_id` is a caller-chosen unique id per usage event. Processed ids
+    /// are kept in temporary storage for `USAGE_ID_TTL_LEDGERS`; resubmitting
+    /// the same id (e.g. a retry after a timeout) does not debit again; its
+    /// receipt carries the originally charged amount and the current balance.
+    ///
+    /// # Errors
+    ///
+    /// | Variant | Reason |
+    /// |---------|--------|
+    /// | `NotFound` | Subscription ID does not exist. |
+    /// | `NotActive` | Subscription is not `Active` (or `Paused` without usage while paused). |
+    /// | `UsageNotEnabled` | `usage_enabled` is `false`. |
+    /// | `InvalidAmount` | `usage_amount` is zero or negative. |
+    /// | `UsageCapExceeded` | Would exceed the subscriber's per-period cap. |
+    /// | `InsufficientBalance` | Prepaid balance cannot cover the debit. |
+    pub fn charge_usage(
+        env: Env,
+        subscription_id: u64,
+        usage_amount: i128,
+        usage_id: BytesN<32>,
+    ) -> Result<UsageChargeReceipt, Error> {
+        charge_core::charge_usage_one(&env, subscription_id, usage_amount, usage_id)
+    }
+
+    /// Charge `units` of metered usage at the subscription's stored `usage_rate`,
+    /// or across its usage tiers when any are set (see `set_usage_tiers`).
+    ///
+    /// Computes the amount with checked math and debits it like
+    /// `charge_usage`, including `usage_id` deduplication. Returns the same
+    /// receipt as `charge_usage`, with the computed token amount (the original
+    /// amount for a repeated `usage_id`). Emits `usage_units` with `(units, amount)`.
+    ///
+    /// # Errors
+    ///
+    /// Same as `charge_usage`, plus `InvalidAmount` for zero units or no
+    /// price, and `Overflow` if the amount does not fit in `i128`.
+    pub fn charge_usage_units(
+        env: Env,
+        subscription_id: u64,
+        units: u64,
+        usage_id: BytesN<32>,
+    ) -> Result<UsageChargeReceipt, Error> {
+        charge_core::charge_usage_units_one(&env, subscription_id, units, usage_id)
+    }
+
+    /// Charge usage from a report signed by the merchant's attestation key,
+    /// so reports can come from a trusted enclave and be relayed by anyone.
     ///
-    /// # Security
+    /// `signature` is an ed25519 signature over the XDR encoding of
+    /// `(contract_id, subscription_id, usage_amount, nonce)`. `nonce` must be
+    /// greater than `last_usage_nonce` on the subscription. Returns the same
+    /// receipt as `charge_usage`.
     ///
-    /// - Immediate effect — old admin loses access instantly.
-    /// - Irreversible without the new admin's cooperation.
-    /// - Emits an `admin_rotation` event for audit trail.
-    pub fn rotate_admin(env: Env, current_admin: Address, new_admin: Address) -> Result<(), Error> {
-        admin::do_rotate_admin(&env, current_admin, new_admin)
+    /// # Errors
+    ///
+    /// Same as `charge_usage`, plus `InvalidAttestation` when the merchant has
+    /// no key registered or the nonce is not fresh. A signature that does not
+    /// verify traps the host (the SDK offers no fallible check), so it fails
+    /// with an invoke error rather than `InvalidAttestation`.
+    pub fn charge_usage_attested(
+        env: Env,
+        subscription_id: u64,
+        usage_amount: i128,
+        nonce: u64,
+        signature: BytesN<64>,
+    ) -> Result<UsageChargeReceipt, Error> {
+        charge_core::charge_usage_attested_one(
+            &env,
+            subscription_id,
+            usage_amount,
+            nonce,
+            signature,
+        )
     }
 
-    /// **ADMIN ONLY**: Recover stranded funds from the contract.
+    /// Merchant (as `operator`) commits a batch of usage as the merkle `root`
+    /// of `(subscription_id, amount)` leaves summing to `total`. Nothing is
+    /// debited yet; leaves are applied later with `apply_usage_leaf`. Emits
+    /// `usage_root`. Returns the root id.
+    pub fn settle_usage_batch(
+        env: Env,
+        operator: Address,
+        root: BytesN<32>,
+        total: i128,
+    ) -> Result<u32, Error> {
+        usage_batch::do_settle_usage_batch(&env, operator, root, total)
+    }
+
+    /// Apply one leaf of a posted usage root. Anyone may call; each leaf
+    /// applies at most once per root (`Replay`). The debit follows the usage
+    /// rules, except that a short balance is handled like a partial interval
+    /// charge when the subscription allows it.
     ///
-    /// Tightly-scoped mechanism for recovering funds that have become
-    /// inaccessible through normal operations. Each recovery emits a
-    /// `RecoveryEvent` with full audit details.
-    pub fn recover_stranded_funds(
+    /// # Errors
+    ///
+    /// Same as `charge_usage`, plus `InvalidMerkleProof` for a bad proof or a
+    /// leaf that would exceed the root's total, and `Unauthorized` if the
+    /// subscription does not belong to the root's operator.
+    pub fn apply_usage_leaf(
         env: Env,
-        admin: Address,
-        recipient: Address,
+        root_id: u32,
+        proof: Vec<BytesN<32>>,
+        subscription_id: u64,
         amount: i128,
-        reason: RecoveryReason,
     ) -> Result<(), Error> {
-        admin::do_recover_stranded_funds(&env, admin, recipient, amount, reason)
+        usage_batch::do_apply_usage_leaf(&env, root_id, proof, subscription_id, amount)
     }
 
-    /// Charge a batch of subscriptions in one transaction. Admin only.
-    ///
-    /// Returns a per-subscription result vector so callers can identify
-    /// which charges succeeded and which failed (with error codes).
-    pub fn batch_charge(
+    /// Read a posted usage root.
+    pub fn get_usage_root(env: Env, root_id: u32) -> Result<UsageRoot, Error> {
+        usage_batch::get_usage_root(&env, root_id)
+    }
+
+    /// Cap total usage charges per billing period (0 = unlimited). Subscriber
+    /// only. Usage charges that would exceed the cap fail with
+    /// `UsageCapExceeded`; the running total resets on each interval charge.
+    pub fn set_usage_cap(
         env: Env,
-        subscription_ids: Vec<u32>,
-    ) -> Result<Vec<BatchChargeResult>, Error> {
-        admin::do_batch_charge(&env, &subscription_ids)
+        subscription_id: u64,
+        subscriber: Address,
+        cap: i128,
+    ) -> Result<(), Error> {
+        subscription::do_set_usage_cap(&env, subscription_id, subscriber, cap)
     }
 
-    // ── Subscription lifecycle ───────────────────────────────────────────
+    /// Pay down arrears left by a partial charge from the prepaid balance.
+    /// Subscriber only. Returns the amount settled; emits `settled` with
+    /// `(settled, remaining_arrears)`. A subscription with arrears cannot be
+    /// resumed (`ArrearsOutstanding`).
+    pub fn settle_arrears(
+        env: Env,
+        subscription_id: u64,
+        subscriber: Address,
+    ) -> Result<i128, Error> {
+        charge_core::settle_arrears_one(&env, subscription_id, subscriber)
+    }
 
-    /// Create a new subscription. Caller deposits initial USDC; contract stores agreement.
-    pub fn create_subscription(
+    /// Set volume pricing tiers for `charge_usage_units`. Requires both the
+    /// subscriber's and the merchant's signatures. Tiers must have strictly
+    /// increasing `up_to` and positive rates (`InvalidUsageTiers`); an empty
+    /// list reverts to the flat `usage_rate`.
+    pub fn set_usage_tiers(
         env: Env,
+        subscription_id: u64,
+        tiers: Vec<UsageTier>,
+    ) -> Result<(), Error> {
+        subscription::do_set_usage_tiers(&env, subscription_id, tiers)
+    }
+
+    /// Set the balance below which a `low_bal` warning is emitted (0 = off).
+    /// Defaults to the subscription `amount`. Subscriber only.
+    pub fn set_low_balance_threshold(
+        env: Env,
+        subscription_id: u64,
         subscriber: Address,
+        threshold: i128,
+    ) -> Result<(), Error> {
+        subscription::do_set_low_balance_threshold(&env, subscription_id, subscriber, threshold)
+    }
+
+    /// Set or clear (`None`) the merchant contract whose
+    /// `on_charge(subscription_id, subscriber, amount)` is invoked after each
+    /// successful `charge_subscription` or `batch_charge` item. With
+    /// `required`, a failing callback aborts the call with `CallbackFailed`,
+    /// so the charge is not applied; otherwise the failure is ignored and
+    /// `cb_failed` is emitted. Merchant only.
+    pub fn set_charge_callback(
+        env: Env,
+        subscription_id: u64,
         merchant: Address,
-        amount: i128,
-        interval_seconds: u64,
-        usage_enabled: bool,
-    ) -> Result<u32, Error> {
-        subscription::do_create_subscription(
+        callback: Option<Address>,
+        required: bool,
+    ) -> Result<(), Error> {
+        subscription::do_set_charge_callback(&env, subscription_id, merchant, callback, required)
+    }
+
+    /// Change the number of seats billed per interval. Subscriber only.
+    ///
+    /// Stored as `pending_quantity` and billed from the next interval charge
+    /// on, with no proration of the current period. Fails with `InvalidAmount`
+    /// for 0 and `Overflow` if `amount * new_quantity` does not fit.
+    pub fn update_quantity(
+        env: Env,
+        subscription_id: u64,
+        subscriber: Address,
+        new_quantity: u32,
+    ) -> Result<(), Error> {
+        subscription::do_update_quantity(&env, subscription_id, subscriber, new_quantity)
+    }
+
+    /// Like `update_quantity`, but an increase takes effect immediately and the
+    /// rest of the current period is billed now:
+    /// `(new_total - old_total) * remaining_seconds / interval_seconds`,
+    /// rounded down. The billing anchor is unchanged. Fails with
+    /// `InsufficientBalance` (changing nothing) if the subscription's
+    /// funds cannot cover it, and `NotActive` unless `Active`. A decrease
+    /// credits nothing and waits for the next charge. Returns the amount
+    /// charged. Emits `prorated` with `(old_quantity, new_quantity, amount)`.
+    pub fn update_quantity_prorated(
+        env: Env,
+        subscription_id: u64,
+        subscriber: Address,
+        new_quantity: u32,
+    ) -> Result<i128, Error> {
+        charge_core::update_quantity_prorated_one(&env, subscription_id, subscriber, new_quantity)
+    }
+
+    /// Merchant sets loyalty terms: after `after_periods` consecutive
+    /// successful full interval charges, further interval charges are
+    /// discounted by `discount_bps` (at most 10_000, else `InvalidAmount`).
+    /// A failed or partial charge, an `InsufficientBalance` episode or
+    /// cancellation resets the streak. Either value 0 switches loyalty off.
+    pub fn set_loyalty_terms(
+        env: Env,
+        subscription_id: u64,
+        merchant: Address,
+        after_periods: u32,
+        discount_bps: u32,
+    ) -> Result<(), Error> {
+        subscription::do_set_loyalty_terms(
             &env,
-            subscriber,
+            subscription_id,
             merchant,
-            amount,
-            interval_seconds,
-            usage_enabled,
+            after_periods,
+            discount_bps,
         )
     }
 
-    /// Subscriber deposits more USDC into their prepaid vault.
-    ///
-    /// Rejects deposits below the configured minimum threshold.
-    pub fn deposit_funds(
+    /// Current payment streak, loyalty terms, and whether the next interval
+    /// charge is discounted.
+    pub fn get_loyalty_status(env: Env, subscription_id: u64) -> Result<LoyaltyStatus, Error> {
+        queries::get_loyalty_status(&env, subscription_id)
+    }
+
+    /// Set or clear (`None`) the subscription's opaque external reference.
+    /// Requires both the subscriber's and the merchant's signatures; at most
+    /// `MAX_METADATA_LEN` bytes (`MetadataTooLarge`).
+    pub fn set_metadata(
+        env: Env,
+        subscription_id: u64,
+        metadata: Option<Bytes>,
+    ) -> Result<(), Error> {
+        subscription::do_set_metadata(&env, subscription_id, metadata)
+    }
+
+    // ── Disputes ─────────────────────────────────────────────────────────
+
+    /// Subscriber contests the charge for billing period `period_seq` (the
+    /// `charged` event's `period_seq`). Only possible while the charge is inside the
+    /// settlement delay, and once per charge. The amount is frozen out of the
+    /// merchant's pending earnings until `resolve_dispute`. Returns the dispute id.
+    pub fn open_dispute(
         env: Env,
-        subscription_id: u32,
+        subscription_id: u64,
         subscriber: Address,
+        period_seq: u32,
+    ) -> Result<u32, Error> {
+        dispute::do_open_dispute(&env, subscription_id, subscriber, period_seq)
+    }
+
+    /// **ADMIN ONLY**: Settle an open dispute. `refund = true` credits the
+    /// amount back to the subscription's prepaid balance; `false` releases it
+    /// to the merchant.
+    pub fn resolve_dispute(
+        env: Env,
+        admin: Address,
+        dispute_id: u32,
+        refund: bool,
+    ) -> Result<(), Error> {
+        dispute::do_resolve_dispute(&env, admin, dispute_id, refund)
+    }
+
+    /// Read a dispute by id.
+    pub fn get_dispute(env: Env, dispute_id: u32) -> Result<Dispute, Error> {
+        dispute::get_dispute(&env, dispute_id)
+    }
+
+    // ── Merchant ─────────────────────────────────────────────────────────
+
+    /// Merchant withdraws accumulated USDC to `destination`, its own wallet or
+    /// e.g. a treasury. Only earnings past the settlement delay count; see
+    /// `get_merchant_balances`. With a locked payout destination any other
+    /// `destination` fails with `PayoutDestinationLocked`. Returns the amount
+    /// still withdrawable (`available`) afterwards.
+    pub fn withdraw_merchant_funds(
+        env: Env,
+        merchant: Address,
+        amount: i128,
+        destination: Address,
+    ) -> Result<i128, Error> {
+        let token = admin::get_token(&env)?;
+        merchant::withdraw_merchant_funds(&env, merchant, token, amount, destination)
+    }
+
+    /// Merchant withdraws its whole available balance in the `init` token to
+    /// its locked payout destination, or else to itself. Returns the amount
+    /// transferred; with nothing available it returns 0 and does nothing.
+    pub fn withdraw_merchant_all(env: Env, merchant: Address) -> Result<i128, Error> {
+        let token = admin::get_token(&env)?;
+        merchant::withdraw_merchant_all(&env, merchant, token)
+    }
+
+    /// Merchant withdraws accumulated earnings in a specific `token` to
+    /// `destination`. Returns the amount still withdrawable in that token.
+    pub fn withdraw_merchant_token_funds(
+        env: Env,
+        merchant: Address,
+        token: Address,
         amount: i128,
+        destination: Address,
+    ) -> Result<i128, Error> {
+        merchant::withdraw_merchant_funds(&env, merchant, token, amount, destination)
+    }
+
+    /// Merchant permanently restricts withdrawals and automatic payouts to
+    /// `destination`. The lock cannot be changed or lifted and moves with
+    /// `rotate_merchant`. Emits `payout_locked`.
+    pub fn lock_payout_destination(
+        env: Env,
+        merchant: Address,
+        destination: Address,
     ) -> Result<(), Error> {
-        subscription::do_deposit_funds(&env, subscription_id, subscriber, amount)
+        merchant::do_lock_payout_destination(&env, merchant, destination)
     }
 
-    /// Cancel the subscription. Allowed from Active, Paused, or InsufficientBalance.
-    /// Transitions to the terminal `Cancelled` state.
-    pub fn cancel_subscription(
+    /// The merchant's locked payout destination, if any.
+    pub fn get_payout_lock(env: Env, merchant: Address) -> Option<Address> {
+        merchant::get_payout_lock(&env, &merchant)
+    }
+
+    /// **ADMIN ONLY**: Register `merchant` under `name`, or rename and
+    /// reactivate an existing registration. Emits `merchant_registered`.
+    pub fn register_merchant(
         env: Env,
-        subscription_id: u32,
-        authorizer: Address,
+        admin: Address,
+        merchant: Address,
+        name: Symbol,
     ) -> Result<(), Error> {
-        subscription::do_cancel_subscription(&env, subscription_id, authorizer)
+        merchant::do_register_merchant(&env, admin, merchant, name)
     }
 
-    /// Subscriber withdraws their remaining prepaid_balance after cancellation.
-    pub fn withdraw_subscriber_funds(
+    /// **ADMIN ONLY**: Activate or deactivate a registered merchant. A
+    /// deactivated merchant gets no new subscriptions; existing ones keep
+    /// charging and its earnings stay withdrawable. Emits `merchant_active`.
+    pub fn set_merchant_active(
         env: Env,
-        subscription_id: u32,
-        subscriber: Address,
+        admin: Address,
+        merchant: Address,
+        active: bool,
     ) -> Result<(), Error> {
-        subscription::do_withdraw_subscriber_funds(&env, subscription_id, subscriber)
+        merchant::do_set_merchant_active(&env, admin, merchant, active)
     }
 
-    /// Pause subscription (no charges until resumed). Allowed from Active.
-    pub fn pause_subscription(
+    /// **ADMIN ONLY**: Grant or revoke the viewer role, which unlocks
+    /// `debug_subscription` and `debug_merchant` for support staff. Emits
+    /// `viewer_added` / `viewer_removed`.
+    pub fn set_viewer(
         env: Env,
-        subscription_id: u32,
-        authorizer: Address,
+        admin: Address,
+        viewer: Address,
+        enabled: bool,
     ) -> Result<(), Error> {
-        subscription::do_pause_subscription(&env, subscription_id, authorizer)
+        viewer::do_set_viewer(&env, admin, viewer, enabled)
     }
 
-    /// Resume a subscription to Active. Allowed from Paused or InsufficientBalance.
-    pub fn resume_subscription(
+    /// Whether `address` holds the viewer role.
+    pub fn is_viewer(env: Env, address: Address) -> bool {
+        viewer::is_viewer(&env, &address)
+    }
+
+    /// **VIEWER ONLY**: The subscription with its stats, charge history,
+    /// withdrawable balance, delegate payer and auto top-up config, in one
+    /// call. Fails with `Unauthorized` for addresses without the viewer role.
+    pub fn debug_subscription(
+        env: Env,
+        viewer: Address,
+        subscription_id: u64,
+    ) -> Result<SubscriptionDebug, Error> {
+        viewer::debug_subscription(&env, viewer, subscription_id)
+    }
+
+    /// **VIEWER ONLY**: A merchant's registration, balances in every
+    /// supported token, payout config and lock, minimum top-up, and its
+    /// subscription and plan indexes, in one call.
+    pub fn debug_merchant(
+        env: Env,
+        viewer: Address,
+        merchant: Address,
+    ) -> Result<MerchantDebug, Error> {
+        viewer::debug_merchant(&env, viewer, merchant)
+    }
+
+    /// The subscription's referrer, fee in bps and remaining fee-paying
+    /// charges, while any remain.
+    pub fn get_referral(env: Env, subscription_id: u64) -> Result<Option<Referral>, Error> {
+        referral::get_referral(&env, subscription_id)
+    }
+
+    /// Registration record of `merchant`, or `NotFound`.
+    pub fn get_merchant_info(env: Env, merchant: Address) -> Result<MerchantInfo, Error> {
+        merchant::get_merchant_info(&env, &merchant)
+    }
+
+    /// Merchant requires subscriptions created with a trial to post
+    /// `terms.bond`, or with `None` stops requiring it. The bond merges into
+    /// `prepaid_balance` at the first successful charge; if the subscription
+    /// ends first it is refunded less `forfeit_bps`, fixed at creation.
+    /// `InvalidAmount` for a non-positive bond or `forfeit_bps` above 10000.
+    /// Emits `trial_bond_terms`.
+    pub fn set_trial_bond_terms(
+        env: Env,
+        merchant: Address,
+        terms: Option<TrialBondTerms>,
+    ) -> Result<(), Error> {
+        trial_bond::do_set_trial_bond_terms(&env, merchant, terms)
+    }
+
+    /// The merchant's trial bond terms, if any.
+    pub fn get_trial_bond_terms(env: Env, merchant: Address) -> Option<TrialBondTerms> {
+        trial_bond::get_terms(&env, &merchant)
+    }
+
+    /// The bond a subscription posted for its trial, until it converts or ends.
+    pub fn get_trial_bond(env: Env, subscription_id: u64) -> Option<TrialBond> {
+        trial_bond::get(&env, subscription_id)
+    }
+
+    /// Admin or the merchant sets a merchant-specific minimum top-up, or
+    /// removes it with `None`. Deposits to the merchant's subscriptions must
+    /// meet the larger of this and the token's global minimum; removing the
+    /// override falls back to the global value. Emits `merchant_min_topup`.
+    pub fn set_merchant_min_topup(
         env: Env,
-        subscription_id: u32,
         authorizer: Address,
+        merchant: Address,
+        min_topup: Option<i128>,
     ) -> Result<(), Error> {
-        subscription::do_resume_subscription(&env, subscription_id, authorizer)
+        merchant::do_set_merchant_min_topup(&env, authorizer, merchant, min_topup)
     }
 
-    // ── Charging ─────────────────────────────────────────────────────────
+    /// The merchant's minimum top-up override, if any.
+    pub fn get_merchant_min_topup(env: Env, merchant: Address) -> Option<i128> {
+        merchant::get_merchant_min_topup(&env, &merchant)
+    }
 
-    /// Billing engine calls this to charge one interval.
-    ///
-    /// Enforces strict interval timing and replay protection.
-    pub fn charge_subscription(env: Env, subscription_id: u32) -> Result<(), Error> {
-        charge_core::charge_one(&env, subscription_id, None)
+    /// Minimum `deposit_funds` amount that applies to a subscription right now.
+    pub fn get_effective_min_topup(env: Env, subscription_id: u64) -> Result<i128, Error> {
+        merchant::get_effective_min_topup(&env, subscription_id)
     }
 
-    /// Charge a metered usage amount against the subscription's prepaid balance.
-    ///
-    /// Designed for integration with an **off-chain usage metering service**:
-    /// the service measures consumption, then calls this entrypoint with the
-    /// computed `usage_amount` to debit the subscriber's vault.
-    ///
-    /// # Requirements
-    ///
-    /// * The subscription must be `Active`.
-    /// * `usage_enabled` must be `true` on the subscription.
-    /// * `usage_amount` must be positive (`> 0`).
-    /// * `prepaid_balance` must be >= `usage_amount`.
-    ///
-    /// # Behaviour
-    ///
-    /// On success, `prepaid_balance` is reduced by `usage_amount`.  If the
-    /// debit drains the balance to zero the subscription transitions to
-    /// `InsufficientBalance` status, signalling that no further charges
-    /// (interval or usage) can proceed until the subscriber tops up.
+    /// Merchant registers the ed25519 public key that signs its usage reports,
+    /// or removes it with `None`. While a key is set, `charge_usage` and
+    /// `charge_usage_units` fail with `InvalidAttestation` for the merchant's
+    /// subscriptions and usage must go through `charge_usage_attested`.
+    pub fn set_usage_attestation_key(
+        env: Env,
+        merchant: Address,
+        public_key: Option<BytesN<32>>,
+    ) -> Result<(), Error> {
+        merchant::do_set_usage_attestation_key(&env, merchant, public_key)
+    }
+
+    /// The merchant's registered usage attestation key, if any.
+    pub fn get_usage_attestation_key(env: Env, merchant: Address) -> Option<BytesN<32>> {
+        merchant::get_usage_attestation_key(&env, &merchant)
+    }
+
+    /// Move a merchant identity to a new address (key rotation, multisig
+    /// treasury). Both addresses must sign. Accrued balances move now; existing
+    /// subscriptions and plans follow lazily via a redirect, so future charges,
+    /// refunds and withdrawals go to `new_merchant`. Emits `merchant_rotated`.
+    pub fn rotate_merchant(
+        env: Env,
+        old_merchant: Address,
+        new_merchant: Address,
+    ) -> Result<(), Error> {
+        merchant::do_rotate_merchant(&env, old_merchant, new_merchant)
+    }
+
+    /// Merchant returns `amount` of their accrued balance to a subscription's
+    /// prepaid balance (e.g. to reverse an erroneous charge).
     ///
-    /// # Errors
+    /// Works on `Cancelled` subscriptions too, increasing the subscriber's
+    /// withdrawable remainder. Fails with `InsufficientMerchantBalance` if the
+    /// merchant has not accrued enough, or `InvalidAmount` for `amount <= 0`.
+    pub fn refund(
+        env: Env,
+        subscription_id: u64,
+        merchant: Address,
+        amount: i128,
+    ) -> Result<(), Error> {
+        merchant::do_refund(&env, subscription_id, merchant, amount)
+    }
+
+    /// Merchant credits `amount` of their accrued balance to a subscription's
+    /// prepaid balance to correct over-metered usage, without a token transfer.
     ///
-    /// | Variant | Reason |
-    /// |---------|--------|
-    /// | `NotFound` | Subscription ID does not exist. |
-    /// | `NotActive` | Subscription is not `Active`. |
-    /// | `UsageNotEnabled` | `usage_enabled` is `false`. |
-    /// | `InvalidAmount` | `usage_amount` is zero or negative. |
-    /// | `InsufficientPrepaidBalance` | Prepaid balance cannot cover the debit. |
-    pub fn charge_usage(env: Env, subscription_id: u32, usage_amount: i128) -> Result<(), Error> {
-        charge_core::charge_usage_one(&env, subscription_id, usage_amount)
+    /// Same checks as `refund`, but recorded as a `Credit` in the charge
+    /// history and in `total_credited` of the subscription stats, so credits
+    /// and refunds reconcile separately. Emits `credit`.
+    pub fn issue_usage_credit(
+        env: Env,
+        merchant: Address,
+        subscription_id: u64,
+        amount: i128,
+    ) -> Result<(), Error> {
+        merchant::do_issue_usage_credit(&env, merchant, subscription_id, amount)
     }
 
-    // ── Merchant ─────────────────────────────────────────────────────────
+    /// Accrued, not yet withdrawn earnings of a merchant in the `init` token.
+    pub fn get_merchant_balance(env: Env, merchant: Address) -> Result<i128, Error> {
+        let token = admin::get_token(&env)?;
+        Ok(merchant::get_merchant_balance(&env, &merchant, &token))
+    }
+
+    /// Accrued, not yet withdrawn earnings of a merchant in `token`.
+    pub fn get_merchant_token_balance(env: Env, merchant: Address, token: Address) -> i128 {
+        merchant::get_merchant_balance(&env, &merchant, &token)
+    }
+
+    /// A merchant's earnings in `token`, split into the part withdrawable now
+    /// and the part still inside the settlement delay.
+    pub fn get_merchant_balances(env: Env, merchant: Address, token: Address) -> MerchantBalances {
+        merchant::get_merchant_balances(&env, &merchant, &token)
+    }
+
+    /// A merchant's aggregate numbers in the `init` token: active
+    /// subscribers, revenue all-time and in the current 30-day window, and
+    /// available vs pending balance. Kept incrementally, so the cost does not
+    /// grow with the merchant's subscription count.
+    pub fn get_merchant_stats(env: Env, merchant: Address) -> Result<MerchantStats, Error> {
+        merchant_stats::get_merchant_stats(&env, &merchant)
+    }
+
+    /// Merchant opts in to automatic payouts: whenever a charge leaves more
+    /// than `threshold` available in the charged token, the whole available
+    /// balance is sent to `destination` in the same invocation. A failed
+    /// transfer never fails the charge. Emits `payout_config`.
+    pub fn set_payout_config(
+        env: Env,
+        merchant: Address,
+        destination: Address,
+        threshold: i128,
+        enabled: bool,
+    ) -> Result<(), Error> {
+        merchant::do_set_payout_config(&env, merchant, destination, threshold, enabled)
+    }
 
-    /// Merchant withdraws accumulated USDC to their wallet.
-    pub fn withdraw_merchant_funds(env: Env, merchant: Address, amount: i128) -> Result<(), Error> {
-        merchant::withdraw_merchant_funds(&env, merchant, amount)
+    /// Merchant's auto-payout config, `None` if never set.
+    pub fn get_payout_config(env: Env, merchant: Address) -> Option<PayoutConfig> {
+        merchant::get_payout_config(&env, &merchant)
     }
 
     // ── Queries ──────────────────────────────────────────────────────────
 
+    /// The status transition table: every status with the statuses it may move
+    /// to. Generated from the same rules the contract enforces, so clients can
+    /// render valid actions without copying the table.
+    pub fn describe_state_machine(env: Env) -> Vec<(SubscriptionStatus, Vec<SubscriptionStatus>)> {
+        state_machine::describe_state_machine(&env)
+    }
+
     /// Read subscription by id.
-    pub fn get_subscription(env: Env, subscription_id: u32) -> Result<Subscription, Error> {
+    pub fn get_subscription(env: Env, subscription_id: u64) -> Result<Subscription, Error> {
         queries::get_subscription(&env, subscription_id)
     }
 
+    /// Publish the full current record as a `snapshot` event, so an indexer
+    /// can resynchronize it with one transaction. Anyone may call it.
+    pub fn snapshot(env: Env, subscription_id: u64) -> Result<(), Error> {
+        snapshot::do_snapshot(&env, subscription_id)
+    }
+
+    /// Extend the storage TTL of a subscription so it is not archived between
+    /// charges. Anyone may call it; the billing engine calls it
+    /// opportunistically for long-idle subscriptions. Every write to a
+    /// subscription does this too. Cheap when the entry is already fresh.
+    pub fn extend_subscription_ttl(env: Env, subscription_id: u64) -> Result<(), Error> {
+        queries::get_subscription(&env, subscription_id)?;
+        ttl::bump_subscription(&env, subscription_id);
+        history::bump(&env, subscription_id);
+        Ok(())
+    }
+
+    /// The last `CHARGE_HISTORY_LEN` successful charges (interval and usage),
+    /// oldest first, each with its timestamp, amount, kind and `seq`. Older
+    /// charges roll off; the `charged`/`usage` events keep the full record.
+    pub fn get_charge_history(
+        env: Env,
+        subscription_id: u64,
+    ) -> Result<Vec<ChargeHistoryEntry>, Error> {
+        queries::get_subscription(&env, subscription_id)?;
+        Ok(history::get_charge_history(&env, subscription_id))
+    }
+
+    /// Read up to `MAX_BULK_READ` subscriptions in one call, in the order of
+    /// `ids`. Ids that do not exist come back as `None`. Pairs with
+    /// `list_subscriptions_by_subscriber` and `get_subscriptions_by_merchant`.
+    ///
+    /// # Errors
+    ///
+    /// `BatchTooLarge` if `ids` holds more than `MAX_BULK_READ` (50) entries.
+    pub fn get_subscriptions(env: Env, ids: Vec<u64>) -> Result<Vec<Option<Subscription>>, Error> {
+        queries::get_subscriptions(&env, ids)
+    }
+
+    /// Lifetime billing stats (totals, charge count, last charge amount).
+    pub fn get_subscription_stats(
+        env: Env,
+        subscription_id: u64,
+    ) -> Result<SubscriptionStats, Error> {
+        queries::get_subscription_stats(&env, subscription_id)
+    }
+
+    /// Maximum the subscriber can withdraw right now via `withdraw_subscriber_excess`.
+    pub fn get_withdrawable_balance(env: Env, subscription_id: u64) -> Result<i128, Error> {
+        queries::get_withdrawable_balance(&env, subscription_id)
+    }
+
+    /// Pending merchant-proposed usage toggle, if any.
+    pub fn get_pending_usage_toggle(
+        env: Env,
+        subscription_id: u64,
+    ) -> Result<Option<UsageToggle>, Error> {
+        usage_toggle::get_pending_usage_toggle(&env, subscription_id)
+    }
+
+    /// Pending merchant-proposed amount, if any.
+    pub fn get_pending_amount_change(
+        env: Env,
+        subscription_id: u64,
+    ) -> Result<Option<i128>, Error> {
+        queries::get_pending_amount_change(&env, subscription_id)
+    }
+
+    /// Usage tiers for a subscription; empty when flat pricing applies.
+    pub fn get_usage_tiers(env: Env, subscription_id: u64) -> Result<Vec<UsageTier>, Error> {
+        queries::get_usage_tiers(&env, subscription_id)
+    }
+
     /// Estimate how much a subscriber needs to deposit to cover N future intervals.
     pub fn estimate_topup_for_intervals(
         env: Env,
-        subscription_id: u32,
+        subscription_id: u64,
         num_intervals: u32,
     ) -> Result<i128, Error> {
         queries::estimate_topup_for_intervals(&env, subscription_id, num_intervals)
     }
 
     /// Get estimated next charge info (timestamp + whether charge is expected).
-    pub fn get_next_charge_info(env: Env, subscription_id: u32) -> Result<NextChargeInfo, Error> {
+    pub fn get_next_charge_info(env: Env, subscription_id: u64) -> Result<NextChargeInfo, Error> {
         let sub = queries::get_subscription(&env, subscription_id)?;
         Ok(compute_next_charge_info(&sub))
     }
@@ -273,7 +2021,7 @@ impl SubscriptionVault {
     pub fn list_subscriptions_by_subscriber(
         env: Env,
         subscriber: Address,
-        start_from_id: u32,
+        start_from_id: u64,
         limit: u32,
     ) -> Result<crate::queries::SubscriptionsPage, Error> {
         crate::queries::list_subscriptions_by_subscriber(&env, subscriber, start_from_id, limit)
@@ -290,7 +2038,7 @@ impl SubscriptionVault {
     /// Migrate storage layout from a previous schema version to the current one.
     ///
     /// Currently handles the v0 → v1 transition, which re-keys all subscriptions
-    /// from bare `u32` keys to typed `DataKey::Sub(u32)` keys.  The function is
+    /// from bare `u32` keys to typed `DataKey::Sub(u64)` keys.  The function is
     /// idempotent: subscriptions already stored under `DataKey::Sub` are not
     /// touched.
     ///
@@ -303,43 +2051,41 @@ impl SubscriptionVault {
             .storage()
             .instance()
             .get(&DataKey::Admin)
-            .ok_or(Error::NotFound)?;
+            .ok_or(Error::NotInitialized)?;
         if admin != stored_admin {
             return Err(Error::Unauthorized);
         }
 
         if from_version == 0 {
             // v0 → v1: subscriptions were keyed by bare u32; re-key them under DataKey::Sub.
-            let next_id: u32 = env
+            let next_id: u64 = env
                 .storage()
                 .instance()
                 .get(&DataKey::NextId)
                 .unwrap_or_else(|| {
-                    // v0 stored next_id under a Symbol key
+                    // v0 stored a u32 next_id under a Symbol key
                     let old_key = soroban_sdk::Symbol::new(&env, "next_id");
                     let n: u32 = env.storage().instance().get(&old_key).unwrap_or(0);
                     // Migrate the counter key itself
                     if n > 0 {
-                        env.storage().instance().set(&DataKey::NextId, &n);
+                        env.storage()
+                            .instance()
+                            .set(&DataKey::NextId, &u64::from(n));
                     }
-                    n
+                    u64::from(n)
                 });
 
-            for id in 0..next_id {
-                if env
-                    .storage()
-                    .instance()
-                    .get::<DataKey, crate::types::Subscription>(&DataKey::Sub(id))
-                    .is_some()
-                {
+            // v0 ids never exceeded u32::MAX.
+            for id in 0..next_id.min(u64::from(u32::MAX)) {
+                if stored_sub::has_subscription(&env, id) {
                     continue; // Already migrated
                 }
                 if let Some(sub) = env
                     .storage()
                     .instance()
-                    .get::<u32, crate::types::Subscription>(&id)
+                    .get::<u32, crate::types::Subscription>(&(id as u32))
                 {
-                    env.storage().instance().set(&DataKey::Sub(id), &sub);
+                    save_subscription(&env, id, &mut LoadedSubscription::new(sub))?;
                 }
             }
 
@@ -352,5 +2098,8 @@ impl SubscriptionVault {
     }
 }
 
+#[cfg(any(test, feature = "testutils"))]
+pub mod testutils;
+
 #[cfg(test)]
 mod test;
@@ -0,0 +1,92 @@
+//! Lifetime spending cap: a subscriber-set ceiling on everything a
+//! subscription may ever charge, interval and usage combined.
+//!
+//! **PRs that only change the lifetime cap should edit this file only.**
+
+use crate::queries::{get_subscription, get_subscription_stats};
+use crate::stored_sub::Optional;
+use crate::types::Error;
+use soroban_sdk::{symbol_short, Address, Env};
+
+/// The cap, or 0 when unlimited.
+pub fn get(env: &Env, subscription_id: u64) -> i128 {
+    crate::stored_sub::get_optional(env, subscription_id, Optional::LifetimeCap).unwrap_or(0)
+}
+
+fn set(env: &Env, subscription_id: u64, cap: i128) {
+    if cap > 0 {
+        crate::stored_sub::set_optional(env, subscription_id, Optional::LifetimeCap, &cap);
+    } else {
+        crate::stored_sub::remove_optional(env, subscription_id, Optional::LifetimeCap);
+    }
+}
+
+pub fn remove(env: &Env, subscription_id: u64) {
+    set(env, subscription_id, 0);
+}
+
+/// Everything charged so far: interval (including proration and settled
+/// arrears) plus usage.
+fn charged_total(env: &Env, subscription_id: u64) -> Result<i128, Error> {
+    let stats = get_subscription_stats(env, subscription_id)?;
+    stats
+        .total_charged
+        .checked_add(stats.total_usage_charged)
+        .ok_or(Error::Overflow)
+}
+
+/// `LifetimeCapExceeded` unless `amount` more fits under the cap. Charge
+/// paths call this before touching any state.
+pub fn check(env: &Env, subscription_id: u64, amount: i128) -> Result<(), Error> {
+    let cap = get(env, subscription_id);
+    if cap == 0 {
+        return Ok(());
+    }
+    let total = charged_total(env, subscription_id)?
+        .checked_add(amount)
+        .ok_or(Error::Overflow)?;
+    if total > cap {
+        return Err(Error::LifetimeCapExceeded);
+    }
+    Ok(())
+}
+
+pub fn get_lifetime_cap(env: &Env, subscription_id: u64) -> Result<i128, Error> {
+    get_subscription(env, subscription_id)?;
+    Ok(get(env, subscription_id))
+}
+
+/// Cap chosen at creation; negative is `InvalidAmount`.
+pub fn init(env: &Env, subscription_id: u64, cap: i128) -> Result<(), Error> {
+    if cap < 0 {
+        return Err(Error::InvalidAmount);
+    }
+    set(env, subscription_id, cap);
+    Ok(())
+}
+
+/// Change the cap. Subscriber only. 0 removes it; otherwise it may not be
+/// below what has already been charged (`InvalidAmount`). Emits `life_cap`
+/// with `(old, new)`.
+pub fn do_set_lifetime_cap(
+    env: &Env,
+    subscription_id: u64,
+    subscriber: Address,
+    cap: i128,
+) -> Result<(), Error> {
+    subscriber.require_auth();
+
+    let sub = get_subscription(env, subscription_id)?;
+    if subscriber != sub.subscriber {
+        return Err(Error::Unauthorized);
+    }
+    if cap < 0 || (cap > 0 && cap < charged_total(env, subscription_id)?) {
+        return Err(Error::InvalidAmount);
+    }
+
+    let old = get(env, subscription_id);
+    set(env, subscription_id, cap);
+    env.events()
+        .publish((symbol_short!("life_cap"), subscription_id), (old, cap));
+    Ok(())
+}
@@ -0,0 +1,89 @@
+//! Basis-point and proration arithmetic shared by fees, discounts, referral
+//! cuts, trial bond forfeits and prorated charges.
+//!
+//! **PRs that only change rounding rules should edit this file only.**
+//!
+//! Every helper splits `amount` into a quotient and remainder of the
+//! denominator before multiplying, so `amount * numerator` never has to fit
+//! in an `i128`: any amount works with up to 100% (`MAX_BPS`, or `elapsed <=
+//! total < 2^63`). Results that do not fit return `Error::Overflow`. The rounding
+//! direction is part of each helper's contract; call sites pick the helper
+//! whose direction favours the party that should keep the fraction.
+
+use crate::Error;
+
+/// Basis points in 100%.
+pub const MAX_BPS: u32 = 10_000;
+
+const BPS: i128 = MAX_BPS as i128;
+
+/// `amount * bps / 10_000`, rounded down (toward negative infinity).
+///
+/// ```
+/// use subscription_vault::math::mul_bps_floor;
+///
+/// assert_eq!(mul_bps_floor(999, 2_500), Ok(249));
+/// assert_eq!(mul_bps_floor(-999, 2_500), Ok(-250));
+/// assert_eq!(mul_bps_floor(i128::MAX, 10_000), Ok(i128::MAX));
+/// ```
+pub fn mul_bps_floor(amount: i128, bps: u32) -> Result<i128, Error> {
+    let (whole, part) = split_bps(amount, bps)?;
+    whole
+        .checked_add(part.div_euclid(BPS))
+        .ok_or(Error::Overflow)
+}
+
+/// `amount * bps / 10_000`, rounded up (toward positive infinity).
+///
+/// ```
+/// use subscription_vault::math::mul_bps_ceil;
+///
+/// assert_eq!(mul_bps_ceil(999, 2_500), Ok(250));
+/// assert_eq!(mul_bps_ceil(-999, 2_500), Ok(-249));
+/// assert_eq!(mul_bps_ceil(1, 1), Ok(1));
+/// ```
+pub fn mul_bps_ceil(amount: i128, bps: u32) -> Result<i128, Error> {
+    let (whole, part) = split_bps(amount, bps)?;
+    let up = i128::from(part.rem_euclid(BPS) != 0);
+    whole
+        .checked_add(part.div_euclid(BPS) + up)
+        .ok_or(Error::Overflow)
+}
+
+/// `(q * bps, r * bps)` for `amount = q * 10_000 + r`, with `q` truncated so
+/// that `|q * 10_000| <= |amount|` (a floored `q` overflows at `i128::MIN`).
+/// The callers round `r * bps / 10_000`, the only fractional part.
+fn split_bps(amount: i128, bps: u32) -> Result<(i128, i128), Error> {
+    let bps = i128::from(bps);
+    let whole = (amount / BPS).checked_mul(bps).ok_or(Error::Overflow)?;
+    Ok((whole, amount % BPS * bps))
+}
+
+/// `amount * elapsed / total`, rounded toward zero, so neither a charge nor a
+/// credit is ever rounded up. `Error::Overflow` if `total` is 0.
+///
+/// ```
+/// use subscription_vault::math::prorate;
+/// use subscription_vault::Error;
+///
+/// assert_eq!(prorate(100, 1, 3), Ok(33));
+/// assert_eq!(prorate(-100, 1, 3), Ok(-33));
+/// assert_eq!(prorate(i128::MAX, 5, 5), Ok(i128::MAX));
+/// assert_eq!(prorate(100, 1, 0), Err(Error::Overflow));
+/// ```
+pub fn prorate(amount: i128, elapsed: u64, total: u64) -> Result<i128, Error> {
+    if total == 0 {
+        return Err(Error::Overflow);
+    }
+    let (elapsed, total) = (i128::from(elapsed), i128::from(total));
+    // Truncating division keeps the remainder's sign equal to `amount`'s, so
+    // both terms round the same way.
+    let whole = (amount / total)
+        .checked_mul(elapsed)
+        .ok_or(Error::Overflow)?;
+    let part = (amount % total)
+        .checked_mul(elapsed)
+        .ok_or(Error::Overflow)?
+        / total;
+    whole.checked_add(part).ok_or(Error::Overflow)
+}
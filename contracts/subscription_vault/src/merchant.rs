@@ -1,15 +1,773 @@
-//! Merchant entrypoints: withdraw_merchant_funds.
+//! Merchant entrypoints: withdraw_merchant_funds, refund, merchant balance ledger.
 //!
 //! **PRs that only change merchant payouts should edit this file only.**
 
-use crate::safe_math::validate_non_negative;
-use crate::types::Error;
-use soroban_sdk::{Address, Env, Symbol};
+use crate::admin::{get_token_min_topup, require_admin, require_not_paused};
+use crate::queries::get_subscription;
+use crate::stats::{load_subscription, save_subscription, LoadedSubscription};
+use crate::types::{
+    DataKey, DataKeyExt, Error, MerchantBalances, MerchantInfo, MerchantRotatedEvent, PayoutConfig,
+    PendingBucket, Subscription, SubscriptionRefundedEvent, UsageCreditEvent, MAX_PENDING_BUCKETS,
+};
+use soroban_sdk::{symbol_short, Address, BytesN, Env, IntoVal, Symbol, TryFromVal, Val, Vec};
 
-pub fn withdraw_merchant_funds(env: &Env, merchant: Address, amount: i128) -> Result<(), Error> {
+/// Accrued balance of `merchant` in `token`, pending or not. Balances are
+/// kept per token since subscriptions may bill in different assets.
+pub fn get_merchant_balance(env: &Env, merchant: &Address, token: &Address) -> i128 {
+    env.storage()
+        .instance()
+        .get(&DataKey::MerchantBalance(merchant.clone(), token.clone()))
+        .unwrap_or(0)
+}
+
+fn set_merchant_balance(env: &Env, merchant: &Address, token: &Address, balance: i128) {
+    env.storage().instance().set(
+        &DataKey::MerchantBalance(merchant.clone(), token.clone()),
+        &balance,
+    );
+}
+
+fn add_merchant_balance(
+    env: &Env,
+    merchant: &Address,
+    token: &Address,
+    amount: i128,
+) -> Result<(), Error> {
+    let balance = get_merchant_balance(env, merchant, token)
+        .checked_add(amount)
+        .ok_or(Error::Overflow)?;
+    set_merchant_balance(env, merchant, token, balance);
+    Ok(())
+}
+
+/// Credit `amount` to the merchant's accrued balance. Called by the charge
+/// paths. With a settlement delay configured the amount is also held pending
+/// until `now + delay`.
+pub fn credit_merchant_balance(
+    env: &Env,
+    merchant: &Address,
+    token: &Address,
+    amount: i128,
+) -> Result<(), Error> {
+    crate::merchant_stats::record_charge(env, merchant, token, amount)?;
+    credit_earnings(env, merchant, token, amount)
+}
+
+/// Credit `amount` to `payee`'s balance without counting it as merchant
+/// revenue; referrers are paid this way.
+pub(crate) fn credit_earnings(
+    env: &Env,
+    payee: &Address,
+    token: &Address,
+    amount: i128,
+) -> Result<(), Error> {
+    add_merchant_balance(env, payee, token, amount)?;
+    let delay = crate::admin::get_settlement_delay(env);
+    if delay > 0 && amount > 0 {
+        let release_at = bucket_release_at(env.ledger().timestamp(), delay)?;
+        add_pending(env, payee, token, release_at, amount)?;
+    }
+    Ok(())
+}
+
+/// Take `amount` out of the merchant's balance. Withdrawals may only use
+/// released funds; refunds may also dip into pending, newest first.
+fn debit_merchant_balance(
+    env: &Env,
+    merchant: &Address,
+    token: &Address,
+    amount: i128,
+    include_pending: bool,
+) -> Result<i128, Error> {
+    let balance = get_merchant_balance(env, merchant, token);
+    let mut pending = live_pending(env, merchant, token);
+    let held = pending_total(&pending)?;
+    let spendable = if include_pending {
+        balance
+    } else {
+        balance.checked_sub(held).ok_or(Error::Overflow)?
+    };
+    if spendable < amount {
+        return Err(Error::InsufficientMerchantBalance);
+    }
+    let remaining = balance.checked_sub(amount).ok_or(Error::Overflow)?;
+    trim_pending(&mut pending, held, remaining)?;
+    set_pending(env, merchant, token, &pending);
+    set_merchant_balance(env, merchant, token, remaining);
+    Ok(remaining)
+}
+
+/// Drop pending amounts, newest first, until `held` fits in `balance`.
+fn trim_pending(pending: &mut Vec<PendingBucket>, held: i128, balance: i128) -> Result<(), Error> {
+    let mut excess = held.checked_sub(balance).ok_or(Error::Overflow)?;
+    while excess > 0 {
+        let mut newest = pending.pop_back().ok_or(Error::Overflow)?;
+        if newest.amount > excess {
+            newest.amount -= excess;
+            pending.push_back(newest);
+            break;
+        }
+        excess -= newest.amount;
+    }
+    Ok(())
+}
+
+/// Take a disputed `amount` out of the merchant's earnings, preferring the
+/// pending buckets that release at or after the charge's `release_at`.
+pub fn freeze_merchant_funds(
+    env: &Env,
+    merchant: &Address,
+    token: &Address,
+    release_at: u64,
+    amount: i128,
+) -> Result<(), Error> {
+    let balance = get_merchant_balance(env, merchant, token);
+    if balance < amount {
+        return Err(Error::InsufficientMerchantBalance);
+    }
+    let mut left = amount;
+    let mut pending = Vec::new(env);
+    for mut bucket in live_pending(env, merchant, token).iter() {
+        if bucket.release_at >= release_at && left > 0 {
+            let take = bucket.amount.min(left);
+            bucket.amount -= take;
+            left -= take;
+        }
+        if bucket.amount > 0 {
+            pending.push_back(bucket);
+        }
+    }
+    let remaining = balance - amount;
+    let held = pending_total(&pending)?;
+    trim_pending(&mut pending, held, remaining)?;
+    set_pending(env, merchant, token, &pending);
+    set_merchant_balance(env, merchant, token, remaining);
+    Ok(())
+}
+
+/// Return a frozen dispute amount to the merchant, withdrawable immediately.
+pub fn unfreeze_merchant_funds(
+    env: &Env,
+    merchant: &Address,
+    token: &Address,
+    amount: i128,
+) -> Result<(), Error> {
+    add_merchant_balance(env, merchant, token, amount)
+}
+
+/// Release time for funds credited at `now`, rounded up to a bucket boundary
+/// so that at most about [`MAX_PENDING_BUCKETS`] buckets are live at once.
+fn bucket_release_at(now: u64, delay: u64) -> Result<u64, Error> {
+    let width = delay.div_ceil(MAX_PENDING_BUCKETS as u64);
+    let release_at = now.checked_add(delay).ok_or(Error::Overflow)?;
+    release_at
+        .div_ceil(width)
+        .checked_mul(width)
+        .ok_or(Error::Overflow)
+}
+
+/// Pending buckets not yet released, oldest first. Released buckets are
+/// dropped on read; their amounts are already part of the accrued balance.
+fn live_pending(env: &Env, merchant: &Address, token: &Address) -> Vec<PendingBucket> {
+    let now = env.ledger().timestamp();
+    let stored: Vec<PendingBucket> = env
+        .storage()
+        .instance()
+        .get(&DataKey::MerchantPending(merchant.clone(), token.clone()))
+        .unwrap_or(Vec::new(env));
+    let mut live = Vec::new(env);
+    for bucket in stored.iter() {
+        if bucket.release_at > now {
+            live.push_back(bucket);
+        }
+    }
+    live
+}
+
+fn set_pending(env: &Env, merchant: &Address, token: &Address, pending: &Vec<PendingBucket>) {
+    let key = DataKey::MerchantPending(merchant.clone(), token.clone());
+    if pending.is_empty() {
+        env.storage().instance().remove(&key);
+    } else {
+        env.storage().instance().set(&key, pending);
+    }
+}
+
+fn pending_total(pending: &Vec<PendingBucket>) -> Result<i128, Error> {
+    let mut total = 0i128;
+    for bucket in pending.iter() {
+        total = total.checked_add(bucket.amount).ok_or(Error::Overflow)?;
+    }
+    Ok(total)
+}
+
+/// Add `amount` to the bucket releasing at `release_at`, keeping buckets
+/// sorted. When the ring is full the two newest buckets merge, taking the
+/// later release time, so funds are never released early.
+fn add_pending(
+    env: &Env,
+    merchant: &Address,
+    token: &Address,
+    release_at: u64,
+    amount: i128,
+) -> Result<(), Error> {
+    let mut pending = live_pending(env, merchant, token);
+    let mut index = pending.len();
+    while index > 0 && pending.get(index - 1).unwrap().release_at > release_at {
+        index -= 1;
+    }
+    match pending.get(index.wrapping_sub(1)) {
+        Some(mut bucket) if bucket.release_at == release_at => {
+            bucket.amount = bucket.amount.checked_add(amount).ok_or(Error::Overflow)?;
+            pending.set(index - 1, bucket);
+        }
+        _ => pending.insert(index, PendingBucket { release_at, amount }),
+    }
+    if pending.len() > MAX_PENDING_BUCKETS {
+        let newest = pending.pop_back().unwrap();
+        let mut merged = pending.pop_back().unwrap();
+        merged.amount = merged
+            .amount
+            .checked_add(newest.amount)
+            .ok_or(Error::Overflow)?;
+        merged.release_at = newest.release_at;
+        pending.push_back(merged);
+    }
+    set_pending(env, merchant, token, &pending);
+    Ok(())
+}
+
+/// Earnings of `merchant` in `token`, split into withdrawable and pending.
+pub fn get_merchant_balances(env: &Env, merchant: &Address, token: &Address) -> MerchantBalances {
+    let total = get_merchant_balance(env, merchant, token);
+    let pending = pending_total(&live_pending(env, merchant, token)).unwrap_or(total);
+    MerchantBalances {
+        available: total.saturating_sub(pending),
+        pending,
+    }
+}
+
+/// Pay out `amount` of settled earnings to `destination`, which must be the
+/// locked payout destination if the merchant set one. Returns what is still
+/// withdrawable.
+pub fn withdraw_merchant_funds(
+    env: &Env,
+    merchant: Address,
+    token: Address,
+    amount: i128,
+    destination: Address,
+) -> Result<i128, Error> {
+    merchant.require_auth();
+    require_not_paused(env)?;
+    crate::admin::require_not_blocked(env, &merchant)?;
+    crate::admin::require_not_blocked(env, &destination)?;
+    require_payout_destination(env, &merchant, &destination)?;
+    if amount <= 0 {
+        return Err(Error::InvalidAmount);
+    }
+    pay_out(env, &merchant, token, amount, destination)
+}
+
+/// Pay out the merchant's whole available balance in `token` to its locked
+/// payout destination, or to itself. Returns the amount paid; with nothing
+/// available this is 0 and nothing is transferred or emitted.
+pub fn withdraw_merchant_all(env: &Env, merchant: Address, token: Address) -> Result<i128, Error> {
+    merchant.require_auth();
+    require_not_paused(env)?;
+    crate::admin::require_not_blocked(env, &merchant)?;
+    let destination = get_payout_lock(env, &merchant).unwrap_or(merchant.clone());
+    crate::admin::require_not_blocked(env, &destination)?;
+
+    let amount = get_merchant_balances(env, &merchant, &token).available;
+    if amount <= 0 {
+        return Ok(0);
+    }
+    pay_out(env, &merchant, token, amount, destination)?;
+    Ok(amount)
+}
+
+/// Debit `amount` of settled earnings, transfer it and emit `withdrawn`.
+/// Returns what is still withdrawable.
+fn pay_out(
+    env: &Env,
+    merchant: &Address,
+    token: Address,
+    amount: i128,
+    destination: Address,
+) -> Result<i128, Error> {
+    let remaining = debit_merchant_balance(env, merchant, &token, amount, false)?;
+    let available = get_merchant_balances(env, merchant, &token).available;
+
+    // Storage is final before the external call (checks-effects-interactions).
+    let token_client = soroban_sdk::token::Client::new(env, &token);
+    token_client.transfer(&env.current_contract_address(), &destination, &amount);
+
+    env.events().publish(
+        (Symbol::new(env, "withdrawn"), merchant.clone()),
+        (token, destination, amount, remaining),
+    );
+    Ok(available)
+}
+
+/// Merchant permanently restricts every payout, manual or automatic, to
+/// `destination`. Meant for a hot operating key paying into a cold treasury,
+/// so the lock cannot be changed or lifted: a second call fails with
+/// `PayoutDestinationLocked`. Emits `payout_locked`.
+pub fn do_lock_payout_destination(
+    env: &Env,
+    merchant: Address,
+    destination: Address,
+) -> Result<(), Error> {
+    merchant.require_auth();
+    if get_payout_lock(env, &merchant).is_some() {
+        return Err(Error::PayoutDestinationLocked);
+    }
+    if let Some(config) = get_payout_config(env, &merchant) {
+        if config.destination != destination {
+            return Err(Error::PayoutDestinationLocked);
+        }
+    }
+    env.storage()
+        .instance()
+        .set(&DataKeyExt::PayoutLock(merchant.clone()), &destination);
+    env.events()
+        .publish((Symbol::new(env, "payout_locked"), merchant), destination);
+    Ok(())
+}
+
+pub fn get_payout_lock(env: &Env, merchant: &Address) -> Option<Address> {
+    env.storage()
+        .instance()
+        .get(&DataKeyExt::PayoutLock(merchant.clone()))
+}
+
+fn require_payout_destination(
+    env: &Env,
+    merchant: &Address,
+    destination: &Address,
+) -> Result<(), Error> {
+    match get_payout_lock(env, merchant) {
+        Some(locked) if locked != *destination => Err(Error::PayoutDestinationLocked),
+        _ => Ok(()),
+    }
+}
+
+/// Merchant sets where, and above which available balance, its earnings are
+/// paid out automatically after a charge. Replaces any previous config.
+pub fn do_set_payout_config(
+    env: &Env,
+    merchant: Address,
+    destination: Address,
+    threshold: i128,
+    enabled: bool,
+) -> Result<(), Error> {
     merchant.require_auth();
-    validate_non_negative(amount)?;
+    if threshold < 0 {
+        return Err(Error::InvalidAmount);
+    }
+    require_payout_destination(env, &merchant, &destination)?;
+    let config = PayoutConfig {
+        destination,
+        threshold,
+        enabled,
+    };
+    env.storage()
+        .instance()
+        .set(&DataKeyExt::PayoutConfig(merchant.clone()), &config);
     env.events()
-        .publish((Symbol::new(env, "withdrawn"), merchant.clone()), amount);
+        .publish((Symbol::new(env, "payout_config"), merchant), config);
     Ok(())
 }
+
+pub fn get_payout_config(env: &Env, merchant: &Address) -> Option<PayoutConfig> {
+    env.storage()
+        .instance()
+        .get(&DataKeyExt::PayoutConfig(merchant.clone()))
+}
+
+/// Run after a charge credited `merchant`: once its available balance in
+/// `token` exceeds the enabled config's threshold, transfer all of it to the
+/// destination. A failed transfer restores the balance, for a manual
+/// withdrawal, and emits `payout_failed` instead of failing the charge.
+pub(crate) fn auto_payout(env: &Env, merchant: &Address, token: &Address) {
+    let Some(config) = get_payout_config(env, merchant).filter(|c| c.enabled) else {
+        return;
+    };
+    let available = get_merchant_balances(env, merchant, token).available;
+    if available <= config.threshold
+        || crate::admin::is_blacklisted(env, merchant)
+        || crate::admin::is_blacklisted(env, &config.destination)
+        || require_payout_destination(env, merchant, &config.destination).is_err()
+    {
+        return;
+    }
+    let balance = get_merchant_balance(env, merchant, token);
+    if debit_merchant_balance(env, merchant, token, available, false).is_err() {
+        return;
+    }
+
+    let result = soroban_sdk::token::Client::new(env, token).try_transfer(
+        &env.current_contract_address(),
+        &config.destination,
+        &available,
+    );
+    let event = if matches!(result, Ok(Ok(()))) {
+        symbol_short!("payout")
+    } else {
+        set_merchant_balance(env, merchant, token, balance);
+        Symbol::new(env, "payout_failed")
+    };
+    env.events().publish(
+        (event, merchant.clone()),
+        (token.clone(), config.destination, available),
+    );
+}
+
+/// Admin registers `merchant`, or renames and reactivates an existing
+/// registration. `registered_at` keeps the first registration time.
+pub fn do_register_merchant(
+    env: &Env,
+    admin: Address,
+    merchant: Address,
+    name: Symbol,
+) -> Result<(), Error> {
+    admin.require_auth();
+    let stored = require_admin(env)?;
+    if admin != stored {
+        return Err(Error::Unauthorized);
+    }
+    let registered_at = get_merchant_info(env, &merchant)
+        .map(|info| info.registered_at)
+        .unwrap_or(env.ledger().timestamp());
+    let info = MerchantInfo {
+        name,
+        registered_at,
+        active: true,
+    };
+    env.storage()
+        .instance()
+        .set(&DataKey::MerchantInfo(merchant.clone()), &info);
+    env.events()
+        .publish((Symbol::new(env, "merchant_registered"), merchant), info);
+    Ok(())
+}
+
+/// Admin (de)activates a registered merchant. Only new subscriptions are
+/// affected; existing ones keep charging and the merchant can still withdraw.
+pub fn do_set_merchant_active(
+    env: &Env,
+    admin: Address,
+    merchant: Address,
+    active: bool,
+) -> Result<(), Error> {
+    admin.require_auth();
+    let stored = require_admin(env)?;
+    if admin != stored {
+        return Err(Error::Unauthorized);
+    }
+    let mut info = get_merchant_info(env, &merchant)?;
+    info.active = active;
+    env.storage()
+        .instance()
+        .set(&DataKey::MerchantInfo(merchant.clone()), &info);
+    env.events()
+        .publish((Symbol::new(env, "merchant_active"), merchant), active);
+    Ok(())
+}
+
+pub fn get_merchant_info(env: &Env, merchant: &Address) -> Result<MerchantInfo, Error> {
+    env.storage()
+        .instance()
+        .get(&DataKey::MerchantInfo(merchant.clone()))
+        .ok_or(Error::NotFound)
+}
+
+/// New subscriptions need an active registration when allowlist mode is on.
+/// A deactivated merchant is refused even with the mode off.
+pub fn require_merchant_accepting(env: &Env, merchant: &Address) -> Result<(), Error> {
+    match get_merchant_info(env, merchant) {
+        Ok(info) if info.active => Ok(()),
+        Ok(_) => Err(Error::MerchantNotRegistered),
+        Err(_) if crate::admin::is_merchant_allowlist_enabled(env) => {
+            Err(Error::MerchantNotRegistered)
+        }
+        Err(_) => Ok(()),
+    }
+}
+
+/// Merchant registers (or with `None` removes) the ed25519 public key that
+/// must sign its usage reports. While set, usage can only be charged through
+/// `charge_usage_attested`.
+pub fn do_set_usage_attestation_key(
+    env: &Env,
+    merchant: Address,
+    public_key: Option<BytesN<32>>,
+) -> Result<(), Error> {
+    merchant.require_auth();
+    let key = DataKey::UsageAttestationKey(merchant.clone());
+    match &public_key {
+        Some(pk) => env.storage().instance().set(&key, pk),
+        None => env.storage().instance().remove(&key),
+    }
+    env.events().publish(
+        (Symbol::new(env, "usage_attestation_key"), merchant),
+        public_key,
+    );
+    Ok(())
+}
+
+pub fn get_usage_attestation_key(env: &Env, merchant: &Address) -> Option<BytesN<32>> {
+    env.storage()
+        .instance()
+        .get(&DataKey::UsageAttestationKey(merchant.clone()))
+}
+
+/// Admin or the merchant sets (or with `None` removes) the merchant's minimum
+/// top-up. Deposits must meet the larger of this and the token's global
+/// minimum, so an override can raise the floor but never lower it.
+pub fn do_set_merchant_min_topup(
+    env: &Env,
+    authorizer: Address,
+    merchant: Address,
+    min_topup: Option<i128>,
+) -> Result<(), Error> {
+    authorizer.require_auth();
+    if authorizer != merchant && authorizer != require_admin(env)? {
+        return Err(Error::Unauthorized);
+    }
+    let key = DataKey::MerchantMinTopup(merchant.clone());
+    match min_topup {
+        Some(min) if min < 0 => return Err(Error::InvalidAmount),
+        Some(min) => {
+            env.storage().persistent().set(&key, &min);
+            crate::ttl::bump_persistent(env, &key);
+        }
+        None => env.storage().persistent().remove(&key),
+    }
+    env.events().publish(
+        (Symbol::new(env, "merchant_min_topup"), merchant),
+        min_topup,
+    );
+    Ok(())
+}
+
+pub fn get_merchant_min_topup(env: &Env, merchant: &Address) -> Option<i128> {
+    env.storage()
+        .persistent()
+        .get(&DataKey::MerchantMinTopup(merchant.clone()))
+}
+
+/// Minimum deposit for `sub`: the stricter of its token's global minimum and
+/// its merchant's override.
+pub fn effective_min_topup(env: &Env, sub: &Subscription) -> Result<i128, Error> {
+    let global = get_token_min_topup(env, &sub.token)?;
+    Ok(get_merchant_min_topup(env, &sub.merchant).map_or(global, |min| min.max(global)))
+}
+
+pub fn get_effective_min_topup(env: &Env, subscription_id: u64) -> Result<i128, Error> {
+    effective_min_topup(env, &get_subscription(env, subscription_id)?)
+}
+
+/// Current address of `merchant`, following rotations. Rotation never targets
+/// an already-rotated address, so the chain cannot loop.
+pub fn resolve_merchant(env: &Env, merchant: Address) -> Address {
+    let mut current = merchant;
+    while let Some(next) = env
+        .storage()
+        .instance()
+        .get::<_, Address>(&DataKey::MerchantRedirect(current.clone()))
+    {
+        current = next;
+    }
+    current
+}
+
+/// Hand a merchant identity over to `new_merchant`. Both must sign.
+///
+/// Subscriptions and plans are not rewritten here, which would be unbounded:
+/// a redirect is stored instead and applied whenever they are loaded. Accrued
+/// balances in allowlisted tokens and the merchant's subscription and plan
+/// indexes move eagerly.
+pub fn do_rotate_merchant(
+    env: &Env,
+    old_merchant: Address,
+    new_merchant: Address,
+) -> Result<(), Error> {
+    if old_merchant == new_merchant {
+        return Err(Error::InvalidMerchantRotation);
+    }
+    old_merchant.require_auth();
+    new_merchant.require_auth();
+    let storage = env.storage().instance();
+    if storage.has(&DataKey::MerchantRedirect(old_merchant.clone()))
+        || storage.has(&DataKey::MerchantRedirect(new_merchant.clone()))
+    {
+        return Err(Error::InvalidMerchantRotation);
+    }
+    if let Some(locked) = get_payout_lock(env, &old_merchant) {
+        require_payout_destination(env, &new_merchant, &locked)?;
+    }
+
+    for token in crate::admin::get_supported_tokens(env).iter() {
+        let balance = get_merchant_balance(env, &old_merchant, &token);
+        if balance > 0 {
+            add_merchant_balance(env, &new_merchant, &token, balance)?;
+            for bucket in live_pending(env, &old_merchant, &token).iter() {
+                add_pending(env, &new_merchant, &token, bucket.release_at, bucket.amount)?;
+            }
+            storage.remove(&DataKey::MerchantBalance(
+                old_merchant.clone(),
+                token.clone(),
+            ));
+            storage.remove(&DataKey::MerchantPending(old_merchant.clone(), token));
+        }
+    }
+    move_index::<u64>(
+        env,
+        DataKey::MerchantSubs(old_merchant.clone()),
+        DataKey::MerchantSubs(new_merchant.clone()),
+    );
+    move_index::<u32>(
+        env,
+        DataKey::MerchantPlans(old_merchant.clone()),
+        DataKey::MerchantPlans(new_merchant.clone()),
+    );
+    if let Some(min_topup) = get_merchant_min_topup(env, &old_merchant) {
+        env.storage()
+            .persistent()
+            .remove(&DataKey::MerchantMinTopup(old_merchant.clone()));
+        if get_merchant_min_topup(env, &new_merchant).is_none() {
+            let key = DataKey::MerchantMinTopup(new_merchant.clone());
+            env.storage().persistent().set(&key, &min_topup);
+            crate::ttl::bump_persistent(env, &key);
+        }
+    }
+    if let Some(public_key) = get_usage_attestation_key(env, &old_merchant) {
+        storage.remove(&DataKey::UsageAttestationKey(old_merchant.clone()));
+        if get_usage_attestation_key(env, &new_merchant).is_none() {
+            storage.set(
+                &DataKey::UsageAttestationKey(new_merchant.clone()),
+                &public_key,
+            );
+        }
+    }
+    // The lock binds whoever holds the identity, so rotating cannot shed it.
+    if let Some(locked) = get_payout_lock(env, &old_merchant) {
+        storage.remove(&DataKeyExt::PayoutLock(old_merchant.clone()));
+        storage.set(&DataKeyExt::PayoutLock(new_merchant.clone()), &locked);
+    }
+    if let Some(config) = get_payout_config(env, &old_merchant) {
+        storage.remove(&DataKeyExt::PayoutConfig(old_merchant.clone()));
+        if get_payout_config(env, &new_merchant).is_none() {
+            storage.set(&DataKeyExt::PayoutConfig(new_merchant.clone()), &config);
+        }
+    }
+    crate::trial_bond::move_terms(env, &old_merchant, &new_merchant);
+    crate::merchant_stats::move_stats(env, &old_merchant, &new_merchant)?;
+    storage.set(
+        &DataKey::MerchantRedirect(old_merchant.clone()),
+        &new_merchant,
+    );
+
+    env.events().publish(
+        (Symbol::new(env, "merchant_rotated"), old_merchant.clone()),
+        MerchantRotatedEvent {
+            old_merchant,
+            new_merchant,
+            timestamp: env.ledger().timestamp(),
+        },
+    );
+    Ok(())
+}
+
+fn move_index<T>(env: &Env, from: DataKey, to: DataKey)
+where
+    T: IntoVal<Env, Val> + TryFromVal<Env, Val>,
+{
+    let storage = env.storage().instance();
+    let Some(moved) = storage.get::<_, Vec<T>>(&from) else {
+        return;
+    };
+    let mut ids: Vec<T> = storage.get(&to).unwrap_or(Vec::new(env));
+    ids.append(&moved);
+    storage.set(&to, &ids);
+    storage.remove(&from);
+}
+
+/// Move `amount` of the merchant's accrued balance back into a subscription's
+/// `prepaid_balance`. Allowed in any status, including `Cancelled`, where it
+/// increases what the subscriber can withdraw.
+pub fn do_refund(
+    env: &Env,
+    subscription_id: u64,
+    merchant: Address,
+    amount: i128,
+) -> Result<(), Error> {
+    let mut sub = return_to_prepaid(env, subscription_id, &merchant, amount)?;
+    sub.total_refunded = sub
+        .total_refunded
+        .checked_add(amount)
+        .ok_or(Error::Overflow)?;
+    save_subscription(env, subscription_id, &mut sub)?;
+
+    env.events().publish(
+        (symbol_short!("refunded"), subscription_id),
+        SubscriptionRefundedEvent {
+            subscription_id,
+            merchant,
+            amount,
+            prepaid_balance: sub.prepaid_balance,
+        },
+    );
+    Ok(())
+}
+
+/// Like [`do_refund`], but for correcting over-metered usage: tracked in the
+/// subscription's stats and charge history rather than `total_refunded`.
+pub fn do_issue_usage_credit(
+    env: &Env,
+    merchant: Address,
+    subscription_id: u64,
+    amount: i128,
+) -> Result<(), Error> {
+    let mut sub = return_to_prepaid(env, subscription_id, &merchant, amount)?;
+    save_subscription(env, subscription_id, &mut sub)?;
+    crate::charge_core::record_credit(env, subscription_id, amount);
+
+    env.events().publish(
+        (symbol_short!("credit"), subscription_id),
+        UsageCreditEvent {
+            subscription_id,
+            merchant,
+            amount,
+            prepaid_balance: sub.prepaid_balance,
+        },
+    );
+    Ok(())
+}
+
+/// Move `amount` of the merchant's accrued balance into the subscription's
+/// `prepaid_balance`; the caller saves the returned record.
+fn return_to_prepaid(
+    env: &Env,
+    subscription_id: u64,
+    merchant: &Address,
+    amount: i128,
+) -> Result<LoadedSubscription, Error> {
+    merchant.require_auth();
+
+    let mut sub = load_subscription(env, subscription_id)?;
+    if *merchant != sub.merchant {
+        return Err(Error::Unauthorized);
+    }
+    if amount <= 0 {
+        return Err(Error::InvalidAmount);
+    }
+
+    debit_merchant_balance(env, merchant, &sub.token, amount, true)?;
+    sub.prepaid_balance = sub
+        .prepaid_balance
+        .checked_add(amount)
+        .ok_or(Error::Overflow)?;
+    Ok(sub)
+}
@@ -0,0 +1,145 @@
+//! Merchant statistics: active subscribers and revenue per merchant.
+//!
+//! **PRs that only change merchant statistics should edit this file only.**
+//!
+//! Iterating a merchant's subscriptions would exceed instruction limits, so
+//! the counters are kept incrementally: `stats::save_subscription` reports
+//! every status change, and `merchant::credit_merchant_balance` every charge.
+//! Balances are not copied; `get_merchant_stats` reads them from the ledger.
+
+use crate::types::{
+    DataKeyExt, Error, MerchantCounters, MerchantStats, Subscription, SubscriptionStatus,
+    MERCHANT_STATS_PERIOD_SECONDS,
+};
+use soroban_sdk::{Address, Env};
+
+fn load(env: &Env, merchant: &Address) -> MerchantCounters {
+    env.storage()
+        .persistent()
+        .get(&DataKeyExt::MerchantStats(merchant.clone()))
+        .unwrap_or_default()
+}
+
+fn store(env: &Env, merchant: &Address, counters: &MerchantCounters) {
+    let key = DataKeyExt::MerchantStats(merchant.clone());
+    env.storage().persistent().set(&key, counters);
+    crate::ttl::bump_persistent(env, &key);
+}
+
+fn period_start(env: &Env) -> u64 {
+    let now = env.ledger().timestamp();
+    now - now % MERCHANT_STATS_PERIOD_SECONDS
+}
+
+/// `counters` with the period total reset if its window has passed.
+fn rolled(env: &Env, mut counters: MerchantCounters) -> MerchantCounters {
+    let start = period_start(env);
+    if counters.period_start != start {
+        counters.period_start = start;
+        counters.charged_this_period = 0;
+    }
+    counters
+}
+
+fn add_active(env: &Env, merchant: &Address, joined: bool) -> Result<(), Error> {
+    let mut counters = load(env, merchant);
+    counters.active_subscribers = if joined {
+        counters
+            .active_subscribers
+            .checked_add(1)
+            .ok_or(Error::Overflow)?
+    } else {
+        counters.active_subscribers.saturating_sub(1)
+    };
+    store(env, merchant, &counters);
+    Ok(())
+}
+
+/// Fold a subscription write into the active-subscriber counts. `was_active`
+/// is the merchant the stored record was counted under while `Active`.
+pub(crate) fn on_save(
+    env: &Env,
+    was_active: Option<&Address>,
+    sub: &Subscription,
+) -> Result<(), Error> {
+    let is_active = sub.status == SubscriptionStatus::Active;
+    match was_active {
+        Some(old) if is_active && *old == sub.merchant => Ok(()),
+        Some(old) => {
+            add_active(env, old, false)?;
+            if is_active {
+                add_active(env, &sub.merchant, true)?;
+            }
+            Ok(())
+        }
+        None if is_active => add_active(env, &sub.merchant, true),
+        None => Ok(()),
+    }
+}
+
+/// Count `amount` charged for `merchant` in `token`.
+pub(crate) fn record_charge(
+    env: &Env,
+    merchant: &Address,
+    token: &Address,
+    amount: i128,
+) -> Result<(), Error> {
+    if amount <= 0 || *token != crate::admin::get_token(env)? {
+        return Ok(());
+    }
+    let mut counters = rolled(env, load(env, merchant));
+    counters.total_charged = counters
+        .total_charged
+        .checked_add(amount)
+        .ok_or(Error::Overflow)?;
+    counters.charged_this_period = counters
+        .charged_this_period
+        .checked_add(amount)
+        .ok_or(Error::Overflow)?;
+    store(env, merchant, &counters);
+    Ok(())
+}
+
+/// Merge `old_merchant`'s counters into `new_merchant`'s on rotation.
+pub(crate) fn move_stats(
+    env: &Env,
+    old_merchant: &Address,
+    new_merchant: &Address,
+) -> Result<(), Error> {
+    let key = DataKeyExt::MerchantStats(old_merchant.clone());
+    let Some(old) = env.storage().persistent().get::<_, MerchantCounters>(&key) else {
+        return Ok(());
+    };
+    env.storage().persistent().remove(&key);
+    let old = rolled(env, old);
+    let mut counters = rolled(env, load(env, new_merchant));
+    counters.active_subscribers = counters
+        .active_subscribers
+        .checked_add(old.active_subscribers)
+        .ok_or(Error::Overflow)?;
+    counters.total_charged = counters
+        .total_charged
+        .checked_add(old.total_charged)
+        .ok_or(Error::Overflow)?;
+    counters.charged_this_period = counters
+        .charged_this_period
+        .checked_add(old.charged_this_period)
+        .ok_or(Error::Overflow)?;
+    store(env, new_merchant, &counters);
+    Ok(())
+}
+
+pub fn get_merchant_stats(env: &Env, merchant: &Address) -> Result<MerchantStats, Error> {
+    let merchant = crate::merchant::resolve_merchant(env, merchant.clone());
+    let counters = rolled(env, load(env, &merchant));
+    let balances =
+        crate::merchant::get_merchant_balances(env, &merchant, &crate::admin::get_token(env)?);
+    Ok(MerchantStats {
+        active_subscribers: counters.active_subscribers,
+        total_charged: counters.total_charged,
+        charged_this_period: counters.charged_this_period,
+        period_start: counters.period_start,
+        available: balances.available,
+        pending: balances.pending,
+    })
+}
@@ -0,0 +1,74 @@
+//! Multicall: several subscriber operations under one signature, e.g. create
+//! and deposit, or cancel and withdraw.
+//!
+//! **PRs that only change multicall should edit this file only.**
+//!
+//! The caller authorizes once; every op then runs as the caller through the
+//! same `*_authorized` bodies as the single entrypoints, so each still checks
+//! that the caller is the subscriber (or, for cancel, the merchant). Ops run in
+//! order and the first failure aborts the whole call, undoing earlier ops.
+
+use crate::queries::get_subscription;
+use crate::subscription::{
+    cancel_authorized, create_authorized, deposit_authorized, withdraw_subscriber_authorized,
+};
+use crate::types::{Error, VaultOp, VaultOpResult, MAX_MULTICALL_OPS, MULTICALL_LAST_CREATED};
+use soroban_sdk::{Address, Env, Vec};
+
+pub fn do_multicall(
+    env: &Env,
+    caller: Address,
+    ops: Vec<VaultOp>,
+) -> Result<Vec<VaultOpResult>, Error> {
+    if ops.len() > MAX_MULTICALL_OPS {
+        return Err(Error::BatchTooLarge);
+    }
+    caller.require_auth();
+
+    let mut last_created = None;
+    let mut results = Vec::new(env);
+    for op in ops.iter() {
+        let result = match op {
+            VaultOp::CreateSubscription(args) => {
+                let id = create_authorized(
+                    env,
+                    caller.clone(),
+                    args.merchant,
+                    args.amount,
+                    args.interval_seconds,
+                    args.usage_enabled,
+                    args.options,
+                )?;
+                last_created = Some(id);
+                VaultOpResult::Created(id)
+            }
+            VaultOp::DepositFunds(id, amount) => VaultOpResult::Deposited(deposit_authorized(
+                env,
+                resolve(id, last_created)?,
+                caller.clone(),
+                amount,
+            )?),
+            VaultOp::CancelSubscription(id, reason) => {
+                cancel_authorized(env, resolve(id, last_created)?, caller.clone(), reason)?;
+                VaultOpResult::Cancelled
+            }
+            VaultOp::WithdrawSubscriberFunds(id) => {
+                let id = resolve(id, last_created)?;
+                let refund = get_subscription(env, id)?.prepaid_balance;
+                withdraw_subscriber_authorized(env, id, caller.clone())?;
+                VaultOpResult::Withdrawn(refund)
+            }
+        };
+        results.push_back(result);
+    }
+    Ok(results)
+}
+
+/// Map [`MULTICALL_LAST_CREATED`] to the id created earlier in the call.
+fn resolve(id: u64, last_created: Option<u64>) -> Result<u64, Error> {
+    if id == MULTICALL_LAST_CREATED {
+        last_created.ok_or(Error::NotFound)
+    } else {
+        Ok(id)
+    }
+}
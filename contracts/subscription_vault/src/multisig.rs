@@ -0,0 +1,212 @@
+//! Multisig admin: an M-of-N admin set whose approvals replace the single
+//! admin's signature for timelock operations and for changes to the set.
+//!
+//! **PRs that only change multisig admin approvals should edit this file only.**
+//!
+//! Without a set the single stored admin acts alone, as before. The legacy
+//! admin configures the first set with `set_admins`; from then on a
+//! [`MultisigAction`] is proposed by one member, identified by the sha256 of
+//! its XDR, and the approval that reaches the threshold executes it.
+//! Approvals lapse `approval_window` seconds after the proposal.
+
+use crate::admin::require_admin;
+use crate::types::{
+    AdminProposal, AdminSet, DataKeyExt, Error, MultisigAction, DEFAULT_APPROVAL_WINDOW_SECONDS,
+    MAX_ADMINS,
+};
+use soroban_sdk::{xdr::ToXdr, Address, BytesN, Env, Symbol, Vec};
+
+pub fn get_admin_set(env: &Env) -> Option<AdminSet> {
+    env.storage().instance().get(&DataKeyExt::AdminSet)
+}
+
+pub(crate) fn is_enabled(env: &Env) -> bool {
+    env.storage().instance().has(&DataKeyExt::AdminSet)
+}
+
+pub fn get_admin_proposal(env: &Env, action_hash: &BytesN<32>) -> Option<AdminProposal> {
+    env.storage()
+        .instance()
+        .get(&DataKeyExt::AdminProposal(action_hash.clone()))
+}
+
+pub fn action_hash(env: &Env, action: &MultisigAction) -> BytesN<32> {
+    env.crypto().sha256(&action.clone().to_xdr(env)).to_bytes()
+}
+
+fn validate_admins(admins: &Vec<Address>, threshold: u32) -> Result<(), Error> {
+    if admins.len() > MAX_ADMINS {
+        return Err(Error::BatchTooLarge);
+    }
+    if threshold == 0 || threshold > admins.len() {
+        return Err(Error::InvalidAmount);
+    }
+    for (i, admin) in admins.iter().enumerate() {
+        if admins.first_index_of(&admin) != Some(i as u32) {
+            return Err(Error::InvalidAmount);
+        }
+    }
+    Ok(())
+}
+
+/// Reject actions that could never execute, so they fail at proposal time.
+fn validate(action: &MultisigAction) -> Result<(), Error> {
+    match action {
+        MultisigAction::SetAdmins(admins, threshold) => validate_admins(admins, *threshold),
+        MultisigAction::SetApprovalWindow(0) => Err(Error::InvalidInterval),
+        _ => Ok(()),
+    }
+}
+
+fn store_set(env: &Env, set: &AdminSet) {
+    env.storage().instance().set(&DataKeyExt::AdminSet, set);
+    env.events().publish(
+        (Symbol::new(env, "admins_set"),),
+        (set.admins.clone(), set.threshold, set.approval_window),
+    );
+}
+
+/// The legacy single admin installs the first admin set. Later changes are
+/// `MultisigAction::SetAdmins` proposals.
+pub fn do_set_admins(
+    env: &Env,
+    admin: Address,
+    admins: Vec<Address>,
+    threshold: u32,
+) -> Result<(), Error> {
+    admin.require_auth();
+    if admin != require_admin(env)? || is_enabled(env) {
+        return Err(Error::Unauthorized);
+    }
+    validate_admins(&admins, threshold)?;
+    store_set(
+        env,
+        &AdminSet {
+            admins,
+            threshold,
+            approval_window: DEFAULT_APPROVAL_WINDOW_SECONDS,
+        },
+    );
+    Ok(())
+}
+
+fn require_member(env: &Env, admin: &Address) -> Result<AdminSet, Error> {
+    admin.require_auth();
+    let set = get_admin_set(env).ok_or(Error::Unauthorized)?;
+    if !set.admins.contains(admin) {
+        return Err(Error::Unauthorized);
+    }
+    Ok(set)
+}
+
+/// `action_hash`'s proposal while it still accepts approvals.
+fn live_proposal(env: &Env, action_hash: &BytesN<32>) -> Option<AdminProposal> {
+    get_admin_proposal(env, action_hash).filter(|p| env.ledger().timestamp() <= p.expires_at)
+}
+
+/// Propose `action` with the proposer's approval, or add that approval to a
+/// live proposal of the same action. Returns the action hash.
+pub fn do_propose_admin_action(
+    env: &Env,
+    admin: Address,
+    action: MultisigAction,
+) -> Result<BytesN<32>, Error> {
+    let set = require_member(env, &admin)?;
+    validate(&action)?;
+    let hash = action_hash(env, &action);
+    let proposal = match live_proposal(env, &hash) {
+        Some(proposal) => proposal,
+        None => {
+            let expires_at = env
+                .ledger()
+                .timestamp()
+                .checked_add(set.approval_window)
+                .ok_or(Error::Overflow)?;
+            env.events().publish(
+                (Symbol::new(env, "admin_proposed"), hash.clone()),
+                (action.clone(), expires_at),
+            );
+            AdminProposal {
+                action,
+                approvals: Vec::new(env),
+                expires_at,
+            }
+        }
+    };
+    add_approval(env, &set, &hash, proposal, admin)?;
+    Ok(hash)
+}
+
+/// Approve a live proposal. Returns true if this approval met the threshold
+/// and executed the action.
+pub fn do_approve_action(
+    env: &Env,
+    admin: Address,
+    action_hash: BytesN<32>,
+) -> Result<bool, Error> {
+    let set = require_member(env, &admin)?;
+    let proposal = live_proposal(env, &action_hash).ok_or(Error::NotFound)?;
+    add_approval(env, &set, &action_hash, proposal, admin)
+}
+
+fn add_approval(
+    env: &Env,
+    set: &AdminSet,
+    hash: &BytesN<32>,
+    mut proposal: AdminProposal,
+    admin: Address,
+) -> Result<bool, Error> {
+    if proposal.approvals.contains(&admin) {
+        return Err(Error::Replay);
+    }
+    proposal.approvals.push_back(admin.clone());
+    // Approvals of admins removed from the set since no longer count.
+    let approvals = proposal
+        .approvals
+        .iter()
+        .filter(|a| set.admins.contains(a))
+        .count() as u32;
+    env.events().publish(
+        (Symbol::new(env, "admin_approved"), hash.clone()),
+        (admin, approvals, set.threshold),
+    );
+
+    let key = DataKeyExt::AdminProposal(hash.clone());
+    if approvals < set.threshold {
+        env.storage().instance().set(&key, &proposal);
+        return Ok(false);
+    }
+    env.storage().instance().remove(&key);
+    execute(env, set, proposal.action)?;
+    Ok(true)
+}
+
+fn execute(env: &Env, set: &AdminSet, action: MultisigAction) -> Result<(), Error> {
+    match action {
+        MultisigAction::QueueAdminAction(action, eta) => {
+            crate::timelock::queue(env, action, eta).map(|_| ())
+        }
+        MultisigAction::CancelAdminAction(id) => crate::timelock::cancel(env, id),
+        MultisigAction::SetAdmins(admins, threshold) => {
+            store_set(
+                env,
+                &AdminSet {
+                    admins,
+                    threshold,
+                    approval_window: set.approval_window,
+                },
+            );
+            Ok(())
+        }
+        MultisigAction::SetApprovalWindow(approval_window) => {
+            store_set(
+                env,
+                &AdminSet {
+                    approval_window,
+                    ..set.clone()
+                },
+            );
+            Ok(())
+        }
+    }
+}
@@ -0,0 +1,68 @@
+//! Notification address: a watch-only address the subscriber names so wallets
+//! can follow a subscription funded from a different (e.g. cold) key.
+//!
+//! **PRs that only change notification addresses should edit this file only.**
+//!
+//! When set, the address is appended as the last topic of the events a
+//! subscriber wants to hear about: `low_bal`, the charge-failed events
+//! (`grace`, `insufficient_balance`, `dunn_end`) and renewals (`charged`).
+//! Otherwise those topics are unchanged. No funds ever move to or from it,
+//! and it is dropped when the subscription changes hands.
+
+use crate::queries::get_subscription;
+use crate::stored_sub::Optional;
+use crate::types::Error;
+use soroban_sdk::{Address, Env, IntoVal, Symbol, Val, Vec};
+
+pub fn get(env: &Env, subscription_id: u64) -> Option<Address> {
+    crate::stored_sub::get_optional(env, subscription_id, Optional::NotifyAddress)
+}
+
+pub fn remove(env: &Env, subscription_id: u64) {
+    crate::stored_sub::remove_optional(env, subscription_id, Optional::NotifyAddress);
+}
+
+/// `topics` with the notification address of `subscription_id` appended, if set.
+pub(crate) fn topics<T: IntoVal<Env, Vec<Val>>>(
+    env: &Env,
+    subscription_id: u64,
+    topics: T,
+) -> Vec<Val> {
+    let mut topics: Vec<Val> = topics.into_val(env);
+    if let Some(address) = get(env, subscription_id) {
+        topics.push_back(address.into_val(env));
+    }
+    topics
+}
+
+/// Subscriber sets the notification address, or clears it with `None`.
+pub fn do_set_notify_address(
+    env: &Env,
+    subscriber: Address,
+    subscription_id: u64,
+    notify_address: Option<Address>,
+) -> Result<(), Error> {
+    subscriber.require_auth();
+
+    let sub = get_subscription(env, subscription_id)?;
+    if subscriber != sub.subscriber {
+        return Err(Error::Unauthorized);
+    }
+
+    match &notify_address {
+        Some(address) => {
+            crate::stored_sub::set_optional(env, subscription_id, Optional::NotifyAddress, address)
+        }
+        None => remove(env, subscription_id),
+    }
+    env.events().publish(
+        (Symbol::new(env, "notify_set"), subscription_id),
+        notify_address,
+    );
+    Ok(())
+}
+
+pub fn get_notify_address(env: &Env, subscription_id: u64) -> Result<Option<Address>, Error> {
+    get_subscription(env, subscription_id)?;
+    Ok(get(env, subscription_id))
+}
@@ -0,0 +1,95 @@
+//! Fiat pricing: subscriptions priced in a fiat amount (e.g. 9.99 USD) and
+//! converted to token units through an admin-allowlisted SEP-40 price oracle
+//! each time an interval charge comes due.
+//!
+//! **PRs that only change fiat pricing should edit this file only.**
+//!
+//! # Conversion
+//!
+//! `lastprice(Stellar(token))` gives the fiat price of one whole token at the
+//! oracle's precision, the same precision as `fiat_amount`. The amount in the
+//! token's smallest unit is
+//!
+//! ```text
+//! ceil(fiat_amount * 10^token_decimals / price)
+//! ```
+//!
+//! rounded up, so the merchant never receives less than the fiat price. A
+//! missing, non-positive or failing price, or one older than `oracle_max_age`,
+//! fails with `StaleOraclePrice` before anything is written.
+
+use crate::admin::{get_oracle_max_age, is_price_oracle};
+use crate::queries::get_subscription;
+use crate::stored_sub::Optional;
+use crate::types::{Error, FiatPricing, OracleAsset, PriceData, Subscription, SubscriptionOptions};
+use soroban_sdk::{token, Address, Env, IntoVal, InvokeError, Symbol};
+
+pub fn get(env: &Env, subscription_id: u64) -> Option<FiatPricing> {
+    crate::stored_sub::get_optional(env, subscription_id, Optional::FiatPricing)
+}
+
+pub fn remove(env: &Env, subscription_id: u64) {
+    crate::stored_sub::remove_optional(env, subscription_id, Optional::FiatPricing);
+}
+
+/// Token amount for `pricing` right now. See the module docs for rounding.
+pub fn quote(env: &Env, token: &Address, pricing: &FiatPricing) -> Result<i128, Error> {
+    if pricing.fiat_amount <= 0 {
+        return Err(Error::InvalidAmount);
+    }
+    if !is_price_oracle(env, &pricing.oracle) {
+        return Err(Error::OracleNotAllowed);
+    }
+    let args = (OracleAsset::Stellar(token.clone()),).into_val(env);
+    let price = match env.try_invoke_contract::<Option<PriceData>, InvokeError>(
+        &pricing.oracle,
+        &Symbol::new(env, "lastprice"),
+        args,
+    ) {
+        Ok(Ok(Some(data))) => data,
+        _ => return Err(Error::StaleOraclePrice),
+    };
+    let age = env.ledger().timestamp().saturating_sub(price.timestamp);
+    if price.price <= 0 || age > get_oracle_max_age(env) {
+        return Err(Error::StaleOraclePrice);
+    }
+
+    let scale = 10i128
+        .checked_pow(token::Client::new(env, token).decimals())
+        .ok_or(Error::Overflow)?;
+    let numerator = pricing
+        .fiat_amount
+        .checked_mul(scale)
+        .ok_or(Error::Overflow)?;
+    let units = numerator / price.price;
+    Ok(if numerator % price.price == 0 {
+        units
+    } else {
+        units + 1
+    })
+}
+
+/// Pricing requested by creation options: fiat when `price_oracle` is set.
+pub fn from_options(options: &SubscriptionOptions) -> Option<FiatPricing> {
+    options.price_oracle.as_ref().map(|oracle| FiatPricing {
+        oracle: oracle.clone(),
+        fiat_amount: options.fiat_amount,
+    })
+}
+
+pub fn set(env: &Env, subscription_id: u64, pricing: &FiatPricing) {
+    crate::stored_sub::set_optional(env, subscription_id, Optional::FiatPricing, pricing);
+}
+
+/// Re-quote a fiat-priced `sub.amount` in memory; no-op for token-priced ones.
+pub fn reprice(env: &Env, subscription_id: u64, sub: &mut Subscription) -> Result<(), Error> {
+    if let Some(pricing) = get(env, subscription_id) {
+        sub.amount = quote(env, &sub.token, &pricing)?;
+    }
+    Ok(())
+}
+
+pub fn get_fiat_pricing(env: &Env, subscription_id: u64) -> Result<Option<FiatPricing>, Error> {
+    get_subscription(env, subscription_id)?;
+    Ok(get(env, subscription_id))
+}
@@ -0,0 +1,122 @@
+//! Usage while paused: subscriptions whose usage charges keep running in
+//! `Paused` status. Interval charges stay blocked while paused.
+//!
+//! **PRs that only change usage while paused should edit this file only.**
+//!
+//! The flag is agreed at creation (`SubscriptionOptions::usage_while_paused`)
+//! or later by both parties: the merchant proposes, the subscriber accepts.
+//! A usage charge that drains a paused subscription moves it straight to
+//! `InsufficientBalance`.
+
+use crate::queries::get_subscription;
+use crate::state_machine::get_allowed_transitions;
+use crate::stored_sub::Optional;
+use crate::types::{DataKeyExt, Error, Subscription, SubscriptionStatus};
+use soroban_sdk::{Address, Env, Symbol};
+
+pub fn is_enabled(env: &Env, subscription_id: u64) -> bool {
+    crate::stored_sub::has_optional(env, subscription_id, Optional::UsageWhilePaused)
+}
+
+/// Status check of a usage charge: `Active`, or `Paused` with the flag set.
+pub(crate) fn require_usage_status(
+    env: &Env,
+    subscription_id: u64,
+    sub: &Subscription,
+) -> Result<(), Error> {
+    match sub.status {
+        SubscriptionStatus::Active => Ok(()),
+        SubscriptionStatus::Paused if is_enabled(env, subscription_id) => Ok(()),
+        _ => Err(Error::NotActive),
+    }
+}
+
+fn set(env: &Env, subscription_id: u64, enabled: bool) {
+    if enabled {
+        crate::stored_sub::set_optional(env, subscription_id, Optional::UsageWhilePaused, &true);
+    } else {
+        crate::stored_sub::remove_optional(env, subscription_id, Optional::UsageWhilePaused);
+    }
+}
+
+/// Record the flag agreed at creation; a no-op when not agreed.
+pub(crate) fn init(env: &Env, subscription_id: u64, enabled: bool) {
+    if enabled {
+        set(env, subscription_id, true);
+    }
+}
+
+pub fn remove(env: &Env, subscription_id: u64) {
+    set(env, subscription_id, false);
+    crate::stored_sub::remove_entry(env, &DataKeyExt::PendingUsageWhilePaused(subscription_id));
+}
+
+/// Merchant proposes turning the flag on or off; the subscriber must accept.
+/// Replaces any earlier pending proposal.
+pub fn do_propose_usage_while_paused(
+    env: &Env,
+    subscription_id: u64,
+    merchant: Address,
+    enabled: bool,
+) -> Result<(), Error> {
+    merchant.require_auth();
+
+    let sub = get_subscription(env, subscription_id)?;
+    if merchant != sub.merchant {
+        return Err(Error::Unauthorized);
+    }
+    if get_allowed_transitions(&sub.status).is_empty() {
+        return Err(Error::InvalidStatusTransition);
+    }
+
+    crate::stored_sub::set_entry(
+        env,
+        &DataKeyExt::PendingUsageWhilePaused(subscription_id),
+        &enabled,
+    );
+    env.events().publish(
+        (Symbol::new(env, "paused_usage_proposed"), subscription_id),
+        (is_enabled(env, subscription_id), enabled),
+    );
+    Ok(())
+}
+
+/// Subscriber accepts the pending proposal, which applies at once.
+pub fn do_accept_usage_while_paused(
+    env: &Env,
+    subscription_id: u64,
+    subscriber: Address,
+) -> Result<(), Error> {
+    subscriber.require_auth();
+
+    let sub = get_subscription(env, subscription_id)?;
+    if subscriber != sub.subscriber {
+        return Err(Error::Unauthorized);
+    }
+    if get_allowed_transitions(&sub.status).is_empty() {
+        return Err(Error::InvalidStatusTransition);
+    }
+    let key = DataKeyExt::PendingUsageWhilePaused(subscription_id);
+    let enabled: bool = crate::stored_sub::get_entry(env, &key).ok_or(Error::NotFound)?;
+
+    let old = is_enabled(env, subscription_id);
+    set(env, subscription_id, enabled);
+    crate::stored_sub::remove_entry(env, &key);
+    env.events().publish(
+        (Symbol::new(env, "paused_usage_accepted"), subscription_id),
+        (old, enabled),
+    );
+    Ok(())
+}
+
+/// `(enabled, pending)`: the current flag and the merchant's unaccepted proposal, if any.
+pub fn get_usage_while_paused(
+    env: &Env,
+    subscription_id: u64,
+) -> Result<(bool, Option<bool>), Error> {
+    get_subscription(env, subscription_id)?;
+    Ok((
+        is_enabled(env, subscription_id),
+        crate::stored_sub::get_entry(env, &DataKeyExt::PendingUsageWhilePaused(subscription_id)),
+    ))
+}
@@ -0,0 +1,150 @@
+//! Subscription plans: reusable merchant pricing that subscribers sign up to.
+//!
+//! **PRs that only change plan management should edit this file only.**
+
+use crate::stats::{load_subscription, save_subscription};
+use crate::subscription::{do_create_subscription, require_no_live_subscription};
+use crate::types::{DataKey, Error, Plan, SubscriptionOptions};
+use soroban_sdk::{symbol_short, Address, Env, Vec};
+
+pub fn get_plan(env: &Env, plan_id: u32) -> Result<Plan, Error> {
+    let mut plan: Plan = env
+        .storage()
+        .instance()
+        .get(&DataKey::Plan(plan_id))
+        .ok_or(Error::NotFound)?;
+    plan.merchant = crate::merchant::resolve_merchant(env, plan.merchant);
+    Ok(plan)
+}
+
+pub fn do_create_plan(
+    env: &Env,
+    merchant: Address,
+    amount: i128,
+    interval_seconds: u64,
+    usage_enabled: bool,
+    usage_rate: i128,
+) -> Result<u32, Error> {
+    merchant.require_auth();
+    if env
+        .storage()
+        .instance()
+        .has(&DataKey::MerchantRedirect(merchant.clone()))
+    {
+        return Err(Error::InvalidMerchantRotation);
+    }
+    if usage_rate < 0 {
+        return Err(Error::InvalidAmount);
+    }
+    crate::admin::require_amount_in_limits(env, amount)?;
+    crate::admin::require_interval_in_limits(env, interval_seconds)?;
+
+    let plan_id: u32 = env
+        .storage()
+        .instance()
+        .get(&DataKey::NextPlanId)
+        .unwrap_or(1);
+    let next = plan_id.checked_add(1).ok_or(Error::Overflow)?;
+    env.storage().instance().set(&DataKey::NextPlanId, &next);
+
+    let plan = Plan {
+        merchant: merchant.clone(),
+        amount,
+        interval_seconds,
+        usage_enabled,
+        usage_rate,
+        active: true,
+    };
+    env.storage().instance().set(&DataKey::Plan(plan_id), &plan);
+
+    let key = DataKey::MerchantPlans(merchant.clone());
+    let mut ids: Vec<u32> = env.storage().instance().get(&key).unwrap_or(Vec::new(env));
+    ids.push_back(plan_id);
+    env.storage().instance().set(&key, &ids);
+
+    env.events().publish(
+        (symbol_short!("plan_new"), plan_id),
+        (merchant, amount, interval_seconds),
+    );
+    Ok(plan_id)
+}
+
+/// Stop new sign-ups. Existing subscriptions are unaffected.
+pub fn do_deactivate_plan(env: &Env, merchant: Address, plan_id: u32) -> Result<(), Error> {
+    merchant.require_auth();
+
+    let mut plan = get_plan(env, plan_id)?;
+    if merchant != plan.merchant {
+        return Err(Error::Unauthorized);
+    }
+    if !plan.active {
+        return Err(Error::PlanInactive);
+    }
+
+    plan.active = false;
+    env.storage().instance().set(&DataKey::Plan(plan_id), &plan);
+    env.events()
+        .publish((symbol_short!("plan_off"), plan_id), ());
+    Ok(())
+}
+
+/// Create a subscription with the plan's current terms and optionally fund it.
+/// `initial_deposit` is subject to the same rules as on `create_subscription_with_options`.
+pub fn do_subscribe_to_plan(
+    env: &Env,
+    subscriber: Address,
+    plan_id: u32,
+    initial_deposit: i128,
+) -> Result<u64, Error> {
+    let plan = get_plan(env, plan_id)?;
+    if !plan.active {
+        return Err(Error::PlanInactive);
+    }
+    // Plan subscriptions are de-duplicated per plan rather than per merchant.
+    let plan_key = DataKey::ActivePlanSub(subscriber.clone(), plan_id);
+    require_no_live_subscription(env, &plan_key)?;
+
+    let options = SubscriptionOptions {
+        usage_rate: plan.usage_rate,
+        allow_duplicates: true,
+        initial_deposit,
+        ..Default::default()
+    };
+    let id = do_create_subscription(
+        env,
+        subscriber.clone(),
+        plan.merchant,
+        plan.amount,
+        plan.interval_seconds,
+        plan.usage_enabled,
+        options,
+    )?;
+
+    let mut sub = load_subscription(env, id)?;
+    sub.plan_id = plan_id;
+    save_subscription(env, id, &mut sub)?;
+    env.storage().instance().set(&plan_key, &id);
+
+    let count_key = DataKey::PlanSubCount(plan_id);
+    let count: u32 = env.storage().instance().get(&count_key).unwrap_or(0);
+    let count = count.checked_add(1).ok_or(Error::Overflow)?;
+    env.storage().instance().set(&count_key, &count);
+    Ok(id)
+}
+
+pub fn get_merchant_plans(env: &Env, merchant: Address) -> Vec<u32> {
+    env.storage()
+        .instance()
+        .get(&DataKey::MerchantPlans(merchant))
+        .unwrap_or(Vec::new(env))
+}
+
+/// Subscriptions ever created from the plan, including ones since cancelled.
+pub fn get_plan_subscriber_count(env: &Env, plan_id: u32) -> Result<u32, Error> {
+    get_plan(env, plan_id)?;
+    Ok(env
+        .storage()
+        .instance()
+        .get(&DataKey::PlanSubCount(plan_id))
+        .unwrap_or(0))
+}
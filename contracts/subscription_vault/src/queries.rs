@@ -2,19 +2,139 @@
 //!
 //! **PRs that only add or change read-only/query behavior should edit this file only.**
 
-use crate::types::{DataKey, Error, NextChargeInfo, Subscription, SubscriptionStatus};
+use crate::stored_sub::{read_subscription, Optional};
+use crate::types::{
+    DataKey, Error, LoyaltyStatus, NextChargeInfo, Subscription, SubscriptionStats,
+    SubscriptionStatus, UsageTier, MAX_BULK_READ,
+};
 use soroban_sdk::{contracttype, Address, Env, Vec};
 
-pub fn get_subscription(env: &Env, subscription_id: u32) -> Result<Subscription, Error> {
-    env.storage()
-        .instance()
-        .get(&DataKey::Sub(subscription_id))
-        .ok_or(Error::NotFound)
+/// Loads a subscription with `merchant` resolved through any rotation.
+pub fn get_subscription(env: &Env, subscription_id: u64) -> Result<Subscription, Error> {
+    let mut sub = read_subscription(env, subscription_id)?.ok_or(Error::NotFound)?;
+    sub.merchant = crate::merchant::resolve_merchant(env, sub.merchant);
+    Ok(sub)
+}
+
+/// Reads several subscriptions at once, in the order given. Missing ids yield
+/// `None` instead of failing the batch.
+pub fn get_subscriptions(env: &Env, ids: Vec<u64>) -> Result<Vec<Option<Subscription>>, Error> {
+    if ids.len() > MAX_BULK_READ {
+        return Err(Error::BatchTooLarge);
+    }
+    let mut result = Vec::new(env);
+    for id in ids.iter() {
+        result.push_back(get_subscription(env, id).ok());
+    }
+    Ok(result)
+}
+
+/// Lifetime billing stats; all zero for a subscription that was never charged.
+pub fn get_subscription_stats(env: &Env, subscription_id: u64) -> Result<SubscriptionStats, Error> {
+    get_subscription(env, subscription_id)?;
+    Ok(crate::stored_sub::get_entry(env, &DataKey::SubStats(subscription_id)).unwrap_or_default())
+}
+
+/// Merchant-proposed amount awaiting subscriber acceptance, if any.
+pub fn get_pending_amount_change(env: &Env, subscription_id: u64) -> Result<Option<i128>, Error> {
+    get_subscription(env, subscription_id)?;
+    Ok(crate::stored_sub::get_entry(
+        env,
+        &DataKey::PendingAmount(subscription_id),
+    ))
+}
+
+/// Address a pending transfer would move the subscription to, if any.
+pub fn get_pending_transfer(env: &Env, subscription_id: u64) -> Result<Option<Address>, Error> {
+    get_subscription(env, subscription_id)?;
+    Ok(crate::stored_sub::get_entry(
+        env,
+        &DataKey::PendingTransfer(subscription_id),
+    ))
+}
+
+/// Usage tiers for a subscription; empty when flat `usage_rate` pricing applies.
+pub fn get_usage_tiers(env: &Env, subscription_id: u64) -> Result<Vec<UsageTier>, Error> {
+    get_subscription(env, subscription_id)?;
+    Ok(
+        crate::stored_sub::get_optional(env, subscription_id, Optional::UsageTiers)
+            .unwrap_or(Vec::new(env)),
+    )
+}
+
+/// What the next interval charge bills: `amount` times the pending quantity if
+/// one is set, otherwise times `quantity`.
+pub fn period_amount(sub: &Subscription) -> Result<i128, Error> {
+    let quantity = if sub.pending_quantity > 0 {
+        sub.pending_quantity
+    } else {
+        sub.quantity
+    };
+    sub.amount
+        .checked_mul(i128::from(quantity))
+        .ok_or(Error::Overflow)
+}
+
+/// Payer recorded by `create_gift_subscription`; `None` for other subscriptions.
+pub fn get_gift_payer(env: &Env, subscription_id: u64) -> Result<Option<Address>, Error> {
+    get_subscription(env, subscription_id)?;
+    Ok(crate::stored_sub::get_optional(
+        env,
+        subscription_id,
+        Optional::GiftPayer,
+    ))
+}
+
+/// The loyalty discount applies to the next interval charge.
+pub fn loyalty_active(sub: &Subscription) -> bool {
+    let loyalty = &sub.loyalty;
+    loyalty.after_periods > 0 && loyalty.discount_bps > 0 && loyalty.streak >= loyalty.after_periods
+}
+
+pub fn get_loyalty_status(env: &Env, subscription_id: u64) -> Result<LoyaltyStatus, Error> {
+    let sub = get_subscription(env, subscription_id)?;
+    Ok(LoyaltyStatus {
+        streak: sub.loyalty.streak,
+        after_periods: sub.loyalty.after_periods,
+        discount_bps: sub.loyalty.discount_bps,
+        active: loyalty_active(&sub),
+    })
+}
+
+/// Part of the prepaid balance a subscriber must leave in the vault, by status.
+///
+/// `Active`, `Paused` and `Pending` keep one interval's [`period_amount`], so
+/// a charge right after resume or activation can still succeed.
+/// `InsufficientBalance` already failed to cover a period and terminal
+/// statuses are never charged again, so they reserve nothing.
+pub fn withdrawal_reserve(sub: &Subscription) -> i128 {
+    match sub.status {
+        SubscriptionStatus::Active | SubscriptionStatus::Paused | SubscriptionStatus::Pending => {
+            period_amount(sub).unwrap_or(i128::MAX)
+        }
+        SubscriptionStatus::InsufficientBalance
+        | SubscriptionStatus::Cancelled
+        | SubscriptionStatus::Completed
+        | SubscriptionStatus::Expired => 0,
+    }
+}
+
+/// Prepaid balance the subscriber may withdraw now: everything above
+/// [`withdrawal_reserve`].
+pub fn compute_withdrawable(sub: &Subscription) -> i128 {
+    sub.prepaid_balance
+        .saturating_sub(withdrawal_reserve(sub))
+        .max(0)
+}
+
+pub fn get_withdrawable_balance(env: &Env, subscription_id: u64) -> Result<i128, Error> {
+    let sub = get_subscription(env, subscription_id)?;
+    Ok(compute_withdrawable(&sub))
 }
 
 pub fn estimate_topup_for_intervals(
     env: &Env,
-    subscription_id: u32,
+    subscription_id: u64,
     num_intervals: u32,
 ) -> Result<i128, Error> {
     let sub = get_subscription(env, subscription_id)?;
@@ -24,8 +144,7 @@ pub fn estimate_topup_for_intervals(
     }
 
     let intervals_i128: i128 = num_intervals.into();
-    let required = sub
-        .amount
+    let required = period_amount(&sub)?
         .checked_mul(intervals_i128)
         .ok_or(Error::Overflow)?;
 
@@ -52,7 +171,7 @@ pub fn get_subscriptions_by_merchant(
     limit: u32,
 ) -> Vec<Subscription> {
     let key = DataKey::MerchantSubs(merchant);
-    let ids: Vec<u32> = env.storage().instance().get(&key).unwrap_or(Vec::new(env));
+    let ids: Vec<u64> = env.storage().instance().get(&key).unwrap_or(Vec::new(env));
 
     let len = ids.len();
     if start >= len || limit == 0 {
@@ -69,11 +188,7 @@ pub fn get_subscriptions_by_merchant(
     let mut i = start;
     while i < end {
         let sub_id = ids.get(i).unwrap();
-        if let Some(sub) = env
-            .storage()
-            .instance()
-            .get::<DataKey, Subscription>(&DataKey::Sub(sub_id))
-        {
+        if let Ok(sub) = get_subscription(env, sub_id) {
             result.push_back(sub);
         }
         i += 1;
@@ -86,7 +201,7 @@ pub fn get_subscriptions_by_merchant(
 /// Useful for dashboards and pagination metadata.
 pub fn get_merchant_subscription_count(env: &Env, merchant: Address) -> u32 {
     let key = DataKey::MerchantSubs(merchant);
-    let ids: Vec<u32> = env.storage().instance().get(&key).unwrap_or(Vec::new(env));
+    let ids: Vec<u64> = env.storage().instance().get(&key).unwrap_or(Vec::new(env));
     ids.len()
 }
 
@@ -102,8 +217,11 @@ pub fn compute_next_charge_info(subscription: &Subscription) -> NextChargeInfo {
     let is_charge_expected = match subscription.status {
         SubscriptionStatus::Active => true,
         SubscriptionStatus::InsufficientBalance => true,
+        SubscriptionStatus::Pending => false,
         SubscriptionStatus::Paused => false,
         SubscriptionStatus::Cancelled => false,
+        SubscriptionStatus::Completed => false,
+        SubscriptionStatus::Expired => false,
     };
 
     NextChargeInfo {
@@ -118,7 +236,7 @@ pub fn compute_next_charge_info(subscription: &Subscription) -> NextChargeInfo {
 #[derive(Clone, Debug)]
 pub struct SubscriptionsPage {
     /// List of subscription IDs owned by the subscriber (ordered by ID).
-    pub subscription_ids: Vec<u32>,
+    pub subscription_ids: Vec<u64>,
     /// Whether there are more subscriptions beyond this page.
     pub has_next: bool,
 }
@@ -161,25 +279,21 @@ pub struct SubscriptionsPage {
 pub fn list_subscriptions_by_subscriber(
     env: &Env,
     subscriber: Address,
-    start_from_id: u32,
+    start_from_id: u64,
     limit: u32,
 ) -> Result<SubscriptionsPage, Error> {
     if limit == 0 {
         return Err(Error::NotFound);
     }
 
-    let next_id: u32 = env.storage().instance().get(&DataKey::NextId).unwrap_or(0);
+    let next_id: u64 = env.storage().instance().get(&DataKey::NextId).unwrap_or(0);
 
     let mut subscription_ids = Vec::new(env);
     let mut count = 0u32;
     let mut last_found_id = start_from_id;
 
     for id in start_from_id..next_id {
-        match env
-            .storage()
-            .instance()
-            .get::<DataKey, Subscription>(&DataKey::Sub(id))
-        {
+        match read_subscription(env, id).ok().flatten() {
             Some(sub) => {
                 if sub.subscriber == subscriber {
                     subscription_ids.push_back(id);
@@ -197,11 +311,7 @@ pub fn list_subscriptions_by_subscriber(
     let has_next = if count >= limit {
         let mut found_next = false;
         for id in (last_found_id + 1)..next_id {
-            if let Some(sub) = env
-                .storage()
-                .instance()
-                .get::<DataKey, Subscription>(&DataKey::Sub(id))
-            {
+            if let Some(sub) = read_subscription(env, id).ok().flatten() {
                 if sub.subscriber == subscriber {
                     found_next = true;
                     break;
@@ -0,0 +1,89 @@
+//! Referral fees: a referrer named at creation earns a share of the first
+//! interval charges of the subscription.
+//!
+//! **PRs that only change referral fees should edit this file only.**
+//!
+//! The admin's [`ReferralTerms`] are copied onto the subscription when it is
+//! created, so later changes to the program never reprice existing referrals.
+//! Per charge the keeper reward comes off first; the referral fee is
+//! `bps` of what is left, rounded down, and the merchant gets the rest. The
+//! fee is credited to the referrer's balance, withdrawn like merchant earnings.
+
+use crate::math::mul_bps_floor;
+use crate::stored_sub::Optional;
+use crate::types::{Error, Referral};
+use soroban_sdk::{Address, Env, Symbol};
+
+pub fn get(env: &Env, subscription_id: u64) -> Option<Referral> {
+    crate::stored_sub::get_optional(env, subscription_id, Optional::Referral)
+}
+
+pub fn remove(env: &Env, subscription_id: u64) {
+    crate::stored_sub::remove_optional(env, subscription_id, Optional::Referral);
+}
+
+/// Record `referrer` for a new subscription under the current terms. Nothing
+/// is stored while the program is off.
+pub(crate) fn init(
+    env: &Env,
+    subscription_id: u64,
+    subscriber: &Address,
+    merchant: &Address,
+    referrer: Option<Address>,
+) -> Result<(), Error> {
+    let Some(referrer) = referrer else {
+        return Ok(());
+    };
+    if referrer == *subscriber || referrer == *merchant {
+        return Err(Error::Unauthorized);
+    }
+    crate::admin::require_not_blocked(env, &referrer)?;
+    let terms = crate::admin::get_referral_terms(env);
+    if terms.bps == 0 || terms.periods == 0 {
+        return Ok(());
+    }
+
+    crate::stored_sub::set_optional(
+        env,
+        subscription_id,
+        Optional::Referral,
+        &Referral {
+            referrer: referrer.clone(),
+            bps: terms.bps,
+            periods_left: terms.periods,
+        },
+    );
+    env.events().publish(
+        (Symbol::new(env, "referred"), subscription_id),
+        (referrer, terms.bps, terms.periods),
+    );
+    Ok(())
+}
+
+/// Referral fee out of `share` (the charge after the keeper reward) for one
+/// successful interval charge, credited to the referrer. Returns the fee.
+pub(crate) fn take(
+    env: &Env,
+    subscription_id: u64,
+    token: &Address,
+    share: i128,
+) -> Result<i128, Error> {
+    let Some(mut referral) = get(env, subscription_id) else {
+        return Ok(0);
+    };
+    let fee = mul_bps_floor(share, referral.bps)?;
+
+    referral.periods_left -= 1;
+    if referral.periods_left == 0 {
+        remove(env, subscription_id);
+    } else {
+        crate::stored_sub::set_optional(env, subscription_id, Optional::Referral, &referral);
+    }
+    crate::merchant::credit_earnings(env, &referral.referrer, token, fee)?;
+    Ok(fee)
+}
+
+pub fn get_referral(env: &Env, subscription_id: u64) -> Result<Option<Referral>, Error> {
+    crate::queries::get_subscription(env, subscription_id)?;
+    Ok(get(env, subscription_id))
+}
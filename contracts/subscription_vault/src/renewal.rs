@@ -0,0 +1,106 @@
+//! Renewal approval: subscriptions that need the subscriber's consent before
+//! each interval charge.
+//!
+//! **PRs that only change renewal approval should edit this file only.**
+
+use crate::queries::get_subscription;
+use crate::stored_sub::Optional;
+use crate::types::{Error, RenewalApproval, Subscription, SubscriptionStatus};
+use soroban_sdk::{symbol_short, Address, Env};
+
+pub fn is_required(env: &Env, subscription_id: u64) -> bool {
+    crate::stored_sub::has_optional(env, subscription_id, Optional::RenewalApprovalRequired)
+}
+
+/// The renewal an approval covers is identified by the charge number it
+/// allows, so an approval cannot carry over to a later charge.
+fn next_renewal(sub: &Subscription) -> u32 {
+    sub.periods_charged.saturating_add(1)
+}
+
+/// The next interval charge may go ahead.
+pub fn is_approved(env: &Env, subscription_id: u64, sub: &Subscription) -> bool {
+    !is_required(env, subscription_id)
+        || crate::stored_sub::get_optional::<u32>(env, subscription_id, Optional::RenewalApproved)
+            == Some(next_renewal(sub))
+}
+
+/// Use up the approval after a successful charge.
+pub fn consume(env: &Env, subscription_id: u64) {
+    crate::stored_sub::remove_optional(env, subscription_id, Optional::RenewalApproved);
+}
+
+pub fn remove(env: &Env, subscription_id: u64) {
+    consume(env, subscription_id);
+    crate::stored_sub::remove_optional(env, subscription_id, Optional::RenewalApprovalRequired);
+}
+
+/// Turn the approval requirement on or off. Subscriber or merchant.
+pub fn do_set_renewal_approval_required(
+    env: &Env,
+    subscription_id: u64,
+    authorizer: Address,
+    required: bool,
+) -> Result<(), Error> {
+    authorizer.require_auth();
+
+    let sub = get_subscription(env, subscription_id)?;
+    if authorizer != sub.subscriber && authorizer != sub.merchant {
+        return Err(Error::Unauthorized);
+    }
+
+    let entry = Optional::RenewalApprovalRequired;
+    if required {
+        crate::stored_sub::set_optional(env, subscription_id, entry, &true);
+    } else {
+        crate::stored_sub::remove_optional(env, subscription_id, entry);
+    }
+    env.events().publish(
+        (symbol_short!("renew_req"), subscription_id),
+        (required, authorizer),
+    );
+    Ok(())
+}
+
+/// Approve the next interval charge only. Subscriber only.
+pub fn do_approve_next_renewal(
+    env: &Env,
+    subscription_id: u64,
+    subscriber: Address,
+) -> Result<(), Error> {
+    subscriber.require_auth();
+
+    let sub = get_subscription(env, subscription_id)?;
+    if subscriber != sub.subscriber {
+        return Err(Error::Unauthorized);
+    }
+    if matches!(
+        sub.status,
+        SubscriptionStatus::Cancelled | SubscriptionStatus::Completed | SubscriptionStatus::Expired
+    ) {
+        return Err(Error::NotActive);
+    }
+
+    let renewal = next_renewal(&sub);
+    crate::stored_sub::set_optional(env, subscription_id, Optional::RenewalApproved, &renewal);
+    env.events().publish(
+        (symbol_short!("approved"), subscription_id),
+        (renewal, due_at(&sub)),
+    );
+    Ok(())
+}
+
+pub fn get_renewal_approval(env: &Env, subscription_id: u64) -> Result<RenewalApproval, Error> {
+    let sub = get_subscription(env, subscription_id)?;
+    Ok(RenewalApproval {
+        required: is_required(env, subscription_id),
+        approved: is_approved(env, subscription_id, &sub),
+        renewal: next_renewal(&sub),
+        due_at: due_at(&sub),
+    })
+}
+
+fn due_at(sub: &Subscription) -> u64 {
+    sub.last_payment_timestamp
+        .saturating_add(sub.interval_seconds)
+}
@@ -36,7 +36,7 @@ pub fn safe_add(a: i128, b: i128) -> Result<i128, Error> {
 /// Safely subtracts two i128 values, preventing underflow.
 ///
 /// Uses Rust's `checked_sub()` to detect underflow conditions. If the subtraction
-/// would go below `i128::MIN`, returns `Error::Underflow` instead of panicking.
+/// would go below `i128::MIN`, returns `Error::Overflow` instead of panicking.
 ///
 /// # Arguments
 ///
@@ -46,7 +46,7 @@ pub fn safe_add(a: i128, b: i128) -> Result<i128, Error> {
 /// # Returns
 ///
 /// * `Ok(i128)` - The difference of `a` and `b` if no underflow occurs
-/// * `Err(Error::Underflow)` - If the result would go below `i128::MIN`
+/// * `Err(Error::Overflow)` - If the result would go below `i128::MIN`
 ///
 /// # Examples
 ///
@@ -55,7 +55,7 @@ pub fn safe_add(a: i128, b: i128) -> Result<i128, Error> {
 /// use subscription_vault::Error;
 ///
 /// assert_eq!(safe_sub(200, 100), Ok(100));
-/// assert_eq!(safe_sub(i128::MIN, 1), Err(Error::Underflow));
+/// assert_eq!(safe_sub(i128::MIN, 1), Err(Error::Overflow));
 /// ```
 ///
 /// # Compatibility
@@ -115,7 +115,7 @@ pub fn validate_non_negative(amount: i128) -> Result<(), Error> {
 /// # Returns
 ///
 /// * `Ok(i128)` - The new balance after adding the amount
-/// * `Err(Error::Underflow)` - If `amount` is negative
+/// * `Err(Error::Overflow)` - If `amount` is negative
 /// * `Err(Error::Overflow)` - If the result would exceed `i128::MAX`
 ///
 /// # Guarantees
@@ -129,7 +129,7 @@ pub fn validate_non_negative(amount: i128) -> Result<(), Error> {
 /// use subscription_vault::Error;
 ///
 /// assert_eq!(safe_add_balance(1000, 500), Ok(1500));
-/// assert_eq!(safe_add_balance(1000, -100), Err(Error::Underflow));
+/// assert_eq!(safe_add_balance(1000, -100), Err(Error::Overflow));
 /// assert_eq!(safe_add_balance(i128::MAX, 1), Err(Error::Overflow));
 /// ```
 ///
@@ -158,8 +158,8 @@ pub fn safe_add_balance(balance: i128, amount: i128) -> Result<i128, Error> {
 /// # Returns
 ///
 /// * `Ok(i128)` - The new balance after subtracting the amount (always >= 0)
-/// * `Err(Error::Underflow)` - If `amount` is negative, or if the result would be negative
-/// * `Err(Error::Underflow)` - If the subtraction would go below `i128::MIN`
+/// * `Err(Error::Overflow)` - If `amount` is negative, or if the result would be negative
+/// * `Err(Error::Overflow)` - If the subtraction would go below `i128::MIN`
 ///
 /// # Guarantees
 ///
@@ -173,8 +173,8 @@ pub fn safe_add_balance(balance: i128, amount: i128) -> Result<i128, Error> {
 ///
 /// assert_eq!(safe_sub_balance(1000, 500), Ok(500));
 /// assert_eq!(safe_sub_balance(1000, 1000), Ok(0));
-/// assert_eq!(safe_sub_balance(1000, 1500), Err(Error::Underflow));
-/// assert_eq!(safe_sub_balance(1000, -100), Err(Error::Underflow));
+/// assert_eq!(safe_sub_balance(1000, 1500), Err(Error::Overflow));
+/// assert_eq!(safe_sub_balance(1000, -100), Err(Error::Overflow));
 /// ```
 ///
 /// # Compatibility
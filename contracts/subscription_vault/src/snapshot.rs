@@ -0,0 +1,35 @@
+//! Snapshot events: the full `Subscription` record in one event, so an indexer
+//! that missed ledgers can resynchronize a subscription without replaying the
+//! incremental events.
+//!
+//! **PRs that only change snapshot events should edit this file only.**
+//!
+//! A snapshot is published by the permissionless `snapshot` entrypoint, and
+//! automatically whenever a save changes a subscription's status, since a
+//! transition always rewrites several fields at once (status, `status_changed_at`,
+//! and usually balances or counters). The whole record is one event: even with
+//! maximum-size metadata it is far below the event size limit, the same
+//! payload `purged` already carries.
+
+use crate::queries::get_subscription;
+use crate::types::{Error, Subscription, SubscriptionSnapshotEvent};
+use soroban_sdk::{symbol_short, Env};
+
+pub fn publish(env: &Env, subscription_id: u64, sub: &Subscription) {
+    env.events().publish(
+        (symbol_short!("snapshot"), subscription_id),
+        SubscriptionSnapshotEvent {
+            subscription_id,
+            subscription: sub.clone(),
+            ledger: env.ledger().sequence(),
+            timestamp: env.ledger().timestamp(),
+        },
+    );
+}
+
+/// Publish the current record of `subscription_id`. Reads only; anyone may call it.
+pub fn do_snapshot(env: &Env, subscription_id: u64) -> Result<(), Error> {
+    let sub = get_subscription(env, subscription_id)?;
+    publish(env, subscription_id, &sub);
+    Ok(())
+}
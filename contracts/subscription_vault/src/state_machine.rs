@@ -3,7 +3,39 @@
 //! Kept in a separate module so PRs touching state transitions do not conflict
 //! with PRs touching billing, batch charge, or top-up estimation.
 
-use crate::types::{Error, SubscriptionStatus};
+use crate::types::{Error, Subscription, SubscriptionStatus};
+use soroban_sdk::{Env, Vec};
+
+/// Every status, in discriminant order.
+pub const ALL_STATUSES: [SubscriptionStatus; 7] = [
+    SubscriptionStatus::Active,
+    SubscriptionStatus::Paused,
+    SubscriptionStatus::Cancelled,
+    SubscriptionStatus::InsufficientBalance,
+    SubscriptionStatus::Completed,
+    SubscriptionStatus::Pending,
+    SubscriptionStatus::Expired,
+];
+
+// A new status fails to compile here until it is added to `ALL_STATUSES`.
+const _: () = {
+    const fn position(status: &SubscriptionStatus) -> usize {
+        match status {
+            SubscriptionStatus::Active => 0,
+            SubscriptionStatus::Paused => 1,
+            SubscriptionStatus::Cancelled => 2,
+            SubscriptionStatus::InsufficientBalance => 3,
+            SubscriptionStatus::Completed => 4,
+            SubscriptionStatus::Pending => 5,
+            SubscriptionStatus::Expired => 6,
+        }
+    }
+    let mut i = 0;
+    while i < ALL_STATUSES.len() {
+        assert!(position(&ALL_STATUSES[i]) == i);
+        i += 1;
+    }
+};
 
 /// Validates if a status transition is allowed by the state machine.
 ///
@@ -11,14 +43,24 @@ use crate::types::{Error, SubscriptionStatus};
 ///
 /// | From              | To                  | Allowed |
 /// |-------------------|---------------------|---------|
+/// | Pending           | Active              | Yes     |
+/// | Pending           | Cancelled           | Yes     |
+/// | Pending           | Expired             | Yes     |
 /// | Active            | Paused              | Yes     |
 /// | Active            | Cancelled           | Yes     |
 /// | Active            | InsufficientBalance | Yes     |
+/// | Active            | Completed           | Yes     |
+/// | Active            | Expired             | Yes     |
 /// | Paused            | Active              | Yes     |
 /// | Paused            | Cancelled           | Yes     |
+/// | Paused            | InsufficientBalance | Yes     |
+/// | Paused            | Expired             | Yes     |
 /// | InsufficientBalance | Active            | Yes     |
 /// | InsufficientBalance | Cancelled         | Yes     |
+/// | InsufficientBalance | Expired           | Yes     |
 /// | Cancelled         | *any*               | No      |
+/// | Completed         | *any*               | No      |
+/// | Expired           | *any*               | No      |
 /// | *any*             | Same status         | Yes (idempotent) |
 ///
 /// # Arguments
@@ -37,25 +79,37 @@ pub fn validate_status_transition(
     }
 
     let valid = match from {
+        SubscriptionStatus::Pending => matches!(
+            to,
+            SubscriptionStatus::Active
+                | SubscriptionStatus::Cancelled
+                | SubscriptionStatus::Expired
+        ),
         SubscriptionStatus::Active => matches!(
             to,
             SubscriptionStatus::Paused
                 | SubscriptionStatus::Cancelled
                 | SubscriptionStatus::InsufficientBalance
+                | SubscriptionStatus::Completed
+                | SubscriptionStatus::Expired
+        ),
+        // Paused -> InsufficientBalance: a usage charge allowed while paused drained the balance.
+        SubscriptionStatus::Paused => matches!(
+            to,
+            SubscriptionStatus::Active
+                | SubscriptionStatus::Cancelled
+                | SubscriptionStatus::InsufficientBalance
+                | SubscriptionStatus::Expired
         ),
-        SubscriptionStatus::Paused => {
-            matches!(
-                to,
-                SubscriptionStatus::Active | SubscriptionStatus::Cancelled
-            )
-        }
         SubscriptionStatus::Cancelled => false,
-        SubscriptionStatus::InsufficientBalance => {
-            matches!(
-                to,
-                SubscriptionStatus::Active | SubscriptionStatus::Cancelled
-            )
-        }
+        SubscriptionStatus::InsufficientBalance => matches!(
+            to,
+            SubscriptionStatus::Active
+                | SubscriptionStatus::Cancelled
+                | SubscriptionStatus::Expired
+        ),
+        SubscriptionStatus::Completed => false,
+        SubscriptionStatus::Expired => false,
     };
 
     if valid {
@@ -70,22 +124,78 @@ pub fn validate_status_transition(
 /// This is useful for UI/documentation to show available actions.
 pub fn get_allowed_transitions(status: &SubscriptionStatus) -> &'static [SubscriptionStatus] {
     match status {
+        SubscriptionStatus::Pending => &[
+            SubscriptionStatus::Active,
+            SubscriptionStatus::Cancelled,
+            SubscriptionStatus::Expired,
+        ],
         SubscriptionStatus::Active => &[
             SubscriptionStatus::Paused,
             SubscriptionStatus::Cancelled,
             SubscriptionStatus::InsufficientBalance,
+            SubscriptionStatus::Completed,
+            SubscriptionStatus::Expired,
+        ],
+        SubscriptionStatus::Paused => &[
+            SubscriptionStatus::Active,
+            SubscriptionStatus::Cancelled,
+            SubscriptionStatus::InsufficientBalance,
+            SubscriptionStatus::Expired,
         ],
-        SubscriptionStatus::Paused => &[SubscriptionStatus::Active, SubscriptionStatus::Cancelled],
         SubscriptionStatus::Cancelled => &[],
-        SubscriptionStatus::InsufficientBalance => {
-            &[SubscriptionStatus::Active, SubscriptionStatus::Cancelled]
-        }
+        SubscriptionStatus::InsufficientBalance => &[
+            SubscriptionStatus::Active,
+            SubscriptionStatus::Cancelled,
+            SubscriptionStatus::Expired,
+        ],
+        SubscriptionStatus::Completed => &[],
+        SubscriptionStatus::Expired => &[],
     }
 }
 
+/// The transition table as data: each status in [`ALL_STATUSES`] with its
+/// [`get_allowed_transitions`]. Same-status transitions are implied and not
+/// listed.
+pub fn describe_state_machine(env: &Env) -> Vec<(SubscriptionStatus, Vec<SubscriptionStatus>)> {
+    let mut table = Vec::new(env);
+    for status in ALL_STATUSES {
+        let targets = Vec::from_slice(env, get_allowed_transitions(&status));
+        table.push_back((status, targets));
+    }
+    table
+}
+
 /// Checks if a transition is valid without returning an error.
 ///
 /// Convenience wrapper around [`validate_status_transition`] for boolean checks.
 pub fn can_transition(from: &SubscriptionStatus, to: &SubscriptionStatus) -> bool {
     validate_status_transition(from, to).is_ok()
 }
+
+/// Validates and applies a status transition to `sub`.
+///
+/// Stamps `status_changed_at` with `now` when the status actually changes
+/// (idempotent same-status transitions leave it untouched). Moving to
+/// `InsufficientBalance`, `Cancelled` or `Expired` breaks the payment streak. Returns the
+/// previous status so callers can include it in event payloads.
+pub fn apply_transition(
+    sub: &mut Subscription,
+    to: SubscriptionStatus,
+    now: u64,
+) -> Result<SubscriptionStatus, Error> {
+    validate_status_transition(&sub.status, &to)?;
+    let previous = sub.status.clone();
+    if matches!(
+        to,
+        SubscriptionStatus::InsufficientBalance
+            | SubscriptionStatus::Cancelled
+            | SubscriptionStatus::Expired
+    ) {
+        sub.loyalty.streak = 0;
+    }
+    if previous != to {
+        sub.status = to;
+        sub.status_changed_at = now;
+    }
+    Ok(previous)
+}
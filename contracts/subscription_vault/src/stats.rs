@@ -0,0 +1,194 @@
+//! Vault-wide aggregates: total value locked and subscriptions per status.
+//!
+//! **PRs that only change vault statistics should edit this file only.**
+//!
+//! Summing over every subscription would exceed instruction limits, so the
+//! aggregates are kept incrementally: every subscription write goes through
+//! [`save_subscription`], which applies the difference from the values the
+//! record was loaded with by [`load_subscription`].
+
+use crate::stored_sub::{read_record, write_subscription};
+use crate::types::{
+    DataKey, Error, Subscription, SubscriptionMeta, SubscriptionStatus, VaultStats,
+};
+use core::ops::{Deref, DerefMut};
+use soroban_sdk::{Address, Env, Map};
+
+/// The values a stored record was last counted with in the aggregates.
+struct Counted {
+    prepaid_balance: i128,
+    status: SubscriptionStatus,
+    merchant: Address,
+}
+
+impl Counted {
+    fn of(sub: &Subscription) -> Self {
+        Counted {
+            prepaid_balance: sub.prepaid_balance,
+            status: sub.status.clone(),
+            merchant: sub.merchant.clone(),
+        }
+    }
+}
+
+/// A subscription read for update by [`load_subscription`]. Derefs to the
+/// record; it also remembers what the aggregates hold for it, so
+/// [`save_subscription`] applies the difference without reading it again,
+/// and the meta half as stored, so an unchanged one is not rewritten.
+pub struct LoadedSubscription {
+    sub: Subscription,
+    counted: Option<Counted>,
+    meta: Option<SubscriptionMeta>,
+}
+
+impl LoadedSubscription {
+    /// A record not stored yet; its first save counts it.
+    pub fn new(sub: Subscription) -> Self {
+        LoadedSubscription {
+            sub,
+            counted: None,
+            meta: None,
+        }
+    }
+}
+
+impl Deref for LoadedSubscription {
+    type Target = Subscription;
+
+    fn deref(&self) -> &Subscription {
+        &self.sub
+    }
+}
+
+impl DerefMut for LoadedSubscription {
+    fn deref_mut(&mut self) -> &mut Subscription {
+        &mut self.sub
+    }
+}
+
+/// Read `subscription_id` for update, with its merchant resolved through any
+/// rotation. Read-only paths use `queries::get_subscription`.
+pub fn load_subscription(env: &Env, subscription_id: u64) -> Result<LoadedSubscription, Error> {
+    let (mut sub, meta) = read_record(env, subscription_id)?.ok_or(Error::NotFound)?;
+    sub.merchant = crate::merchant::resolve_merchant(env, sub.merchant);
+    let counted = Some(Counted::of(&sub));
+    Ok(LoadedSubscription { sub, counted, meta })
+}
+
+/// Persist `sub`, extend its TTL and fold its balance and status change into
+/// the aggregates. A status change also stamps `status_changed_at` and
+/// publishes a full snapshot (see `snapshot.rs`), so no path can skip either.
+pub fn save_subscription(
+    env: &Env,
+    subscription_id: u64,
+    loaded: &mut LoadedSubscription,
+) -> Result<(), Error> {
+    let storage = env.storage().instance();
+    let previous = loaded.counted.take();
+    let sub = &mut loaded.sub;
+    let old_balance = previous.as_ref().map_or(0, |old| old.prepaid_balance);
+
+    if old_balance != sub.prepaid_balance {
+        let delta = sub
+            .prepaid_balance
+            .checked_sub(old_balance)
+            .ok_or(Error::Overflow)?;
+        let tvl = get_tvl(env).checked_add(delta).ok_or(Error::Overflow)?;
+        storage.set(&DataKey::Tvl, &tvl);
+    }
+
+    let was_active = previous
+        .as_ref()
+        .filter(|old| old.status == SubscriptionStatus::Active)
+        .map(|old| &old.merchant);
+    crate::merchant_stats::on_save(env, was_active, sub)?;
+
+    let old_status = previous.map(|old| old.status);
+    let transitioned = old_status.as_ref().is_some_and(|old| *old != sub.status);
+    if old_status.as_ref() != Some(&sub.status) {
+        let mut counts = status_counts(env);
+        if let Some(old) = old_status {
+            let count = counts.get(old.clone()).unwrap_or(0);
+            counts.set(old, count.saturating_sub(1));
+        }
+        let count = counts.get(sub.status.clone()).unwrap_or(0);
+        counts.set(
+            sub.status.clone(),
+            count.checked_add(1).ok_or(Error::Overflow)?,
+        );
+        storage.set(&DataKey::StatusCounts, &counts);
+    }
+    if transitioned {
+        sub.status_changed_at = env.ledger().timestamp();
+    }
+
+    loaded.meta = Some(write_subscription(
+        env,
+        subscription_id,
+        sub,
+        loaded.meta.as_ref(),
+    ));
+    crate::ttl::bump_subscription(env, subscription_id);
+    if transitioned {
+        crate::snapshot::publish(env, subscription_id, sub);
+    }
+    loaded.counted = Some(Counted::of(&loaded.sub));
+    Ok(())
+}
+
+/// Delete a subscription record, dropping it from the aggregates.
+pub fn remove_subscription(env: &Env, subscription_id: u64, sub: &Subscription) {
+    let storage = env.storage().instance();
+    let mut counts = status_counts(env);
+    let count = counts.get(sub.status.clone()).unwrap_or(0);
+    counts.set(sub.status.clone(), count.saturating_sub(1));
+    storage.set(&DataKey::StatusCounts, &counts);
+    if sub.prepaid_balance != 0 {
+        storage.set(
+            &DataKey::Tvl,
+            &get_tvl(env).saturating_sub(sub.prepaid_balance),
+        );
+    }
+    crate::stored_sub::delete_subscription(env, subscription_id);
+}
+
+/// Sum of all subscriptions' `prepaid_balance`.
+pub fn get_tvl(env: &Env) -> i128 {
+    env.storage().instance().get(&DataKey::Tvl).unwrap_or(0)
+}
+
+fn status_counts(env: &Env) -> Map<SubscriptionStatus, u32> {
+    env.storage()
+        .instance()
+        .get(&DataKey::StatusCounts)
+        .unwrap_or(Map::new(env))
+}
+
+pub fn get_stats(env: &Env) -> VaultStats {
+    let counts = status_counts(env);
+    let count = |status| counts.get(status).unwrap_or(0);
+    let active = count(SubscriptionStatus::Active);
+    let paused = count(SubscriptionStatus::Paused);
+    let cancelled = count(SubscriptionStatus::Cancelled);
+    let insufficient_balance = count(SubscriptionStatus::InsufficientBalance);
+    let completed = count(SubscriptionStatus::Completed);
+    let pending = count(SubscriptionStatus::Pending);
+    let expired = count(SubscriptionStatus::Expired);
+    VaultStats {
+        tvl: get_tvl(env),
+        total_subscriptions: active
+            .saturating_add(paused)
+            .saturating_add(cancelled)
+            .saturating_add(insufficient_balance)
+            .saturating_add(completed)
+            .saturating_add(pending)
+            .saturating_add(expired),
+        active,
+        paused,
+        cancelled,
+        insufficient_balance,
+        completed,
+        pending,
+        expired,
+    }
+}
@@ -0,0 +1,444 @@
+//! Versioned subscription storage: records under `DataKey::Sub` are upgraded
+//! lazily when read.
+//!
+//! **PRs that only change subscription storage versions should edit this file only.**
+//!
+//! A subscription is stored in two halves: the [`SubscriptionCore`] billing
+//! rewrites under `DataKey::Sub`, and the [`SubscriptionMeta`] set at creation
+//! or by configuration calls under `DataKeyExt::SubMeta`. Callers only see the
+//! joined [`Subscription`]. [`write_subscription`] skips the meta write when
+//! that half is unchanged, so a charge, deposit or usage debit writes half the
+//! bytes and its cost no longer grows with `metadata` or the callback address.
+//!
+//! Reads accept every shape an earlier release may have left behind: a bare
+//! unsplit `Subscription`, a bare v1 record and the [`StoredSub`] envelope.
+//! Older shapes are upgraded in memory with creation defaults; the split form
+//! is written back by the next mutation.
+//!
+//! Current layouts are stored bare rather than wrapped: the envelope adds
+//! an object per record to every load, which pushes a 50-subscription
+//! `batch_charge` over the CPU budget. Bare layouts are told apart by field
+//! count, so adding one means giving it a count no other layout has, or
+//! moving the outgoing one into a `StoredSub` variant, and adding an upgrade
+//! step here.
+//!
+//! Records live in persistent storage, so a call only pays for the
+//! subscriptions it touches. So does every other entry keyed by a
+//! subscription id, through [`get_entry`], [`set_entry`] and
+//! [`remove_entry`]. The [`Optional`] ones are recorded in a bitmask, so
+//! billing does not look up entries a subscription never had. Earlier
+//! releases kept all of these in instance storage; such an entry is still
+//! read from there and moves out on its next write.
+
+use crate::types::{
+    CouponKind, DataKey, DataKeyExt, Error, FundingMode, Loyalty, StoredSub, Subscription,
+    SubscriptionCore, SubscriptionMeta, SubscriptionV1,
+};
+use soroban_sdk::{Env, IntoVal, Map, Symbol, TryFromVal, Val};
+
+/// Field count of a bare [`SubscriptionV1`].
+const V1_FIELDS: u32 = 8;
+/// Field count of a bare [`SubscriptionCore`].
+const CORE_FIELDS: u32 = 20;
+
+/// The record stored for `subscription_id`, upgraded to the current layout.
+pub fn read_subscription(env: &Env, subscription_id: u64) -> Result<Option<Subscription>, Error> {
+    Ok(read_record(env, subscription_id)?.map(|(sub, _)| sub))
+}
+
+/// Like [`read_subscription`], also returning the meta half as stored, or
+/// `None` when the record is not split yet. `stats::load_subscription` hands
+/// it back to [`write_subscription`] so an unchanged half is not rewritten.
+pub(crate) fn read_record(
+    env: &Env,
+    subscription_id: u64,
+) -> Result<Option<(Subscription, Option<SubscriptionMeta>)>, Error> {
+    let key = DataKey::Sub(subscription_id);
+    let Some(raw) = env
+        .storage()
+        .persistent()
+        .get::<_, Val>(&key)
+        .or_else(|| env.storage().instance().get::<_, Val>(&key))
+    else {
+        return Ok(None);
+    };
+    // Decoding a struct from a map of the wrong shape traps instead of
+    // returning an error, so tell the layouts apart before decoding: the
+    // envelope is a vector, and bare records differ in field count.
+    let stored = match Map::<Symbol, Val>::try_from_val(env, &raw) {
+        Err(_) => StoredSub::try_from_val(env, &raw).map_err(|_| Error::NotFound)?,
+        Ok(fields) if fields.len() == V1_FIELDS => {
+            StoredSub::V1(SubscriptionV1::try_from_val(env, &raw).map_err(|_| Error::NotFound)?)
+        }
+        Ok(fields) if fields.len() == CORE_FIELDS => {
+            let core = SubscriptionCore::try_from_val(env, &raw).map_err(|_| Error::NotFound)?;
+            let meta: SubscriptionMeta = env
+                .storage()
+                .persistent()
+                .get(&DataKeyExt::SubMeta(subscription_id))
+                .ok_or(Error::NotFound)?;
+            return Ok(Some((join(core, meta.clone()), Some(meta))));
+        }
+        Ok(_) => StoredSub::V2(Subscription::try_from_val(env, &raw).map_err(|_| Error::NotFound)?),
+    };
+    match stored {
+        StoredSub::V1(v1) => upgrade_v1(env, v1).map(|sub| Some((sub, None))),
+        StoredSub::V2(sub) => Ok(Some((sub, None))),
+    }
+}
+
+/// Whether a record exists for `subscription_id`, in either storage.
+pub fn has_subscription(env: &Env, subscription_id: u64) -> bool {
+    let key = DataKey::Sub(subscription_id);
+    env.storage().persistent().has(&key) || env.storage().instance().has(&key)
+}
+
+/// Store `sub` split in two, writing the meta half only when it differs from
+/// `stored_meta`. Returns the meta half now stored. Only
+/// `stats::save_subscription` calls this; it bumps both entries through
+/// `ttl::bump_subscription`.
+pub(crate) fn write_subscription(
+    env: &Env,
+    subscription_id: u64,
+    sub: &Subscription,
+    stored_meta: Option<&SubscriptionMeta>,
+) -> SubscriptionMeta {
+    let (core, meta) = split(sub);
+    let key = DataKey::Sub(subscription_id);
+    env.storage().persistent().set(&key, &core);
+    drop_instance_copy(env, &key);
+    if stored_meta != Some(&meta) {
+        env.storage()
+            .persistent()
+            .set(&DataKeyExt::SubMeta(subscription_id), &meta);
+    }
+    meta
+}
+
+/// Delete the record. Only `stats::remove_subscription` calls this.
+pub(crate) fn delete_subscription(env: &Env, subscription_id: u64) {
+    remove_entry(env, &DataKey::Sub(subscription_id));
+    let persistent = env.storage().persistent();
+    persistent.remove(&DataKeyExt::SubMeta(subscription_id));
+    persistent.remove(&DataKeyExt::SubEntries(subscription_id));
+}
+
+/// A per-subscription entry (a `DataKey` or `DataKeyExt`), wherever the
+/// release that wrote it put it.
+pub(crate) fn get_entry<K, V>(env: &Env, key: &K) -> Option<V>
+where
+    K: IntoVal<Env, Val>,
+    V: TryFromVal<Env, Val>,
+{
+    env.storage()
+        .persistent()
+        .get(key)
+        .or_else(|| env.storage().instance().get(key))
+}
+
+/// Write a per-subscription entry to persistent storage and keep it alive.
+pub(crate) fn set_entry<K, V>(env: &Env, key: &K, value: &V)
+where
+    K: IntoVal<Env, Val>,
+    V: IntoVal<Env, Val>,
+{
+    env.storage().persistent().set(key, value);
+    crate::ttl::bump_persistent(env, key);
+    drop_instance_copy(env, key);
+}
+
+pub(crate) fn remove_entry<K: IntoVal<Env, Val>>(env: &Env, key: &K) {
+    env.storage().persistent().remove(key);
+    drop_instance_copy(env, key);
+}
+
+/// Per-subscription entries most subscriptions never have, each with a bit
+/// in `DataKeyExt::SubEntries`. Billing looks several of them up on every
+/// charge; the bits let it skip the persistent lookup of absent ones, and
+/// let [`bump_optional`] keep the present ones alive with the record.
+#[derive(Clone, Copy)]
+pub(crate) enum Optional {
+    BillingAnchor,
+    FiatPricing,
+    RenewalApprovalRequired,
+    RenewalApproved,
+    LifetimeCap,
+    AutoTopup,
+    Referral,
+    TrialBond,
+    NotifyAddress,
+    UsageTiers,
+    DelegatePayer,
+    GiftPayer,
+    UsageWhilePaused,
+}
+
+impl Optional {
+    const ALL: [Optional; 13] = [
+        Optional::BillingAnchor,
+        Optional::FiatPricing,
+        Optional::RenewalApprovalRequired,
+        Optional::RenewalApproved,
+        Optional::LifetimeCap,
+        Optional::AutoTopup,
+        Optional::Referral,
+        Optional::TrialBond,
+        Optional::NotifyAddress,
+        Optional::UsageTiers,
+        Optional::DelegatePayer,
+        Optional::GiftPayer,
+        Optional::UsageWhilePaused,
+    ];
+
+    fn bit(self) -> u32 {
+        1 << self as u32
+    }
+
+    fn key(self, env: &Env, id: u64) -> Val {
+        match self {
+            Optional::BillingAnchor => DataKeyExt::BillingAnchor(id).into_val(env),
+            Optional::FiatPricing => DataKeyExt::FiatPricing(id).into_val(env),
+            Optional::RenewalApprovalRequired => {
+                DataKeyExt::RenewalApprovalRequired(id).into_val(env)
+            }
+            Optional::RenewalApproved => DataKeyExt::RenewalApproved(id).into_val(env),
+            Optional::LifetimeCap => DataKeyExt::LifetimeCap(id).into_val(env),
+            Optional::AutoTopup => DataKeyExt::AutoTopup(id).into_val(env),
+            Optional::Referral => DataKeyExt::Referral(id).into_val(env),
+            Optional::TrialBond => DataKeyExt::TrialBond(id).into_val(env),
+            Optional::NotifyAddress => DataKeyExt::NotifyAddress(id).into_val(env),
+            Optional::UsageTiers => DataKey::UsageTiers(id).into_val(env),
+            Optional::DelegatePayer => DataKeyExt::DelegatePayer(id).into_val(env),
+            Optional::GiftPayer => DataKey::GiftPayer(id).into_val(env),
+            Optional::UsageWhilePaused => DataKeyExt::UsageWhilePaused(id).into_val(env),
+        }
+    }
+}
+
+/// The [`Optional`] bits set for `subscription_id`.
+fn present(env: &Env, subscription_id: u64) -> u32 {
+    env.storage()
+        .persistent()
+        .get(&DataKeyExt::SubEntries(subscription_id))
+        .unwrap_or(0)
+}
+
+/// An [`Optional`] entry of `subscription_id`. One without its bit can only
+/// be a copy an earlier release left in instance storage.
+pub(crate) fn get_optional<V: TryFromVal<Env, Val>>(
+    env: &Env,
+    subscription_id: u64,
+    entry: Optional,
+) -> Option<V> {
+    let key = entry.key(env, subscription_id);
+    if present(env, subscription_id) & entry.bit() != 0 {
+        get_entry(env, &key)
+    } else {
+        env.storage().instance().get(&key)
+    }
+}
+
+pub(crate) fn has_optional(env: &Env, subscription_id: u64, entry: Optional) -> bool {
+    let key = entry.key(env, subscription_id);
+    present(env, subscription_id) & entry.bit() != 0 || env.storage().instance().has(&key)
+}
+
+pub(crate) fn set_optional<V: IntoVal<Env, Val>>(
+    env: &Env,
+    subscription_id: u64,
+    entry: Optional,
+    value: &V,
+) {
+    set_entry(env, &entry.key(env, subscription_id), value);
+    mark(
+        env,
+        subscription_id,
+        present(env, subscription_id) | entry.bit(),
+    );
+}
+
+pub(crate) fn remove_optional(env: &Env, subscription_id: u64, entry: Optional) {
+    remove_entry(env, &entry.key(env, subscription_id));
+    mark(
+        env,
+        subscription_id,
+        present(env, subscription_id) & !entry.bit(),
+    );
+}
+
+fn mark(env: &Env, subscription_id: u64, bits: u32) {
+    if bits == present(env, subscription_id) {
+        return;
+    }
+    let key = DataKeyExt::SubEntries(subscription_id);
+    if bits == 0 {
+        env.storage().persistent().remove(&key);
+    } else {
+        env.storage().persistent().set(&key, &bits);
+        crate::ttl::bump_persistent(env, &key);
+    }
+}
+
+/// Extend the [`Optional`] entries `subscription_id` has. Only
+/// `ttl::bump_subscription` calls this.
+pub(crate) fn bump_optional(env: &Env, subscription_id: u64) {
+    let bits = present(env, subscription_id);
+    if bits == 0 {
+        return;
+    }
+    crate::ttl::bump_persistent(env, &DataKeyExt::SubEntries(subscription_id));
+    for entry in Optional::ALL {
+        if bits & entry.bit() != 0 {
+            crate::ttl::bump_persistent(env, &entry.key(env, subscription_id));
+        }
+    }
+}
+
+/// Remove a record an earlier release left in instance storage. Checked
+/// first so an untouched instance is not rewritten.
+fn drop_instance_copy<K: IntoVal<Env, Val>>(env: &Env, key: &K) {
+    let instance = env.storage().instance();
+    if instance.has(key) {
+        instance.remove(key);
+    }
+}
+
+fn split(sub: &Subscription) -> (SubscriptionCore, SubscriptionMeta) {
+    let core = SubscriptionCore {
+        amount: sub.amount,
+        interval_seconds: sub.interval_seconds,
+        last_payment_timestamp: sub.last_payment_timestamp,
+        status: sub.status.clone(),
+        prepaid_balance: sub.prepaid_balance,
+        periods_charged: sub.periods_charged,
+        status_changed_at: sub.status_changed_at,
+        period_units_used: sub.period_units_used,
+        usage_charged_this_period: sub.usage_charged_this_period,
+        failed_charge_count: sub.failed_charge_count,
+        arrears: sub.arrears,
+        next_interval_seconds: sub.next_interval_seconds,
+        usage_window_start: sub.usage_window_start,
+        usage_window_count: sub.usage_window_count,
+        last_usage_nonce: sub.last_usage_nonce,
+        quantity: sub.quantity,
+        pending_quantity: sub.pending_quantity,
+        discount: sub.discount.clone(),
+        discount_periods_left: sub.discount_periods_left,
+        loyalty: sub.loyalty.clone(),
+    };
+    let meta = SubscriptionMeta {
+        subscriber: sub.subscriber.clone(),
+        merchant: sub.merchant.clone(),
+        token: sub.token.clone(),
+        usage_enabled: sub.usage_enabled,
+        total_refunded: sub.total_refunded,
+        trial_until: sub.trial_until,
+        usage_during_trial: sub.usage_during_trial,
+        max_periods: sub.max_periods,
+        cancel_at: sub.cancel_at,
+        created_at: sub.created_at,
+        usage_rate: sub.usage_rate,
+        usage_cap_per_period: sub.usage_cap_per_period,
+        allow_partial_charge: sub.allow_partial_charge,
+        plan_id: sub.plan_id,
+        funding_mode: sub.funding_mode.clone(),
+        metadata: sub.metadata.clone(),
+        low_balance_threshold: sub.low_balance_threshold,
+        reason: sub.reason,
+        charge_callback: sub.charge_callback.clone(),
+        callback_required: sub.callback_required,
+    };
+    (core, meta)
+}
+
+fn join(core: SubscriptionCore, meta: SubscriptionMeta) -> Subscription {
+    Subscription {
+        subscriber: meta.subscriber,
+        merchant: meta.merchant,
+        amount: core.amount,
+        interval_seconds: core.interval_seconds,
+        last_payment_timestamp: core.last_payment_timestamp,
+        status: core.status,
+        prepaid_balance: core.prepaid_balance,
+        usage_enabled: meta.usage_enabled,
+        total_refunded: meta.total_refunded,
+        token: meta.token,
+        trial_until: meta.trial_until,
+        usage_during_trial: meta.usage_during_trial,
+        max_periods: meta.max_periods,
+        periods_charged: core.periods_charged,
+        cancel_at: meta.cancel_at,
+        created_at: meta.created_at,
+        status_changed_at: core.status_changed_at,
+        usage_rate: meta.usage_rate,
+        period_units_used: core.period_units_used,
+        usage_cap_per_period: meta.usage_cap_per_period,
+        usage_charged_this_period: core.usage_charged_this_period,
+        failed_charge_count: core.failed_charge_count,
+        allow_partial_charge: meta.allow_partial_charge,
+        arrears: core.arrears,
+        next_interval_seconds: core.next_interval_seconds,
+        plan_id: meta.plan_id,
+        funding_mode: meta.funding_mode,
+        metadata: meta.metadata,
+        low_balance_threshold: meta.low_balance_threshold,
+        reason: meta.reason,
+        usage_window_start: core.usage_window_start,
+        usage_window_count: core.usage_window_count,
+        last_usage_nonce: core.last_usage_nonce,
+        charge_callback: meta.charge_callback,
+        callback_required: meta.callback_required,
+        quantity: core.quantity,
+        pending_quantity: core.pending_quantity,
+        discount: core.discount,
+        discount_periods_left: core.discount_periods_left,
+        loyalty: core.loyalty,
+    }
+}
+
+/// Fill the fields v1 did not have with what `create_subscription` would set.
+/// v1 knew a single token, and no creation time, so the last payment stands in.
+fn upgrade_v1(env: &Env, v1: SubscriptionV1) -> Result<Subscription, Error> {
+    Ok(Subscription {
+        subscriber: v1.subscriber,
+        merchant: v1.merchant,
+        amount: v1.amount,
+        interval_seconds: v1.interval_seconds,
+        last_payment_timestamp: v1.last_payment_timestamp,
+        status: v1.status,
+        prepaid_balance: v1.prepaid_balance,
+        usage_enabled: v1.usage_enabled,
+        total_refunded: 0,
+        token: crate::admin::get_token(env)?,
+        trial_until: 0,
+        usage_during_trial: false,
+        max_periods: 0,
+        periods_charged: 0,
+        cancel_at: 0,
+        created_at: v1.last_payment_timestamp,
+        status_changed_at: v1.last_payment_timestamp,
+        usage_rate: 0,
+        period_units_used: 0,
+        usage_cap_per_period: 0,
+        usage_charged_this_period: 0,
+        failed_charge_count: 0,
+        allow_partial_charge: false,
+        arrears: 0,
+        next_interval_seconds: 0,
+        plan_id: 0,
+        funding_mode: FundingMode::Prepaid,
+        metadata: None,
+        low_balance_threshold: v1.amount,
+        reason: 0,
+        usage_window_start: 0,
+        usage_window_count: 0,
+        last_usage_nonce: 0,
+        charge_callback: None,
+        callback_required: false,
+        quantity: 1,
+        pending_quantity: 0,
+        discount: CouponKind::Percent(0),
+        discount_periods_left: 0,
+        loyalty: Loyalty::default(),
+    })
+}
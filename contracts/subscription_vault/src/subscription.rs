@@ -2,13 +2,23 @@
 //!
 //! **PRs that only change subscription lifecycle or billing should edit this file only.**
 
-use crate::queries::get_subscription;
-use crate::state_machine::validate_status_transition;
-use crate::types::{DataKey, Error, Subscription, SubscriptionStatus};
-use soroban_sdk::{Address, Env, Symbol, Vec};
+use crate::queries::{compute_withdrawable, get_subscription};
+use crate::state_machine::{apply_transition, get_allowed_transitions, validate_status_transition};
+use crate::stats::{load_subscription, save_subscription, LoadedSubscription};
+use crate::stored_sub::Optional;
+use crate::types::{
+    AdminCancelledEvent, CouponKind, DataKey, Error, FundingMode, FundsDepositedEvent, Loyalty,
+    StatusChangedEvent, SubscriberWithdrawnEvent, Subscription, SubscriptionCancelledEvent,
+    SubscriptionCreatedEvent, SubscriptionOptions, SubscriptionPausedEvent,
+    SubscriptionPurgedEvent, SubscriptionResumedEvent, SubscriptionStatus,
+    SubscriptionTransferEvent, UsageTier, MAX_METADATA_LEN,
+};
+use soroban_sdk::{symbol_short, Address, Bytes, Env, Symbol, Vec};
 
-pub fn next_id(env: &Env) -> Result<u32, Error> {
-    let id: u32 = env.storage().instance().get(&DataKey::NextId).unwrap_or(0);
+/// Reserve the next subscription id. Fails with `Overflow` rather than
+/// wrapping onto id 0.
+pub fn next_id(env: &Env) -> Result<u64, Error> {
+    let id: u64 = env.storage().instance().get(&DataKey::NextId).unwrap_or(0);
     let next = id.checked_add(1).ok_or(Error::Overflow)?;
     env.storage().instance().set(&DataKey::NextId, &next);
     Ok(id)
@@ -21,160 +31,1289 @@ pub fn do_create_subscription(
     amount: i128,
     interval_seconds: u64,
     usage_enabled: bool,
-) -> Result<u32, Error> {
+    options: SubscriptionOptions,
+) -> Result<u64, Error> {
     subscriber.require_auth();
+    create_authorized(
+        env,
+        subscriber,
+        merchant,
+        amount,
+        interval_seconds,
+        usage_enabled,
+        options,
+    )
+}
+
+/// Creation body for callers that already hold `subscriber`'s authorization.
+pub fn create_authorized(
+    env: &Env,
+    subscriber: Address,
+    merchant: Address,
+    amount: i128,
+    interval_seconds: u64,
+    usage_enabled: bool,
+    options: SubscriptionOptions,
+) -> Result<u64, Error> {
+    create_funded_by(
+        env,
+        subscriber.clone(),
+        subscriber,
+        merchant,
+        amount,
+        interval_seconds,
+        usage_enabled,
+        options,
+    )
+}
+
+/// Create a subscription for `recipient`, paid for by `payer`. Only the payer
+/// signs; the recipient holds every subscriber right from the start.
+pub fn do_create_gift_subscription(
+    env: &Env,
+    payer: Address,
+    recipient: Address,
+    merchant: Address,
+    amount: i128,
+    interval_seconds: u64,
+    initial_deposit: i128,
+) -> Result<u64, Error> {
+    payer.require_auth();
+    create_funded_by(
+        env,
+        payer,
+        recipient,
+        merchant,
+        amount,
+        interval_seconds,
+        false,
+        SubscriptionOptions {
+            initial_deposit,
+            ..Default::default()
+        },
+    )
+}
+
+/// Creation body for callers that already hold `payer`'s authorization.
+/// `options.initial_deposit` is pulled from `payer`; a payer other than the
+/// subscriber is recorded under [`DataKey::GiftPayer`].
+#[allow(clippy::too_many_arguments)]
+fn create_funded_by(
+    env: &Env,
+    payer: Address,
+    subscriber: Address,
+    merchant: Address,
+    amount: i128,
+    interval_seconds: u64,
+    usage_enabled: bool,
+    options: SubscriptionOptions,
+) -> Result<u64, Error> {
+    let merchant = crate::merchant::resolve_merchant(env, merchant);
+    crate::admin::require_not_blocked(env, &payer)?;
+    crate::admin::require_not_blocked(env, &subscriber)?;
+    crate::admin::require_not_blocked(env, &merchant)?;
+    crate::merchant::require_merchant_accepting(env, &merchant)?;
+    let fiat_pricing = crate::oracle::from_options(&options);
+    let token = match options.token {
+        Some(token) => token,
+        None => crate::admin::get_token(env)?,
+    };
+    if !crate::admin::is_token_supported(env, &token) {
+        return Err(Error::TokenNotSupported);
+    }
+    let pair_key = DataKey::ActivePair(subscriber.clone(), merchant.clone());
+    if !options.allow_duplicates {
+        require_no_live_subscription(env, &pair_key)?;
+    }
+
+    validate_metadata(&options.metadata)?;
+    // Fiat-priced: the first quote becomes `amount` and is refreshed at each charge.
+    let amount = match &fiat_pricing {
+        Some(pricing) => crate::oracle::quote(env, &token, pricing)?,
+        None => amount,
+    };
+    crate::admin::require_amount_in_limits(env, amount)?;
+    crate::admin::require_interval_in_limits(env, interval_seconds)?;
+
+    // Up-front funding requirement; waived for trials and direct debit.
+    let now = env.ledger().timestamp();
+    if options.initial_deposit < 0 {
+        return Err(Error::InvalidAmount);
+    }
+    let direct_debit = options.funding_mode == FundingMode::DirectDebit;
+    if direct_debit && options.initial_deposit > 0 {
+        return Err(Error::InvalidFundingMode);
+    }
+    let quantity = options.quantity.max(1);
+    let period_amount = amount
+        .checked_mul(i128::from(quantity))
+        .ok_or(Error::Overflow)?;
+    let required_periods = crate::admin::get_require_initial_periods(env);
+    if required_periods > 0 && options.trial_until <= now && !direct_debit {
+        let required = period_amount
+            .checked_mul(i128::from(required_periods))
+            .ok_or(Error::Overflow)?;
+        if options.initial_deposit < required {
+            return Err(Error::InsufficientInitialDeposit);
+        }
+    }
+
+    let coupon = match &options.coupon {
+        Some(code) => Some(crate::coupon::redeem(env, &merchant, code, now)?),
+        None => None,
+    };
+
+    // Prepaid subscriptions without a trial wait in `Pending` until a deposit
+    // covers one period; the initial deposit below may activate them at once.
+    let status = if options.funding_mode == FundingMode::Prepaid && options.trial_until <= now {
+        SubscriptionStatus::Pending
+    } else {
+        SubscriptionStatus::Active
+    };
+
+    // With a trial, the billing clock starts when the trial ends rather than at creation.
+    let (last_payment_timestamp, first_period_seconds) = crate::anchor::schedule(
+        options.billing_anchor,
+        now.max(options.trial_until),
+        interval_seconds,
+    )?;
     let sub = Subscription {
         subscriber: subscriber.clone(),
         merchant: merchant.clone(),
         amount,
         interval_seconds,
-        last_payment_timestamp: env.ledger().timestamp(),
-        status: SubscriptionStatus::Active,
+        last_payment_timestamp,
+        status,
         prepaid_balance: 0i128,
         usage_enabled,
+        total_refunded: 0i128,
+        token,
+        trial_until: options.trial_until,
+        usage_during_trial: options.usage_during_trial,
+        max_periods: options.max_periods,
+        periods_charged: 0,
+        cancel_at: 0,
+        created_at: now,
+        status_changed_at: now,
+        usage_rate: options.usage_rate,
+        period_units_used: 0,
+        usage_cap_per_period: 0,
+        usage_charged_this_period: 0,
+        failed_charge_count: 0,
+        allow_partial_charge: options.allow_partial_charge,
+        arrears: 0,
+        next_interval_seconds: 0,
+        plan_id: 0,
+        funding_mode: options.funding_mode.clone(),
+        metadata: options.metadata.clone(),
+        low_balance_threshold: period_amount,
+        reason: 0,
+        usage_window_start: 0,
+        usage_window_count: 0,
+        last_usage_nonce: 0,
+        charge_callback: None,
+        callback_required: false,
+        quantity,
+        pending_quantity: 0,
+        discount: coupon
+            .as_ref()
+            .map_or(CouponKind::Percent(0), |c| c.kind.clone()),
+        discount_periods_left: coupon.as_ref().map_or(0, |c| c.duration_periods),
+        loyalty: Loyalty::default(),
     };
+    let mut sub = LoadedSubscription::new(sub);
     let id = next_id(env)?;
-    env.storage().instance().set(&DataKey::Sub(id), &sub);
+    save_subscription(env, id, &mut sub)?;
+    crate::lifetime_cap::init(env, id, options.lifetime_cap)?;
+    crate::referral::init(env, id, &subscriber, &merchant, options.referrer.clone())?;
+    if let Some(pricing) = &fiat_pricing {
+        crate::oracle::set(env, id, pricing);
+    }
+    crate::anchor::init(env, id, options.billing_anchor, first_period_seconds);
+    crate::paused_usage::init(env, id, options.usage_while_paused);
+    if payer != subscriber {
+        crate::stored_sub::set_optional(env, id, Optional::GiftPayer, &payer);
+    }
+    if !options.allow_duplicates {
+        env.storage().instance().set(&pair_key, &id);
+    }
 
     let key = DataKey::MerchantSubs(sub.merchant.clone());
-    let mut ids: Vec<u32> = env.storage().instance().get(&key).unwrap_or(Vec::new(env));
+    let mut ids: Vec<u64> = env.storage().instance().get(&key).unwrap_or(Vec::new(env));
     ids.push_back(id);
     env.storage().instance().set(&key, &ids);
 
+    env.events().publish(
+        (symbol_short!("sub_new"), id),
+        SubscriptionCreatedEvent {
+            subscription_id: id,
+            subscriber: subscriber.clone(),
+            payer: payer.clone(),
+            merchant,
+            amount,
+            interval_seconds,
+            metadata: options.metadata,
+        },
+    );
+    if let Some(code) = options.coupon {
+        env.events().publish((symbol_short!("redeemed"), id), code);
+    }
+
+    crate::trial_bond::post(env, id, &sub, &payer)?;
+    if options.initial_deposit > 0 && payer == subscriber {
+        deposit_authorized(env, id, subscriber, options.initial_deposit)?;
+    } else if options.initial_deposit > 0 {
+        deposit_on_behalf_authorized(env, id, payer, options.initial_deposit)?;
+    }
+
     Ok(id)
 }
 
+/// `DuplicateSubscription` if `key` maps to a subscription that is still live
+/// (`Pending`, `Active`, `Paused`, or `InsufficientBalance`).
+pub fn require_no_live_subscription(env: &Env, key: &DataKey) -> Result<(), Error> {
+    let Some(existing) = env.storage().instance().get::<_, u64>(key) else {
+        return Ok(());
+    };
+    match get_subscription(env, existing) {
+        Ok(sub) if !get_allowed_transitions(&sub.status).is_empty() => {
+            Err(Error::DuplicateSubscription)
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Free the duplicate-prevention slot held by a subscription that just ended.
+pub fn release_live_slot(env: &Env, subscription_id: u64, sub: &Subscription) {
+    let key = if sub.plan_id > 0 {
+        DataKey::ActivePlanSub(sub.subscriber.clone(), sub.plan_id)
+    } else {
+        DataKey::ActivePair(sub.subscriber.clone(), sub.merchant.clone())
+    };
+    if env.storage().instance().get::<_, u64>(&key) == Some(subscription_id) {
+        env.storage().instance().remove(&key);
+    }
+}
+
 pub fn do_deposit_funds(
     env: &Env,
-    subscription_id: u32,
+    subscription_id: u64,
     subscriber: Address,
     amount: i128,
-) -> Result<(), Error> {
+) -> Result<i128, Error> {
     subscriber.require_auth();
+    deposit_authorized(env, subscription_id, subscriber, amount)
+}
+
+/// Deposit body for callers that already hold `subscriber`'s authorization.
+pub fn deposit_authorized(
+    env: &Env,
+    subscription_id: u64,
+    subscriber: Address,
+    amount: i128,
+) -> Result<i128, Error> {
+    let mut sub = load_subscription(env, subscription_id)?;
+    crate::delegate::require_depositor(env, subscription_id, &sub, &subscriber)?;
+    credit_deposit(env, &mut sub, &subscriber, amount)?;
+    sub.failed_charge_count = 0;
+    activate_if_funded(env, subscription_id, &mut sub)?;
+    crate::expiry::record_deposit(env, subscription_id);
+
+    save_subscription(env, subscription_id, &mut sub)?;
+    collect_deposit(env, &sub, &subscriber, amount);
+    env.events().publish(
+        (Symbol::new(env, "deposited"), subscription_id),
+        FundsDepositedEvent {
+            subscription_id,
+            subscriber: sub.subscriber.clone(),
+            payer: subscriber,
+            amount,
+            prepaid_balance: sub.prepaid_balance,
+        },
+    );
+    Ok(sub.prepaid_balance)
+}
+
+/// Third-party top-up: `payer` funds the vault, only `prepaid_balance` changes.
+pub fn do_deposit_on_behalf(
+    env: &Env,
+    subscription_id: u64,
+    payer: Address,
+    amount: i128,
+) -> Result<i128, Error> {
+    payer.require_auth();
+    deposit_on_behalf_authorized(env, subscription_id, payer, amount)
+}
+
+/// Third-party deposit body for callers that already hold `payer`'s authorization.
+fn deposit_on_behalf_authorized(
+    env: &Env,
+    subscription_id: u64,
+    payer: Address,
+    amount: i128,
+) -> Result<i128, Error> {
+    let mut sub = load_subscription(env, subscription_id)?;
+    credit_deposit(env, &mut sub, &payer, amount)?;
+    activate_if_funded(env, subscription_id, &mut sub)?;
+    crate::expiry::record_deposit(env, subscription_id);
+
+    save_subscription(env, subscription_id, &mut sub)?;
+    collect_deposit(env, &sub, &payer, amount);
+    let prepaid_balance = sub.prepaid_balance;
+    env.events().publish(
+        (Symbol::new(env, "deposited_on_behalf"), subscription_id),
+        FundsDepositedEvent {
+            subscription_id,
+            subscriber: sub.subscriber.clone(),
+            payer,
+            amount,
+            prepaid_balance,
+        },
+    );
+    Ok(prepaid_balance)
+}
+
+/// Move a `Pending` subscription to `Active` once its prepaid balance covers
+/// one list-price period. The billing clock restarts at activation, so the
+/// first charge falls one full interval later. Emits `activated`.
+fn activate_if_funded(
+    env: &Env,
+    subscription_id: u64,
+    sub: &mut Subscription,
+) -> Result<(), Error> {
+    if sub.status != SubscriptionStatus::Pending
+        || sub.prepaid_balance < crate::queries::period_amount(sub)?
+    {
+        return Ok(());
+    }
+    let now = env.ledger().timestamp();
+    let previous_status = apply_transition(sub, SubscriptionStatus::Active, now)?;
+    crate::anchor::restart(env, subscription_id, sub, now)?;
+    env.events().publish(
+        (symbol_short!("activated"), subscription_id),
+        StatusChangedEvent {
+            subscription_id,
+            prepaid_balance: sub.prepaid_balance,
+            previous_status,
+            new_status: sub.status.clone(),
+            timestamp: now,
+        },
+    );
+    Ok(())
+}
 
-    let min_topup: i128 = crate::admin::get_min_topup(env)?;
+/// Enforce pause and the effective minimum top-up, then credit `amount` to
+/// `prepaid_balance` in memory. The caller saves `sub` before [`collect_deposit`].
+fn credit_deposit(
+    env: &Env,
+    sub: &mut Subscription,
+    payer: &Address,
+    amount: i128,
+) -> Result<(), Error> {
+    crate::admin::require_not_paused(env)?;
+    crate::admin::require_not_blocked(env, payer)?;
+    crate::admin::require_not_blocked(env, &sub.subscriber)?;
+    if sub.funding_mode == FundingMode::DirectDebit {
+        return Err(Error::InvalidFundingMode);
+    }
+    let min_topup = crate::merchant::effective_min_topup(env, sub)?;
     if amount < min_topup {
         return Err(Error::BelowMinimumTopup);
     }
 
-    let mut sub = get_subscription(env, subscription_id)?;
     sub.prepaid_balance = sub
         .prepaid_balance
         .checked_add(amount)
         .ok_or(Error::Overflow)?;
+    Ok(())
+}
 
-    let token_addr: Address = env
+/// Move a credited deposit from `payer` into the vault. Runs after the
+/// subscription is saved (checks-effects-interactions).
+fn collect_deposit(env: &Env, sub: &Subscription, payer: &Address, amount: i128) {
+    let token_client = soroban_sdk::token::Client::new(env, &sub.token);
+    token_client.transfer(payer, &env.current_contract_address(), &amount);
+}
+
+pub fn do_cancel_subscription(
+    env: &Env,
+    subscription_id: u64,
+    authorizer: Address,
+    reason: u32,
+) -> Result<(), Error> {
+    authorizer.require_auth();
+    cancel_authorized(env, subscription_id, authorizer, reason)
+}
+
+/// Cancellation body for callers that already hold `authorizer`'s authorization.
+pub fn cancel_authorized(
+    env: &Env,
+    subscription_id: u64,
+    authorizer: Address,
+    reason: u32,
+) -> Result<(), Error> {
+    let mut sub = load_subscription(env, subscription_id)?;
+
+    if authorizer != sub.subscriber && authorizer != sub.merchant {
+        return Err(Error::Unauthorized);
+    }
+
+    let now = env.ledger().timestamp();
+    let previous_status = apply_transition(&mut sub, SubscriptionStatus::Cancelled, now)?;
+    sub.reason = reason;
+
+    save_subscription(env, subscription_id, &mut sub)?;
+    release_live_slot(env, subscription_id, &sub);
+    crate::trial_bond::settle(env, subscription_id, &sub)?;
+    env.events().publish(
+        (symbol_short!("cancelled"), subscription_id),
+        SubscriptionCancelledEvent {
+            subscription_id,
+            authorizer,
+            reason,
+            refund_amount: sub.prepaid_balance,
+            previous_status,
+            new_status: sub.status.clone(),
+            timestamp: now,
+        },
+    );
+    Ok(())
+}
+
+/// Delete a finished subscription's storage. Anyone may call it once the
+/// subscription is terminal with nothing left in it: no prepaid balance, no
+/// arrears, and its last charge past the dispute window. Emits `purged`
+/// carrying the final record and lifetime stats.
+pub fn do_purge_subscription(env: &Env, subscription_id: u64) -> Result<(), Error> {
+    let sub = get_subscription(env, subscription_id)?;
+    if !get_allowed_transitions(&sub.status).is_empty()
+        || sub.prepaid_balance != 0
+        || sub.arrears != 0
+        || crate::dispute::charge_disputable(env, subscription_id, sub.periods_charged)
+    {
+        return Err(Error::NotPurgeable);
+    }
+
+    let stats = crate::queries::get_subscription_stats(env, subscription_id)?;
+    release_live_slot(env, subscription_id, &sub);
+    let index_key = DataKey::MerchantSubs(sub.merchant.clone());
+    let mut ids: Vec<u64> = env
         .storage()
         .instance()
-        .get(&DataKey::Token)
-        .ok_or(Error::NotFound)?;
-    let token_client = soroban_sdk::token::Client::new(env, &token_addr);
+        .get(&index_key)
+        .unwrap_or(Vec::new(env));
+    if let Some(pos) = ids.first_index_of(subscription_id) {
+        ids.remove(pos);
+        env.storage().instance().set(&index_key, &ids);
+    }
+    crate::stored_sub::remove_entry(env, &DataKey::ChargedPeriod(subscription_id));
+    crate::stored_sub::remove_entry(env, &DataKey::SubStats(subscription_id));
+    for key in [
+        DataKey::IdemKey(subscription_id),
+        DataKey::PendingAmount(subscription_id),
+        DataKey::ProposedInterval(subscription_id),
+        DataKey::PendingTransfer(subscription_id),
+    ] {
+        crate::stored_sub::remove_entry(env, &key);
+    }
+    crate::stored_sub::remove_optional(env, subscription_id, Optional::UsageTiers);
+    crate::stored_sub::remove_optional(env, subscription_id, Optional::GiftPayer);
+    crate::history::remove(env, subscription_id);
+    crate::renewal::remove(env, subscription_id);
+    crate::expiry::remove(env, subscription_id);
+    crate::lifetime_cap::remove(env, subscription_id);
+    crate::oracle::remove(env, subscription_id);
+    crate::anchor::remove(env, subscription_id);
+    crate::paused_usage::remove(env, subscription_id);
+    crate::usage_toggle::remove(env, subscription_id);
+    crate::delegate::remove(env, subscription_id);
+    crate::notify::remove(env, subscription_id);
+    crate::auto_topup::remove(env, subscription_id);
+    crate::referral::remove(env, subscription_id);
+    crate::stats::remove_subscription(env, subscription_id, &sub);
 
-    token_client.transfer(&subscriber, &env.current_contract_address(), &amount);
+    env.events().publish(
+        (symbol_short!("purged"), subscription_id),
+        SubscriptionPurgedEvent {
+            subscription_id,
+            subscription: sub,
+            stats,
+            timestamp: env.ledger().timestamp(),
+        },
+    );
+    Ok(())
+}
 
-    env.storage()
-        .instance()
-        .set(&DataKey::Sub(subscription_id), &sub);
+/// Admin terminates a subscription (compliance, abuse). The reason code is
+/// kept on the subscription; `prepaid_balance` is untouched and only the
+/// subscriber can withdraw it.
+pub fn do_admin_cancel_subscription(
+    env: &Env,
+    admin: Address,
+    subscription_id: u64,
+    reason_code: u32,
+) -> Result<(), Error> {
+    admin.require_auth();
+    let stored = crate::admin::require_admin(env)?;
+    if admin != stored {
+        return Err(Error::Unauthorized);
+    }
+
+    let mut sub = load_subscription(env, subscription_id)?;
+    if get_allowed_transitions(&sub.status).is_empty() {
+        return Err(Error::InvalidStatusTransition);
+    }
+    let now = env.ledger().timestamp();
+    let previous_status = apply_transition(&mut sub, SubscriptionStatus::Cancelled, now)?;
+    sub.reason = reason_code;
+
+    save_subscription(env, subscription_id, &mut sub)?;
+    release_live_slot(env, subscription_id, &sub);
+    crate::trial_bond::settle(env, subscription_id, &sub)?;
     env.events().publish(
-        (Symbol::new(env, "deposited"), subscription_id),
-        (subscriber, amount, sub.prepaid_balance),
+        (Symbol::new(env, "admin_cancelled"), subscription_id),
+        AdminCancelledEvent {
+            subscription_id,
+            admin,
+            reason_code,
+            prepaid_balance: sub.prepaid_balance,
+            previous_status,
+            new_status: sub.status.clone(),
+            timestamp: now,
+        },
     );
     Ok(())
 }
 
-pub fn do_cancel_subscription(
+/// Schedule cancellation for the end of the current paid-for period.
+///
+/// Sets `cancel_at` to the next charge timestamp. The subscription keeps its
+/// status until then; the first charge attempt at or after `cancel_at`
+/// cancels it instead of charging, even while paused.
+pub fn do_schedule_cancellation(
     env: &Env,
-    subscription_id: u32,
-    authorizer: Address,
+    subscription_id: u64,
+    subscriber: Address,
+) -> Result<u64, Error> {
+    subscriber.require_auth();
+
+    let mut sub = load_subscription(env, subscription_id)?;
+    if subscriber != sub.subscriber {
+        return Err(Error::Unauthorized);
+    }
+    if sub.status == SubscriptionStatus::Cancelled {
+        return Err(Error::InvalidStatusTransition);
+    }
+    validate_status_transition(&sub.status, &SubscriptionStatus::Cancelled)?;
+
+    sub.cancel_at = sub
+        .last_payment_timestamp
+        .checked_add(sub.interval_seconds)
+        .ok_or(Error::Overflow)?;
+    save_subscription(env, subscription_id, &mut sub)?;
+
+    env.events().publish(
+        (Symbol::new(env, "cancel_scheduled"), subscription_id),
+        sub.cancel_at,
+    );
+    Ok(sub.cancel_at)
+}
+
+/// Clear a pending scheduled cancellation. Fails with `NotFound` if none is pending.
+pub fn do_undo_cancellation(
+    env: &Env,
+    subscription_id: u64,
+    subscriber: Address,
 ) -> Result<(), Error> {
-    authorizer.require_auth();
+    subscriber.require_auth();
 
-    let mut sub = get_subscription(env, subscription_id)?;
+    let mut sub = load_subscription(env, subscription_id)?;
+    if subscriber != sub.subscriber {
+        return Err(Error::Unauthorized);
+    }
+    if sub.cancel_at == 0 || sub.status == SubscriptionStatus::Cancelled {
+        return Err(Error::NotFound);
+    }
 
-    if authorizer != sub.subscriber && authorizer != sub.merchant {
+    sub.cancel_at = 0;
+    save_subscription(env, subscription_id, &mut sub)?;
+
+    env.events()
+        .publish((Symbol::new(env, "cancel_undone"), subscription_id), ());
+    Ok(())
+}
+
+/// Merchant proposes a new per-interval `amount`; the subscriber must accept it.
+/// Replaces any earlier pending proposal.
+pub fn do_propose_amount_change(
+    env: &Env,
+    subscription_id: u64,
+    merchant: Address,
+    new_amount: i128,
+) -> Result<(), Error> {
+    merchant.require_auth();
+
+    let sub = get_subscription(env, subscription_id)?;
+    if merchant != sub.merchant {
         return Err(Error::Unauthorized);
     }
+    if get_allowed_transitions(&sub.status).is_empty() {
+        return Err(Error::InvalidStatusTransition);
+    }
+    crate::admin::require_amount_in_limits(env, new_amount)?;
 
-    validate_status_transition(&sub.status, &SubscriptionStatus::Cancelled)?;
-    sub.status = SubscriptionStatus::Cancelled;
+    crate::stored_sub::set_entry(env, &DataKey::PendingAmount(subscription_id), &new_amount);
+    env.events().publish(
+        (Symbol::new(env, "amount_proposed"), subscription_id),
+        (sub.amount, new_amount),
+    );
+    Ok(())
+}
 
-    env.storage()
-        .instance()
-        .set(&DataKey::Sub(subscription_id), &sub);
+/// Subscriber accepts the pending amount. Applies from the next charge on.
+pub fn do_accept_amount_change(
+    env: &Env,
+    subscription_id: u64,
+    subscriber: Address,
+) -> Result<(), Error> {
+    subscriber.require_auth();
+
+    let mut sub = load_subscription(env, subscription_id)?;
+    if subscriber != sub.subscriber {
+        return Err(Error::Unauthorized);
+    }
+    if get_allowed_transitions(&sub.status).is_empty() {
+        return Err(Error::InvalidStatusTransition);
+    }
+    let key = DataKey::PendingAmount(subscription_id);
+    let new_amount: i128 = crate::stored_sub::get_entry(env, &key).ok_or(Error::NotFound)?;
+    // Limits may have tightened since the proposal.
+    crate::admin::require_amount_in_limits(env, new_amount)?;
+
+    let old_amount = sub.amount;
+    sub.amount = new_amount;
+    save_subscription(env, subscription_id, &mut sub)?;
+    crate::stored_sub::remove_entry(env, &key);
+    env.events().publish(
+        (Symbol::new(env, "amount_accepted"), subscription_id),
+        (old_amount, new_amount),
+    );
     Ok(())
 }
 
+/// Drop the pending amount: the subscriber rejects it or the merchant withdraws it.
+pub fn do_clear_amount_change(
+    env: &Env,
+    subscription_id: u64,
+    caller: Address,
+    rejected: bool,
+) -> Result<(), Error> {
+    caller.require_auth();
+
+    let sub = get_subscription(env, subscription_id)?;
+    let expected = if rejected {
+        &sub.subscriber
+    } else {
+        &sub.merchant
+    };
+    if caller != *expected {
+        return Err(Error::Unauthorized);
+    }
+    let key = DataKey::PendingAmount(subscription_id);
+    let new_amount: i128 = crate::stored_sub::get_entry(env, &key).ok_or(Error::NotFound)?;
+
+    crate::stored_sub::remove_entry(env, &key);
+    let topic = if rejected {
+        "amount_rejected"
+    } else {
+        "amount_withdrawn"
+    };
+    env.events()
+        .publish((Symbol::new(env, topic), subscription_id), new_amount);
+    Ok(())
+}
+
+/// Current subscriber proposes moving the subscription to `new_subscriber`
+/// (e.g. a rotated wallet). Replaces any earlier pending transfer.
+pub fn do_propose_transfer(
+    env: &Env,
+    subscription_id: u64,
+    current_subscriber: Address,
+    new_subscriber: Address,
+) -> Result<(), Error> {
+    current_subscriber.require_auth();
+
+    let sub = get_subscription(env, subscription_id)?;
+    if current_subscriber != sub.subscriber {
+        return Err(Error::Unauthorized);
+    }
+    if get_allowed_transitions(&sub.status).is_empty() {
+        return Err(Error::InvalidStatusTransition);
+    }
+
+    crate::stored_sub::set_entry(
+        env,
+        &DataKey::PendingTransfer(subscription_id),
+        &new_subscriber,
+    );
+    let from = sub.subscriber.clone();
+    publish_transfer(
+        env,
+        "transfer_proposed",
+        subscription_id,
+        &sub,
+        from,
+        new_subscriber,
+    );
+    Ok(())
+}
+
+/// Proposed subscriber accepts, taking over the subscription and its balance.
+pub fn do_accept_transfer(
+    env: &Env,
+    subscription_id: u64,
+    new_subscriber: Address,
+) -> Result<(), Error> {
+    new_subscriber.require_auth();
+
+    let mut sub = load_subscription(env, subscription_id)?;
+    let key = DataKey::PendingTransfer(subscription_id);
+    let pending: Address = crate::stored_sub::get_entry(env, &key).ok_or(Error::NotFound)?;
+    if new_subscriber != pending {
+        return Err(Error::Unauthorized);
+    }
+    if get_allowed_transitions(&sub.status).is_empty() {
+        return Err(Error::InvalidStatusTransition);
+    }
+
+    // Move the duplicate-prevention slot, if this subscription holds one.
+    let (old_slot, new_slot) = if sub.plan_id > 0 {
+        (
+            DataKey::ActivePlanSub(sub.subscriber.clone(), sub.plan_id),
+            DataKey::ActivePlanSub(new_subscriber.clone(), sub.plan_id),
+        )
+    } else {
+        (
+            DataKey::ActivePair(sub.subscriber.clone(), sub.merchant.clone()),
+            DataKey::ActivePair(new_subscriber.clone(), sub.merchant.clone()),
+        )
+    };
+    if env.storage().instance().get::<_, u64>(&old_slot) == Some(subscription_id) {
+        require_no_live_subscription(env, &new_slot)?;
+        env.storage().instance().remove(&old_slot);
+        env.storage().instance().set(&new_slot, &subscription_id);
+    }
+
+    let from = core::mem::replace(&mut sub.subscriber, new_subscriber.clone());
+    save_subscription(env, subscription_id, &mut sub)?;
+    crate::stored_sub::remove_entry(env, &key);
+    // The delegate agreed to fund the old subscriber, not whoever comes next,
+    // and the notification address is the old subscriber's to watch.
+    crate::delegate::remove(env, subscription_id);
+    crate::notify::remove(env, subscription_id);
+    publish_transfer(
+        env,
+        "transferred",
+        subscription_id,
+        &sub,
+        from,
+        new_subscriber,
+    );
+    Ok(())
+}
+
+/// Current subscriber withdraws a pending transfer.
+pub fn do_cancel_transfer(
+    env: &Env,
+    subscription_id: u64,
+    subscriber: Address,
+) -> Result<(), Error> {
+    subscriber.require_auth();
+
+    let sub = get_subscription(env, subscription_id)?;
+    if subscriber != sub.subscriber {
+        return Err(Error::Unauthorized);
+    }
+    let key = DataKey::PendingTransfer(subscription_id);
+    let pending: Address = crate::stored_sub::get_entry(env, &key).ok_or(Error::NotFound)?;
+
+    crate::stored_sub::remove_entry(env, &key);
+    let from = sub.subscriber.clone();
+    publish_transfer(
+        env,
+        "transfer_cancelled",
+        subscription_id,
+        &sub,
+        from,
+        pending,
+    );
+    Ok(())
+}
+
+fn publish_transfer(
+    env: &Env,
+    topic: &str,
+    subscription_id: u64,
+    sub: &Subscription,
+    from: Address,
+    to: Address,
+) {
+    env.events().publish(
+        (Symbol::new(env, topic), subscription_id),
+        SubscriptionTransferEvent {
+            subscription_id,
+            merchant: sub.merchant.clone(),
+            from,
+            to,
+            prepaid_balance: sub.prepaid_balance,
+        },
+    );
+}
+
+/// Subscriber requests a new billing interval. With `merchant_signed` the
+/// merchant co-signs and the change is scheduled at once; otherwise it waits
+/// for `do_acknowledge_interval_change`. Either way it applies only after the
+/// next successful charge, so the paid-for period is never shortened.
+pub fn do_update_interval(
+    env: &Env,
+    subscription_id: u64,
+    subscriber: Address,
+    merchant_signed: bool,
+    new_interval: u64,
+) -> Result<(), Error> {
+    subscriber.require_auth();
+
+    let mut sub = load_subscription(env, subscription_id)?;
+    if subscriber != sub.subscriber {
+        return Err(Error::Unauthorized);
+    }
+    if get_allowed_transitions(&sub.status).is_empty() {
+        return Err(Error::InvalidStatusTransition);
+    }
+    crate::admin::require_interval_in_limits(env, new_interval)?;
+
+    let proposed = DataKey::ProposedInterval(subscription_id);
+    if !merchant_signed {
+        crate::stored_sub::set_entry(env, &proposed, &new_interval);
+        env.events().publish(
+            (Symbol::new(env, "interval_proposed"), subscription_id),
+            (sub.interval_seconds, new_interval),
+        );
+        return Ok(());
+    }
+
+    sub.merchant.require_auth();
+    crate::stored_sub::remove_entry(env, &proposed);
+    schedule_interval(env, subscription_id, &mut sub, new_interval)
+}
+
+/// Merchant acknowledges a pending interval proposal, scheduling it.
+pub fn do_acknowledge_interval_change(
+    env: &Env,
+    subscription_id: u64,
+    merchant: Address,
+) -> Result<(), Error> {
+    merchant.require_auth();
+
+    let mut sub = load_subscription(env, subscription_id)?;
+    if merchant != sub.merchant {
+        return Err(Error::Unauthorized);
+    }
+    if get_allowed_transitions(&sub.status).is_empty() {
+        return Err(Error::InvalidStatusTransition);
+    }
+    let proposed = DataKey::ProposedInterval(subscription_id);
+    let new_interval: u64 = crate::stored_sub::get_entry(env, &proposed).ok_or(Error::NotFound)?;
+    crate::admin::require_interval_in_limits(env, new_interval)?;
+
+    crate::stored_sub::remove_entry(env, &proposed);
+    schedule_interval(env, subscription_id, &mut sub, new_interval)
+}
+
+fn schedule_interval(
+    env: &Env,
+    subscription_id: u64,
+    sub: &mut LoadedSubscription,
+    new_interval: u64,
+) -> Result<(), Error> {
+    sub.next_interval_seconds = new_interval;
+    save_subscription(env, subscription_id, sub)?;
+    env.events().publish(
+        (Symbol::new(env, "interval_scheduled"), subscription_id),
+        (sub.interval_seconds, new_interval),
+    );
+    Ok(())
+}
+
+/// Set the per-period usage spending cap (0 = unlimited). Subscriber only;
+/// takes effect immediately, including for usage already charged this period.
+pub fn do_set_usage_cap(
+    env: &Env,
+    subscription_id: u64,
+    subscriber: Address,
+    cap: i128,
+) -> Result<(), Error> {
+    subscriber.require_auth();
+
+    let mut sub = load_subscription(env, subscription_id)?;
+    if subscriber != sub.subscriber {
+        return Err(Error::Unauthorized);
+    }
+    if cap < 0 {
+        return Err(Error::InvalidAmount);
+    }
+
+    sub.usage_cap_per_period = cap;
+    save_subscription(env, subscription_id, &mut sub)?;
+    env.events()
+        .publish((symbol_short!("usage_cap"), subscription_id), cap);
+    Ok(())
+}
+
+/// Set the `low_bal` warning threshold (0 disables it). Subscriber only.
+pub fn do_set_low_balance_threshold(
+    env: &Env,
+    subscription_id: u64,
+    subscriber: Address,
+    threshold: i128,
+) -> Result<(), Error> {
+    subscriber.require_auth();
+
+    let mut sub = load_subscription(env, subscription_id)?;
+    if subscriber != sub.subscriber {
+        return Err(Error::Unauthorized);
+    }
+    if threshold < 0 {
+        return Err(Error::InvalidAmount);
+    }
+
+    sub.low_balance_threshold = threshold;
+    save_subscription(env, subscription_id, &mut sub)?;
+    env.events().publish(
+        (Symbol::new(env, "low_bal_threshold"), subscription_id),
+        threshold,
+    );
+    Ok(())
+}
+
+/// Replace a subscription's usage tiers. Both parties must sign since this
+/// changes what the subscriber pays. An empty list reverts to `usage_rate`.
+pub fn do_set_usage_tiers(
+    env: &Env,
+    subscription_id: u64,
+    tiers: Vec<UsageTier>,
+) -> Result<(), Error> {
+    let sub = get_subscription(env, subscription_id)?;
+    sub.subscriber.require_auth();
+    sub.merchant.require_auth();
+
+    let mut prev_up_to = 0u64;
+    for tier in tiers.iter() {
+        if tier.up_to <= prev_up_to || tier.rate <= 0 {
+            return Err(Error::InvalidUsageTiers);
+        }
+        prev_up_to = tier.up_to;
+    }
+
+    if tiers.is_empty() {
+        crate::stored_sub::remove_optional(env, subscription_id, Optional::UsageTiers);
+    } else {
+        crate::stored_sub::set_optional(env, subscription_id, Optional::UsageTiers, &tiers);
+    }
+    env.events().publish(
+        (Symbol::new(env, "usage_tiers_set"), subscription_id),
+        tiers.len(),
+    );
+    Ok(())
+}
+
+/// Replace a subscription's external reference (`None` clears it). Both
+/// parties must sign, since either side may rely on it for reconciliation.
+pub fn do_set_metadata(
+    env: &Env,
+    subscription_id: u64,
+    metadata: Option<Bytes>,
+) -> Result<(), Error> {
+    let mut sub = load_subscription(env, subscription_id)?;
+    sub.subscriber.require_auth();
+    sub.merchant.require_auth();
+    validate_metadata(&metadata)?;
+
+    sub.metadata = metadata.clone();
+    save_subscription(env, subscription_id, &mut sub)?;
+    env.events().publish(
+        (Symbol::new(env, "metadata_set"), subscription_id),
+        metadata,
+    );
+    Ok(())
+}
+
+/// Set or clear the contract notified after each successful interval charge.
+/// Merchant only.
+pub fn do_set_charge_callback(
+    env: &Env,
+    subscription_id: u64,
+    merchant: Address,
+    callback: Option<Address>,
+    required: bool,
+) -> Result<(), Error> {
+    merchant.require_auth();
+
+    let mut sub = load_subscription(env, subscription_id)?;
+    if merchant != sub.merchant {
+        return Err(Error::Unauthorized);
+    }
+
+    sub.callback_required = required && callback.is_some();
+    sub.charge_callback = callback;
+    save_subscription(env, subscription_id, &mut sub)?;
+    env.events().publish(
+        (symbol_short!("callback"), subscription_id),
+        (sub.charge_callback.clone(), sub.callback_required),
+    );
+    Ok(())
+}
+
+/// Change the number of seats billed per interval. Subscriber only.
+///
+/// The new quantity is stored as `pending_quantity` and billed from the next
+/// interval charge on; the current period is not prorated.
+pub fn do_update_quantity(
+    env: &Env,
+    subscription_id: u64,
+    subscriber: Address,
+    new_quantity: u32,
+) -> Result<(), Error> {
+    subscriber.require_auth();
+
+    let mut sub = load_subscription(env, subscription_id)?;
+    if subscriber != sub.subscriber {
+        return Err(Error::Unauthorized);
+    }
+    stage_quantity(env, subscription_id, &mut sub, new_quantity)
+}
+
+/// Store `new_quantity` as pending (clearing it when it equals the current
+/// quantity), save, and emit `quantity`.
+pub(crate) fn stage_quantity(
+    env: &Env,
+    subscription_id: u64,
+    sub: &mut LoadedSubscription,
+    new_quantity: u32,
+) -> Result<(), Error> {
+    if new_quantity == 0 {
+        return Err(Error::InvalidAmount);
+    }
+    // Reject a quantity the next charge could not bill.
+    sub.amount
+        .checked_mul(i128::from(new_quantity))
+        .ok_or(Error::Overflow)?;
+
+    sub.pending_quantity = if new_quantity == sub.quantity {
+        0
+    } else {
+        new_quantity
+    };
+    save_subscription(env, subscription_id, sub)?;
+    env.events().publish(
+        (symbol_short!("quantity"), subscription_id),
+        (sub.quantity, new_quantity),
+    );
+    Ok(())
+}
+
+/// Set the subscription's loyalty terms. Merchant only.
+///
+/// Once `loyalty.streak` reaches `after_periods`, interval charges are
+/// discounted by `discount_bps`. Either value 0 switches loyalty off. The
+/// current streak is kept.
+pub fn do_set_loyalty_terms(
+    env: &Env,
+    subscription_id: u64,
+    merchant: Address,
+    after_periods: u32,
+    discount_bps: u32,
+) -> Result<(), Error> {
+    merchant.require_auth();
+
+    let mut sub = load_subscription(env, subscription_id)?;
+    if merchant != sub.merchant {
+        return Err(Error::Unauthorized);
+    }
+    if discount_bps > crate::math::MAX_BPS {
+        return Err(Error::InvalidAmount);
+    }
+
+    sub.loyalty.after_periods = after_periods;
+    sub.loyalty.discount_bps = discount_bps;
+    save_subscription(env, subscription_id, &mut sub)?;
+    env.events().publish(
+        (symbol_short!("loyalty"), subscription_id),
+        (after_periods, discount_bps),
+    );
+    Ok(())
+}
+
+pub(crate) fn validate_metadata(metadata: &Option<Bytes>) -> Result<(), Error> {
+    match metadata {
+        Some(bytes) if bytes.len() > MAX_METADATA_LEN => Err(Error::MetadataTooLarge),
+        _ => Ok(()),
+    }
+}
+
 pub fn do_pause_subscription(
     env: &Env,
-    subscription_id: u32,
+    subscription_id: u64,
     authorizer: Address,
+    reason: u32,
 ) -> Result<(), Error> {
     authorizer.require_auth();
 
-    let mut sub = get_subscription(env, subscription_id)?;
-    validate_status_transition(&sub.status, &SubscriptionStatus::Paused)?;
-    sub.status = SubscriptionStatus::Paused;
+    let mut sub = load_subscription(env, subscription_id)?;
+    let now = env.ledger().timestamp();
+    let previous_status = apply_transition(&mut sub, SubscriptionStatus::Paused, now)?;
+    sub.reason = reason;
 
-    env.storage()
-        .instance()
-        .set(&DataKey::Sub(subscription_id), &sub);
+    save_subscription(env, subscription_id, &mut sub)?;
+    env.events().publish(
+        (symbol_short!("paused"), subscription_id),
+        SubscriptionPausedEvent {
+            subscription_id,
+            authorizer,
+            reason,
+            prepaid_balance: sub.prepaid_balance,
+            previous_status,
+            new_status: sub.status.clone(),
+            timestamp: now,
+        },
+    );
     Ok(())
 }
 
 pub fn do_resume_subscription(
     env: &Env,
-    subscription_id: u32,
+    subscription_id: u64,
     authorizer: Address,
 ) -> Result<(), Error> {
     authorizer.require_auth();
 
-    let mut sub = get_subscription(env, subscription_id)?;
-    validate_status_transition(&sub.status, &SubscriptionStatus::Active)?;
-    sub.status = SubscriptionStatus::Active;
+    let mut sub = load_subscription(env, subscription_id)?;
+    if sub.arrears > 0 {
+        return Err(Error::ArrearsOutstanding);
+    }
+    let now = env.ledger().timestamp();
+    let previous_status = apply_transition(&mut sub, SubscriptionStatus::Active, now)?;
 
-    env.storage()
-        .instance()
-        .set(&DataKey::Sub(subscription_id), &sub);
+    save_subscription(env, subscription_id, &mut sub)?;
+    env.events().publish(
+        (symbol_short!("resumed"), subscription_id),
+        SubscriptionResumedEvent {
+            subscription_id,
+            authorizer,
+            prepaid_balance: sub.prepaid_balance,
+            previous_status,
+            new_status: sub.status.clone(),
+            timestamp: now,
+        },
+    );
     Ok(())
 }
 
 pub fn do_withdraw_subscriber_funds(
     env: &Env,
-    subscription_id: u32,
+    subscription_id: u64,
     subscriber: Address,
-) -> Result<(), Error> {
+) -> Result<i128, Error> {
     subscriber.require_auth();
+    withdraw_subscriber_authorized(env, subscription_id, subscriber)
+}
 
-    let mut sub = get_subscription(env, subscription_id)?;
+/// Full-refund body for callers that already hold `subscriber`'s authorization.
+pub fn withdraw_subscriber_authorized(
+    env: &Env,
+    subscription_id: u64,
+    subscriber: Address,
+) -> Result<i128, Error> {
+    let mut sub = load_subscription(env, subscription_id)?;
 
     if subscriber != sub.subscriber {
         return Err(Error::Unauthorized);
     }
 
-    if sub.status != SubscriptionStatus::Cancelled {
+    if !matches!(
+        sub.status,
+        SubscriptionStatus::Cancelled | SubscriptionStatus::Completed | SubscriptionStatus::Expired
+    ) {
         return Err(Error::InvalidStatusTransition);
     }
 
     let amount_to_refund = sub.prepaid_balance;
     if amount_to_refund > 0 {
         sub.prepaid_balance = 0;
-        env.storage()
-            .instance()
-            .set(&DataKey::Sub(subscription_id), &sub);
+        save_subscription(env, subscription_id, &mut sub)?;
 
-        let token_addr: Address = env
-            .storage()
-            .instance()
-            .get(&DataKey::Token)
-            .ok_or(Error::NotFound)?;
-        let token_client = soroban_sdk::token::Client::new(env, &token_addr);
+        let token_client = soroban_sdk::token::Client::new(env, &sub.token);
 
         token_client.transfer(
             &env.current_contract_address(),
             &subscriber,
             &amount_to_refund,
         );
+        env.events().publish(
+            (Symbol::new(env, "subscriber_withdrawn"), subscription_id),
+            SubscriberWithdrawnEvent {
+                subscription_id,
+                subscriber,
+                amount: amount_to_refund,
+                prepaid_balance: 0,
+            },
+        );
+    }
+
+    Ok(sub.prepaid_balance)
+}
+
+/// Withdraw part of the prepaid balance above the status-dependent reserve.
+/// See [`crate::queries::withdrawal_reserve`].
+pub fn do_withdraw_subscriber_excess(
+    env: &Env,
+    subscription_id: u64,
+    subscriber: Address,
+    amount: i128,
+) -> Result<(), Error> {
+    subscriber.require_auth();
+
+    let mut sub = load_subscription(env, subscription_id)?;
+    if subscriber != sub.subscriber {
+        return Err(Error::Unauthorized);
+    }
+    if amount <= 0 {
+        return Err(Error::InvalidAmount);
+    }
+    if amount > sub.prepaid_balance {
+        return Err(Error::InsufficientBalance);
+    }
+    if amount > compute_withdrawable(&sub) {
+        return Err(Error::ReserveRequired);
     }
 
+    sub.prepaid_balance = sub
+        .prepaid_balance
+        .checked_sub(amount)
+        .ok_or(Error::Overflow)?;
+    save_subscription(env, subscription_id, &mut sub)?;
+
+    let token_client = soroban_sdk::token::Client::new(env, &sub.token);
+    token_client.transfer(&env.current_contract_address(), &subscriber, &amount);
+
+    env.events().publish(
+        (Symbol::new(env, "subscriber_withdrawn"), subscription_id),
+        SubscriberWithdrawnEvent {
+            subscription_id,
+            subscriber,
+            amount,
+            prepaid_balance: sub.prepaid_balance,
+        },
+    );
     Ok(())
 }
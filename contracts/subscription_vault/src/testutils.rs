@@ -0,0 +1,116 @@
+//! Test fixtures: a vault with a real Stellar Asset Contract token, funded subscribers and clock helpers.
+//!
+//! Built for this crate's own tests and, with the `testutils` feature, for integrators' test suites.
+//!
+//! **PRs that only change the shared test fixture should edit this file only.**
+
+use crate::{SubscriptionVault, SubscriptionVaultClient};
+use soroban_sdk::testutils::{Address as _, Ledger as _};
+use soroban_sdk::token::{Client as TokenClient, StellarAssetClient};
+use soroban_sdk::{Address, Env, Vec};
+
+/// Minimum top-up passed to `init` by [`VaultTest::new`] (1 USDC at 6 decimals).
+pub const DEFAULT_MIN_TOPUP: i128 = 1_000000;
+
+/// A registered and initialized vault, its token, and a set of funded subscribers.
+///
+/// All auths are mocked, so any address can act as subscriber, merchant or admin.
+pub struct VaultTest {
+    pub env: Env,
+    pub client: SubscriptionVaultClient<'static>,
+    pub admin: Address,
+    /// Stellar Asset Contract the vault was initialized with; `admin` is its issuer.
+    pub token: Address,
+    /// Subscribers generated by [`VaultTest::new`], each holding the requested balance.
+    pub subscribers: Vec<Address>,
+}
+
+impl VaultTest {
+    /// Register the vault and a token, initialize with [`DEFAULT_MIN_TOPUP`], and mint
+    /// `balance` to each of `subscribers` new addresses.
+    pub fn new(subscribers: u32, balance: i128) -> Self {
+        let env = Env::default();
+        env.mock_all_auths();
+        let contract_id = env.register(SubscriptionVault, ());
+        let client = SubscriptionVaultClient::new(&env, &contract_id);
+
+        let admin = Address::generate(&env);
+        let token = env
+            .register_stellar_asset_contract_v2(admin.clone())
+            .address();
+        client.init(&token, &admin, &DEFAULT_MIN_TOPUP);
+
+        let mut fixture = VaultTest {
+            env: env.clone(),
+            client,
+            admin,
+            token,
+            subscribers: Vec::new(&env),
+        };
+        for _ in 0..subscribers {
+            let subscriber = fixture.funded_address(balance);
+            fixture.subscribers.push_back(subscriber);
+        }
+        fixture
+    }
+
+    /// Client for balance checks and transfers on the vault token.
+    pub fn token_client(&self) -> TokenClient<'static> {
+        TokenClient::new(&self.env, &self.token)
+    }
+
+    /// Mint `amount` of the vault token to `to`. Zero is a no-op.
+    pub fn mint(&self, to: &Address, amount: i128) {
+        if amount > 0 {
+            StellarAssetClient::new(&self.env, &self.token).mint(to, &amount);
+        }
+    }
+
+    /// A new address holding `amount` of the vault token.
+    pub fn funded_address(&self, amount: i128) -> Address {
+        let address = Address::generate(&self.env);
+        self.mint(&address, amount);
+        address
+    }
+
+    /// Create an interval subscription for a new subscriber and merchant, and deposit
+    /// `deposit` (minted to the subscriber first). A deposit of at least `amount`
+    /// activates it; a smaller one leaves it `Pending`. Returns the subscription id.
+    pub fn create_funded_subscription(&self, amount: i128, interval: u64, deposit: i128) -> u64 {
+        let subscriber = self.funded_address(deposit);
+        self.create_funded_subscription_for(&subscriber, amount, interval, deposit)
+    }
+
+    /// Like [`VaultTest::create_funded_subscription`] for an existing subscriber, such as
+    /// one of [`VaultTest::subscribers`]. Nothing is minted; the subscriber must hold
+    /// `deposit` already.
+    pub fn create_funded_subscription_for(
+        &self,
+        subscriber: &Address,
+        amount: i128,
+        interval: u64,
+        deposit: i128,
+    ) -> u64 {
+        let merchant = Address::generate(&self.env);
+        let id = self
+            .client
+            .create_subscription(subscriber, &merchant, &amount, &interval, &false);
+        if deposit > 0 {
+            self.client.deposit_funds(&id, subscriber, &deposit);
+        }
+        id
+    }
+
+    /// Set the ledger timestamp and move to the next ledger.
+    pub fn set_time(&self, timestamp: u64) {
+        self.env.ledger().with_mut(|li| {
+            li.sequence_number += 1;
+            li.timestamp = timestamp;
+        });
+    }
+
+    /// Move the clock forward by `secs` and to the next ledger.
+    pub fn advance_time(&self, secs: u64) {
+        self.set_time(self.env.ledger().timestamp() + secs);
+    }
+}
@@ -0,0 +1,160 @@
+//! Admin timelock: fee, token/oracle allowlist and upgrade changes are queued
+//! with an eta at least [`MIN_ADMIN_DELAY_SECONDS`] away and only applied by
+//! `execute_admin_action` once it has passed.
+//!
+//! **PRs that only change the admin timelock should edit this file only.**
+//!
+//! The queue is public so UIs can warn users of pending changes. Execution is
+//! permissionless; the admin's consent was given at queue time and can be
+//! withdrawn with `cancel_admin_action` until then. With an M-of-N admin set,
+//! queueing and cancelling are multisig actions (`multisig.rs`). The emergency pause is
+//! deliberately not timelocked.
+
+use crate::admin::require_admin;
+use crate::types::{
+    AdminAction, DataKeyExt, Error, QueuedAdminAction, ReferralTerms, MAX_QUEUED_ADMIN_ACTIONS,
+    MIN_ADMIN_DELAY_SECONDS,
+};
+use soroban_sdk::{Address, Env, Symbol, Vec};
+
+pub fn get_queued_admin_actions(env: &Env) -> Vec<QueuedAdminAction> {
+    env.storage()
+        .instance()
+        .get(&DataKeyExt::AdminQueue)
+        .unwrap_or(Vec::new(env))
+}
+
+fn store(env: &Env, queue: &Vec<QueuedAdminAction>) {
+    env.storage().instance().set(&DataKeyExt::AdminQueue, queue);
+}
+
+/// The single admin may act alone only while no M-of-N set is configured;
+/// otherwise queueing and cancelling go through `multisig.rs`.
+fn require_single_admin(env: &Env, admin: &Address) -> Result<(), Error> {
+    admin.require_auth();
+    if *admin != require_admin(env)? || crate::multisig::is_enabled(env) {
+        return Err(Error::Unauthorized);
+    }
+    Ok(())
+}
+
+/// Remove `action_id` from the queue and return it.
+fn take(env: &Env, action_id: u64) -> Result<QueuedAdminAction, Error> {
+    let mut queue = get_queued_admin_actions(env);
+    let idx = queue
+        .iter()
+        .position(|q| q.id == action_id)
+        .ok_or(Error::NotFound)?;
+    let queued = queue.get_unchecked(idx as u32);
+    queue.remove(idx as u32);
+    store(env, &queue);
+    Ok(queued)
+}
+
+/// Reject actions that would fail when executed, so they fail at queue time.
+fn validate(action: &AdminAction) -> Result<(), Error> {
+    match action {
+        AdminAction::SetKeeperReward(reward) if *reward < 0 => Err(Error::InvalidAmount),
+        AdminAction::AddSupportedToken(_, min_topup) if *min_topup < 0 => Err(Error::InvalidAmount),
+        AdminAction::SetReferralTerms(bps, _) if *bps > crate::math::MAX_BPS => {
+            Err(Error::InvalidAmount)
+        }
+        _ => Ok(()),
+    }
+}
+
+pub fn do_queue_admin_action(
+    env: &Env,
+    admin: Address,
+    action: AdminAction,
+    eta: u64,
+) -> Result<u64, Error> {
+    require_single_admin(env, &admin)?;
+    queue(env, action, eta)
+}
+
+/// Queue `action` once its queuer has been authorized.
+pub(crate) fn queue(env: &Env, action: AdminAction, eta: u64) -> Result<u64, Error> {
+    validate(&action)?;
+    let now = env.ledger().timestamp();
+    let earliest = now
+        .checked_add(MIN_ADMIN_DELAY_SECONDS)
+        .ok_or(Error::Overflow)?;
+    if eta < earliest {
+        return Err(Error::InvalidInterval);
+    }
+    let mut queue = get_queued_admin_actions(env);
+    if queue.len() >= MAX_QUEUED_ADMIN_ACTIONS {
+        return Err(Error::BatchTooLarge);
+    }
+
+    let id: u64 = env
+        .storage()
+        .instance()
+        .get(&DataKeyExt::NextAdminActionId)
+        .unwrap_or(0);
+    let next = id.checked_add(1).ok_or(Error::Overflow)?;
+    env.storage()
+        .instance()
+        .set(&DataKeyExt::NextAdminActionId, &next);
+    queue.push_back(QueuedAdminAction {
+        id,
+        action: action.clone(),
+        eta,
+        queued_at: now,
+    });
+    store(env, &queue);
+
+    env.events()
+        .publish((Symbol::new(env, "admin_queued"), id), (action, eta));
+    Ok(id)
+}
+
+/// Apply a queued action once its eta has passed. Callable by anyone.
+pub fn do_execute_admin_action(env: &Env, action_id: u64) -> Result<(), Error> {
+    let queued = get_queued_admin_actions(env)
+        .iter()
+        .find(|q| q.id == action_id)
+        .ok_or(Error::NotFound)?;
+    if env.ledger().timestamp() < queued.eta {
+        return Err(Error::IntervalNotElapsed);
+    }
+    take(env, action_id)?;
+
+    env.events().publish(
+        (Symbol::new(env, "admin_executed"), action_id),
+        queued.action.clone(),
+    );
+    match queued.action {
+        AdminAction::SetKeeperReward(reward) => crate::admin::set_keeper_reward(env, reward),
+        AdminAction::AddSupportedToken(token, min_topup) => {
+            crate::admin::add_supported_token(env, token, min_topup)
+        }
+        AdminAction::RemoveSupportedToken(token) => {
+            crate::admin::remove_supported_token(env, token)
+        }
+        AdminAction::AddPriceOracle(oracle) => crate::admin::set_price_oracle(env, oracle, true),
+        AdminAction::RemovePriceOracle(oracle) => {
+            crate::admin::set_price_oracle(env, oracle, false)
+        }
+        AdminAction::Upgrade(hash) => crate::admin::upgrade(env, hash),
+        AdminAction::SetReferralTerms(bps, periods) => {
+            crate::admin::set_referral_terms(env, ReferralTerms { bps, periods })
+        }
+    }
+}
+
+pub fn do_cancel_admin_action(env: &Env, admin: Address, action_id: u64) -> Result<(), Error> {
+    require_single_admin(env, &admin)?;
+    cancel(env, action_id)
+}
+
+/// Cancel `action_id` once its canceller has been authorized.
+pub(crate) fn cancel(env: &Env, action_id: u64) -> Result<(), Error> {
+    let queued = take(env, action_id)?;
+    env.events().publish(
+        (Symbol::new(env, "admin_cancelled"), action_id),
+        queued.action,
+    );
+    Ok(())
+}
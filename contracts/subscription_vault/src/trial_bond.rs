@@ -0,0 +1,145 @@
+//! Trial bonds: a merchant can require subscriptions created with a free trial
+//! to post a small refundable bond, so fresh wallets cannot farm trials.
+//!
+//! **PRs that only change trial bonds should edit this file only.**
+//!
+//! The bond is pulled from the payer at creation and held outside
+//! `prepaid_balance`. The first successful interval charge converts the trial
+//! and merges the bond into `prepaid_balance`. A subscription that ends before
+//! converting (cancelled, dunned out or expired) refunds the bond to the payer,
+//! less the forfeit share credited to the merchant. The forfeit is copied from
+//! the merchant's terms at creation, so changing the terms never applies
+//! retroactively.
+
+use crate::math::{mul_bps_floor, MAX_BPS};
+use crate::stored_sub::Optional;
+use crate::types::{DataKeyExt, Error, Subscription, TrialBond, TrialBondTerms};
+use soroban_sdk::{token, Address, Env, Symbol};
+
+pub fn get_terms(env: &Env, merchant: &Address) -> Option<TrialBondTerms> {
+    env.storage()
+        .instance()
+        .get(&DataKeyExt::TrialBondTerms(merchant.clone()))
+}
+
+pub fn get(env: &Env, subscription_id: u64) -> Option<TrialBond> {
+    crate::stored_sub::get_optional(env, subscription_id, Optional::TrialBond)
+}
+
+fn remove(env: &Env, subscription_id: u64) {
+    crate::stored_sub::remove_optional(env, subscription_id, Optional::TrialBond);
+}
+
+/// Merchant sets (or with `None` removes) the bond required for new trials.
+pub fn do_set_trial_bond_terms(
+    env: &Env,
+    merchant: Address,
+    terms: Option<TrialBondTerms>,
+) -> Result<(), Error> {
+    merchant.require_auth();
+    let key = DataKeyExt::TrialBondTerms(merchant.clone());
+    match &terms {
+        Some(t) if t.bond <= 0 || t.forfeit_bps > MAX_BPS => return Err(Error::InvalidAmount),
+        Some(t) => env.storage().instance().set(&key, t),
+        None => env.storage().instance().remove(&key),
+    }
+    env.events()
+        .publish((Symbol::new(env, "trial_bond_terms"), merchant), terms);
+    Ok(())
+}
+
+/// Carry the terms over on merchant rotation unless the new address has its own.
+pub(crate) fn move_terms(env: &Env, old_merchant: &Address, new_merchant: &Address) {
+    let Some(terms) = get_terms(env, old_merchant) else {
+        return;
+    };
+    let storage = env.storage().instance();
+    storage.remove(&DataKeyExt::TrialBondTerms(old_merchant.clone()));
+    if get_terms(env, new_merchant).is_none() {
+        storage.set(&DataKeyExt::TrialBondTerms(new_merchant.clone()), &terms);
+    }
+}
+
+/// Pull the merchant's bond from `payer` for a subscription created with a
+/// trial. `payer`'s authorization is already held.
+pub(crate) fn post(
+    env: &Env,
+    subscription_id: u64,
+    sub: &Subscription,
+    payer: &Address,
+) -> Result<(), Error> {
+    if sub.trial_until <= env.ledger().timestamp() {
+        return Ok(());
+    }
+    let Some(terms) = get_terms(env, &sub.merchant) else {
+        return Ok(());
+    };
+    token::Client::new(env, &sub.token).transfer(
+        payer,
+        &env.current_contract_address(),
+        &terms.bond,
+    );
+    crate::stored_sub::set_optional(
+        env,
+        subscription_id,
+        Optional::TrialBond,
+        &TrialBond {
+            payer: payer.clone(),
+            amount: terms.bond,
+            forfeit_bps: terms.forfeit_bps,
+        },
+    );
+    env.events().publish(
+        (Symbol::new(env, "trial_bond_posted"), subscription_id),
+        (payer.clone(), terms.bond, terms.forfeit_bps),
+    );
+    Ok(())
+}
+
+/// On the first successful interval charge: move the bond into
+/// `prepaid_balance`. The caller saves `sub`.
+pub(crate) fn convert(
+    env: &Env,
+    subscription_id: u64,
+    sub: &mut Subscription,
+) -> Result<(), Error> {
+    let Some(bond) = get(env, subscription_id) else {
+        return Ok(());
+    };
+    sub.prepaid_balance = sub
+        .prepaid_balance
+        .checked_add(bond.amount)
+        .ok_or(Error::Overflow)?;
+    remove(env, subscription_id);
+    env.events().publish(
+        (Symbol::new(env, "trial_bond_merged"), subscription_id),
+        (bond.amount, sub.prepaid_balance),
+    );
+    Ok(())
+}
+
+/// When a subscription ends before converting: refund the bond to its payer,
+/// less the forfeit credited to the merchant (rounded down).
+pub(crate) fn settle(env: &Env, subscription_id: u64, sub: &Subscription) -> Result<(), Error> {
+    let Some(bond) = get(env, subscription_id) else {
+        return Ok(());
+    };
+    let forfeited = mul_bps_floor(bond.amount, bond.forfeit_bps)?;
+    let refunded = bond.amount.checked_sub(forfeited).ok_or(Error::Overflow)?;
+    remove(env, subscription_id);
+    if forfeited > 0 {
+        crate::merchant::credit_merchant_balance(env, &sub.merchant, &sub.token, forfeited)?;
+    }
+    if refunded > 0 {
+        token::Client::new(env, &sub.token).transfer(
+            &env.current_contract_address(),
+            &bond.payer,
+            &refunded,
+        );
+    }
+    env.events().publish(
+        (Symbol::new(env, "trial_bond_refunded"), subscription_id),
+        (bond.payer, refunded, forfeited),
+    );
+    Ok(())
+}
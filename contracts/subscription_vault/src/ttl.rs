@@ -0,0 +1,53 @@
+//! Storage TTL (state archival): keeps live entries from being archived.
+//!
+//! **PRs that only change TTL thresholds or bump policy should edit this file only.**
+//!
+//! Config and indexes live in instance storage, which shares the contract
+//! instance's TTL. Subscriptions and other per-entry persistent data (usage
+//! roots, merchant overrides) are bumped on each write and read-for-write.
+
+use crate::types::{DataKey, DataKeyExt, INSTANCE_BUMP_LEDGERS, INSTANCE_TTL_THRESHOLD};
+use soroban_sdk::{Env, IntoVal, Val};
+
+/// Extend the contract instance (config and indexes) once its TTL
+/// drops below [`INSTANCE_TTL_THRESHOLD`].
+///
+/// Checked at most once per ledger: the host sizes the whole instance on
+/// every `extend_ttl`, even a no-op one, which dominated the cost of a batch
+/// charge. A second check in the same ledger could not change the TTL.
+pub fn bump_instance(env: &Env) {
+    let storage = env.storage().instance();
+    let sequence = env.ledger().sequence();
+    if storage.get::<_, u32>(&DataKeyExt::InstanceBumpedAt) == Some(sequence) {
+        return;
+    }
+    storage.set(&DataKeyExt::InstanceBumpedAt, &sequence);
+    storage.extend_ttl(INSTANCE_TTL_THRESHOLD, bump_to(env));
+}
+
+/// Keep both halves of a subscription's record and its optional entries
+/// alive, along with the instance they depend on.
+pub fn bump_subscription(env: &Env, subscription_id: u64) {
+    bump_instance(env);
+    let key = DataKey::Sub(subscription_id);
+    if env.storage().persistent().has(&key) {
+        bump_persistent(env, &key);
+    }
+    let meta = DataKeyExt::SubMeta(subscription_id);
+    if env.storage().persistent().has(&meta) {
+        bump_persistent(env, &meta);
+    }
+    crate::stored_sub::bump_optional(env, subscription_id);
+}
+
+/// Extend a persistent entry (a `DataKey` or `DataKeyExt`) on the same
+/// schedule as the instance.
+pub fn bump_persistent<K: IntoVal<Env, Val>>(env: &Env, key: &K) {
+    env.storage()
+        .persistent()
+        .extend_ttl(key, INSTANCE_TTL_THRESHOLD, bump_to(env));
+}
+
+fn bump_to(env: &Env) -> u32 {
+    INSTANCE_BUMP_LEDGERS.min(env.storage().max_ttl())
+}
@@ -3,7 +3,7 @@
 //! Kept in a separate module to reduce merge conflicts when editing state machine
 //! or contract entrypoints.
 
-use soroban_sdk::{contracterror, contracttype, Address};
+use soroban_sdk::{contracterror, contracttype, Address, Bytes, BytesN, Map, Symbol, Vec};
 
 /// Increment this constant whenever the on-chain storage schema changes.
 ///
@@ -11,6 +11,64 @@ use soroban_sdk::{contracterror, contracttype, Address};
 /// Migration logic must read this value and branch on it before touching storage.
 pub const STORAGE_VERSION: u32 = 1;
 
+/// Shortest billing interval accepted by `update_interval` (1 hour).
+pub const MIN_INTERVAL_SECONDS: u64 = 60 * 60;
+
+/// Longest billing interval accepted by `update_interval` (366 days).
+pub const MAX_INTERVAL_SECONDS: u64 = 366 * 24 * 60 * 60;
+
+/// Ledgers a processed `usage_id` is remembered in temporary storage
+/// (~1 day at 5s ledgers), well beyond any metering-service retry window.
+pub const USAGE_ID_TTL_LEDGERS: u32 = 17_280;
+
+/// Ledgers an instance or persistent entry is extended to when bumped
+/// (~30 days at 5s ledgers), capped at the network's max TTL.
+pub const INSTANCE_BUMP_LEDGERS: u32 = 518_400;
+
+/// Remaining TTL below which a touched entry is bumped (~29 days), so busy
+/// entries are extended about once a day rather than on every call.
+pub const INSTANCE_TTL_THRESHOLD: u32 = INSTANCE_BUMP_LEDGERS - 17_280;
+
+/// Default ceiling on a subscription's per-interval `amount`; see [`Limits`].
+pub const DEFAULT_MAX_AMOUNT: i128 = 1_000_000_000_000_000_000;
+
+/// Default `oracle_max_age`: oracle prices older than this fail the charge.
+pub const DEFAULT_ORACLE_MAX_AGE: u64 = 15 * 60;
+
+/// Longest `metadata` a subscription may carry, in bytes.
+pub const MAX_METADATA_LEN: u32 = 128;
+
+/// Ring size of a merchant's pending (escrowed) earnings per token.
+pub const MAX_PENDING_BUCKETS: u32 = 8;
+
+/// Most ids `get_subscriptions` reads in one call, to stay within the read budget.
+pub const MAX_BULK_READ: u32 = 50;
+
+/// Highest `dust_sweep_threshold` the admin may set, in token base units, so
+/// `sweep_dust` can never take more than dust from a subscriber.
+pub const MAX_DUST_SWEEP_THRESHOLD: i128 = 100_000;
+
+/// Charges kept per subscription by `get_charge_history`; older ones roll off.
+pub const CHARGE_HISTORY_LEN: u32 = 12;
+
+// Reason codes for pause and cancel. A documented convention only: any `u32`
+// is accepted and stored as given. See `docs/reason_codes.md`.
+
+/// No reason given.
+pub const REASON_UNSPECIFIED: u32 = 0;
+/// Subscriber: the price is too high.
+pub const REASON_TOO_EXPENSIVE: u32 = 1;
+/// Subscriber: not using the service.
+pub const REASON_NOT_USING: u32 = 2;
+/// Subscriber: moving to another provider or plan.
+pub const REASON_SWITCHING: u32 = 3;
+/// Subscriber: taking a temporary break.
+pub const REASON_TEMPORARY_BREAK: u32 = 4;
+/// Subscriber: any other reason.
+pub const REASON_OTHER: u32 = 99;
+/// First code of the merchant/admin range; codes from here up are defined off-chain.
+pub const REASON_MERCHANT_MIN: u32 = 100;
+
 /// Canonical storage key enum for all contract state.
 ///
 /// ⚠️ Upgrade-sensitive: discriminant order is fixed. Never remove or reorder
@@ -26,67 +84,422 @@ pub enum DataKey {
     Admin,
     /// Minimum deposit threshold. Discriminant 3.
     MinTopup,
-    /// Auto-incrementing subscription ID counter. Discriminant 4.
+    /// Next subscription id to assign (`u64`). Discriminant 4.
     NextId,
     /// On-chain storage schema version. Discriminant 5.
     SchemaVersion,
     /// Subscription record keyed by its ID. Discriminant 6.
-    Sub(u32),
+    Sub(u64),
     /// Last charged billing-period index for replay protection. Discriminant 7.
-    ChargedPeriod(u32),
+    ChargedPeriod(u64),
     /// Idempotency key stored per subscription. Discriminant 8.
-    IdemKey(u32),
+    IdemKey(u64),
+    /// Contract code version, bumped on every `upgrade`. Discriminant 9.
+    ContractVersion,
+    /// Contract-wide emergency pause flag. Discriminant 10.
+    ContractPaused,
+    /// Accrued, not yet withdrawn merchant earnings per (merchant, token). Discriminant 11.
+    MerchantBalance(Address, Address),
+    /// Allowlisted token contracts for new subscriptions (`Vec<Address>`). Discriminant 12.
+    SupportedTokens,
+    /// Per-token minimum top-up (`Map<Address, i128>`). Discriminant 13.
+    TokenMinTopups,
+    /// Lifetime billing stats per subscription, kept off the main record. Discriminant 14.
+    SubStats(u64),
+    /// Tiered usage pricing (`Vec<UsageTier>`) per subscription. Discriminant 15.
+    UsageTiers(u64),
+    /// Processed usage charge id -> charged amount, in temporary storage. Discriminant 16.
+    UsageId(u64, BytesN<32>),
+    /// Failed balance charges before dunning auto-cancels (`u32`, 0 = never). Discriminant 17.
+    MaxFailedCharges,
+    /// Seconds after a missed due date before a subscription leaves `Active` (`u64`). Discriminant 18.
+    GraceSeconds,
+    /// Merchant-proposed new `amount` awaiting subscriber acceptance (`i128`). Discriminant 19.
+    PendingAmount(u64),
+    /// Subscriber-proposed interval awaiting merchant acknowledgement (`u64`). Discriminant 20.
+    ProposedInterval(u64),
+    /// Plan by id. Discriminant 21.
+    Plan(u32),
+    /// Next plan id to assign (plan ids start at 1). Discriminant 22.
+    NextPlanId,
+    /// Plan ids owned by a merchant (`Vec<u32>`). Discriminant 23.
+    MerchantPlans(Address),
+    /// Number of subscriptions created from a plan (`u32`). Discriminant 24.
+    PlanSubCount(u32),
+    /// Live subscription id for a `(subscriber, merchant)` pair, for duplicate prevention. Discriminant 25.
+    ActivePair(Address, Address),
+    /// Live subscription id for a `(subscriber, plan_id)` pair, for duplicate prevention. Discriminant 26.
+    ActivePlanSub(Address, u32),
+    /// Billing periods a new subscription must be funded for at creation (`u32`). Discriminant 27.
+    RequireInitialPeriods,
+    /// Proposed new subscriber for a subscription transfer (`Address`). Discriminant 28.
+    PendingTransfer(u64),
+    /// Address a rotated-away merchant now forwards to (`Address`). Discriminant 29.
+    MerchantRedirect(Address),
+    /// Address blocked by the admin from creating or funding subscriptions (`bool`). Discriminant 30.
+    Blacklisted(Address),
+    /// Only registered, active merchants may get new subscriptions (`bool`). Discriminant 31.
+    MerchantAllowlistEnabled,
+    /// Admin-registered merchant record (`MerchantInfo`). Discriminant 32.
+    MerchantInfo(Address),
+    /// Seconds charged funds stay pending before the merchant can withdraw (`u64`). Discriminant 33.
+    SettlementDelay,
+    /// Not-yet-released merchant earnings, keyed by (merchant, token) (`Vec<PendingBucket>`). Discriminant 34.
+    MerchantPending(Address, Address),
+    /// Disputable charge, keyed by (subscription_id, period_seq), in temporary storage (`ChargeRecord`). Discriminant 35.
+    ChargeRecord(u64, u32),
+    /// Dispute record (`Dispute`). Discriminant 36.
+    Dispute(u32),
+    /// Next dispute id (`u32`). Discriminant 37.
+    NextDisputeId,
+    /// Flat reward paid to the caller of `charge_subscription` (`i128`). Discriminant 38.
+    KeeperReward,
+    /// Usage-charge rate limit as (max calls per window, window seconds) (`(u32, u64)`). Discriminant 39.
+    UsageRateLimit,
+    /// Merchant's ed25519 key for signed usage reports (`BytesN<32>`). Discriminant 40.
+    UsageAttestationKey(Address),
+    /// Posted usage merkle root, in persistent storage (`UsageRoot`). Discriminant 41.
+    UsageRoot(u32),
+    /// Next usage root id (`u32`). Discriminant 42.
+    NextUsageRootId,
+    /// Marks a leaf hash of a usage root as applied, in persistent storage (`bool`). Discriminant 43.
+    UsageLeafApplied(u32, BytesN<32>),
+    /// Merchant's minimum top-up override, in persistent storage (`i128`). Discriminant 44.
+    MerchantMinTopup(Address),
+    /// Sum of all subscriptions' prepaid balances (`i128`). Discriminant 45.
+    Tvl,
+    /// Number of subscriptions per status (`Map<SubscriptionStatus, u32>`). Discriminant 46.
+    StatusCounts,
+    /// Last charges of a subscription, in persistent storage (`Vec<ChargeHistoryEntry>`). Discriminant 47.
+    ChargeHistory(u64),
+    /// Merchant coupon by code, in persistent storage (`Coupon`). Discriminant 48.
+    Coupon(Address, BytesN<16>),
+    /// Payer of a gift subscription (`Address`). Discriminant 49.
+    GiftPayer(u64),
 }
 
+/// Storage keys added after [`DataKey`] reached the 50-variant limit of a
+/// contract type. Keys are stored as `[variant name, fields..]`, so a variant
+/// here must never reuse a `DataKey` variant name. Append-only, like `DataKey`.
+#[contracttype]
+#[derive(Clone)]
+pub enum DataKeyExt {
+    /// Invite by id, in persistent storage (`PendingInvite`). Discriminant 0.
+    Invite(u32),
+    /// Next invite id (`u32`). Discriminant 1.
+    NextInviteId,
+    /// Present when every interval charge needs the subscriber's approval (`bool`). Discriminant 2.
+    RenewalApprovalRequired(u64),
+    /// Charge number (`periods_charged + 1`) the subscriber approved (`u32`). Discriminant 3.
+    RenewalApproved(u64),
+    /// Seconds without activity after which `expire_stale` may expire a subscription (`u64`). Discriminant 4.
+    StalenessSeconds,
+    /// Timestamp of the subscription's last deposit (`u64`), in persistent storage. Discriminant 5.
+    LastDeposit(u64),
+    /// Subscriber's ceiling on lifetime charges (`i128`); absent means unlimited. Discriminant 6.
+    LifetimeCap(u64),
+    /// Admin bounds on `amount` and `interval_seconds` ([`Limits`]). Discriminant 7.
+    Limits,
+    /// Present when the admin allowlisted this price oracle (`bool`). Discriminant 8.
+    PriceOracle(Address),
+    /// Oldest oracle price, in seconds, a charge will use (`u64`). Discriminant 9.
+    OracleMaxAge,
+    /// [`FiatPricing`] of a fiat-denominated subscription. Discriminant 10.
+    FiatPricing(u64),
+    /// [`BillingAnchor`] of an anchored subscription. Discriminant 11.
+    BillingAnchor(u64),
+    /// Seconds before its due time an interval charge may run (`u64`). Discriminant 12.
+    ChargeTolerance,
+    /// [`PayoutConfig`] of a merchant. Discriminant 13.
+    PayoutConfig(Address),
+    /// Permanent payout destination a merchant locked itself to (`Address`). Discriminant 14.
+    PayoutLock(Address),
+    /// Present when usage charges may run while the subscription is `Paused` (`bool`). Discriminant 15.
+    UsageWhilePaused(u64),
+    /// Merchant-proposed `usage_while_paused` awaiting the subscriber (`bool`). Discriminant 16.
+    PendingUsageWhilePaused(u64),
+    /// Merchant-proposed [`UsageToggle`] awaiting the subscriber. Discriminant 17.
+    PendingUsageToggle(u64),
+    /// Present once the admin closed subscription imports for good (`bool`). Discriminant 18.
+    MigrationComplete,
+    /// Running [`MerchantCounters`] of a merchant, in persistent storage. Discriminant 19.
+    MerchantStats(Address),
+    /// Ledger sequence of the last instance TTL check (`u32`); see `ttl.rs`. Discriminant 20.
+    InstanceBumpedAt,
+    /// Delegate payer allowed to `deposit_funds` for a subscription (`Address`). Discriminant 21.
+    DelegatePayer(u64),
+    /// Subscriber's [`AutoTopup`] config. Discriminant 22.
+    AutoTopup(u64),
+    /// Timelocked admin actions awaiting execution (`Vec<QueuedAdminAction>`). Discriminant 23.
+    AdminQueue,
+    /// Id the next queued admin action gets (`u64`). Discriminant 24.
+    NextAdminActionId,
+    /// M-of-N [`AdminSet`]; absent means the single stored admin. Discriminant 25.
+    AdminSet,
+    /// [`AdminProposal`] collecting approvals, by action hash. Discriminant 26.
+    AdminProposal(BytesN<32>),
+    /// Ledger sequence of the last periodic charge (`u32`), in temporary storage. Discriminant 27.
+    LastChargeLedger(u64),
+    /// Present when the admin granted this address the viewer role (`bool`). Discriminant 28.
+    Viewer(Address),
+    /// Admin's [`ReferralTerms`] for new subscriptions. Discriminant 29.
+    ReferralTerms,
+    /// [`Referral`] of a subscription while it still owes referral fees. Discriminant 30.
+    Referral(u64),
+    /// Merchant's [`TrialBondTerms`] for new trials. Discriminant 31.
+    TrialBondTerms(Address),
+    /// [`TrialBond`] held for a subscription until it converts or ends. Discriminant 32.
+    TrialBond(u64),
+    /// Prepaid balance below which a usage debit ends `Active` (`i128`). Discriminant 33.
+    DustThreshold,
+    /// Subscriber's watch address, added as a topic to notification events (`Address`). Discriminant 34.
+    NotifyAddress(u64),
+    /// Seconds a subscription must be terminal before `sweep_dust` (`u64`). Discriminant 35.
+    DustSweepDelay,
+    /// Destination of swept dust (`Address`). Discriminant 36.
+    Treasury,
+    /// Largest balance `sweep_dust` may take (`i128`). Discriminant 37.
+    DustSweepThreshold,
+    /// The [`SubscriptionMeta`] half of a split subscription record, in
+    /// persistent storage next to its [`SubscriptionCore`]. Discriminant 38.
+    SubMeta(u64),
+    /// Which optional per-subscription entries exist (`u32` bitmask, see
+    /// `stored_sub::Optional`), in persistent storage. Discriminant 39.
+    SubEntries(u64),
+}
+
+/// Contract errors, grouped by code range: 1xx configuration and
+/// initialization, 2xx authorization, 3xx state machine, 4xx balances and
+/// amounts, 5xx limits. See `docs/errors.md`.
 #[contracterror]
 #[derive(Clone, Debug, Eq, PartialEq)]
 #[repr(u32)]
 pub enum Error {
-    NotFound = 404,
-    Unauthorized = 401,
-    /// Charge attempted before `last_payment_timestamp + interval_seconds`.
-    IntervalNotElapsed = 1001,
+    // --- Configuration and initialization (1xx). ---
+    /// `init` has not been called; no admin or token is configured.
+    NotInitialized = 100,
+    /// `init` was already called.
+    AlreadyInitialized = 101,
+    /// Money-moving operation attempted while the contract is paused by admin.
+    ContractPaused = 102,
+    /// Token is not on the admin-managed allowlist.
+    TokenNotSupported = 103,
+    /// The price oracle is not (or no longer) allowlisted by the admin.
+    OracleNotAllowed = 104,
+    /// The price oracle returned no usable price, or one older than `oracle_max_age`.
+    StaleOraclePrice = 105,
+    /// `migration_complete` was called; no more subscriptions can be imported.
+    MigrationComplete = 106,
+
+    // --- Authorization (2xx). ---
+    /// Caller is not allowed to perform this operation.
+    Unauthorized = 200,
+    /// Address is on the admin blacklist.
+    AddressBlocked = 201,
+    /// Merchant is not registered, or is deactivated, while new subscriptions require it.
+    MerchantNotRegistered = 202,
+    /// Usage report is unsigned where a signature is required, or its nonce was already used.
+    InvalidAttestation = 203,
+    /// Merkle proof does not lead to the posted root, or leaves exceed its total.
+    InvalidMerkleProof = 204,
+    /// The merchant locked its payout destination; funds may only go there,
+    /// and the lock cannot be changed.
+    PayoutDestinationLocked = 205,
+    /// Merchant rotation to itself, from an already-rotated address, or to one.
+    InvalidMerchantRotation = 206,
+    /// The subscription requires renewal approval and the subscriber has not
+    /// approved the next charge.
+    RenewalNotApproved = 207,
+
+    // --- State machine and lifecycle (3xx). ---
+    /// Subscription, plan, proposal or other record does not exist.
+    NotFound = 300,
+    /// Status change not allowed by the state machine (see `state_machine.rs`).
+    InvalidStatusTransition = 301,
     /// Subscription is not Active (e.g. Paused, Cancelled).
-    NotActive = 1002,
-    InvalidStatusTransition = 400,
-    BelowMinimumTopup = 402,
-    /// Arithmetic overflow in computation (e.g. amount * intervals).
-    Overflow = 403,
-    /// Charge failed due to insufficient prepaid balance.
-    InsufficientBalance = 1003,
-    /// Usage-based charge attempted on a subscription with `usage_enabled = false`.
-    UsageNotEnabled = 1004,
-    /// Usage-based charge amount exceeds the available prepaid balance.
-    InsufficientPrepaidBalance = 1005,
-    /// The provided amount is zero or negative.
-    InvalidAmount = 1006,
+    NotActive = 302,
+    /// Charge attempted before `last_payment_timestamp + interval_seconds`, or
+    /// a timelocked admin action executed before its `eta`.
+    IntervalNotElapsed = 303,
     /// Charge already processed for this billing period.
-    Replay = 1007,
-    /// Recovery amount is zero or negative.
-    InvalidRecoveryAmount = 1008,
+    Replay = 304,
+    /// Interval charge attempted while the subscription is still in its free trial.
+    InTrialPeriod = 305,
+    /// Usage-based charge attempted on a subscription with `usage_enabled = false`.
+    UsageNotEnabled = 306,
+    /// Subscription has unpaid arrears; call `settle_arrears` first.
+    ArrearsOutstanding = 307,
+    /// A live subscription already exists for this subscriber and merchant (or plan).
+    DuplicateSubscription = 308,
+    /// Plan has been deactivated and accepts no new subscribers.
+    PlanInactive = 309,
+    /// Operation does not apply to the subscription's funding mode.
+    InvalidFundingMode = 310,
+    /// Charge is unknown or its settlement window has passed.
+    DisputeWindowClosed = 311,
+    /// A dispute was already opened for this charge.
+    ChargeAlreadyDisputed = 312,
+    /// Dispute was already resolved.
+    DisputeNotOpen = 313,
+    /// Subscription is live, still holds funds or arrears, or has a charge
+    /// that can still be disputed, so it cannot be purged.
+    NotPurgeable = 314,
+    /// A required charge callback failed, so the charge was not applied.
+    CallbackFailed = 315,
+    /// Coupon code is unknown, expired or fully redeemed, or already taken.
+    CouponInvalid = 316,
+    /// Invite expiry is not in the future, or the invite has expired.
+    InviteExpired = 317,
+    /// Staleness expiry is disabled, or the subscription had a deposit or
+    /// charge within the staleness window.
+    NotStale = 318,
+    /// Dust sweeping is disabled or has no treasury, or the subscription is
+    /// not terminal, has been terminal for no longer than `dust_sweep_delay`,
+    /// or holds nothing or more than the dust threshold.
+    NotSweepable = 319,
+    /// A periodic charge of this subscription was already applied in this
+    /// ledger, e.g. by a racing keeper.
+    AlreadyChargedThisLedger = 320,
+
+    // --- Balances and amounts (4xx). ---
+    /// The provided amount is zero or negative, or a rate, bps or count is out of range.
+    InvalidAmount = 400,
+    /// Arithmetic overflow in computation (e.g. amount * intervals), or every
+    /// subscription id has been assigned (the counter never wraps).
+    Overflow = 401,
+    /// Deposit is below the admin-configured minimum top-up.
+    BelowMinimumTopup = 402,
+    /// The subscription's funds cannot cover the charge, usage debit or
+    /// withdrawal. Code 404 is retired; it was the usage-charge duplicate of this.
+    InsufficientBalance = 403,
+    /// Merchant tried to withdraw or refund more than their accrued balance.
+    InsufficientMerchantBalance = 405,
+    /// Withdrawal would leave less than one interval's `amount` in a live subscription.
+    ReserveRequired = 406,
+    /// `initial_deposit` does not cover the required number of billing periods.
+    InsufficientInitialDeposit = 407,
+    /// The charge would take lifetime charges past the subscriber's `lifetime_cap`.
+    LifetimeCapExceeded = 408,
+    /// Usage charge would push this period's usage past the subscriber's cap.
+    UsageCapExceeded = 409,
+
+    // --- Limits and bounds (5xx). ---
+    /// Billing interval outside the configured [`Limits`], or an admin action
+    /// queued with an `eta` sooner than [`MIN_ADMIN_DELAY_SECONDS`] away.
+    InvalidInterval = 500,
+    /// More ids than [`MAX_BULK_READ`] passed to a bulk read.
+    BatchTooLarge = 501,
+    /// Metadata is longer than [`MAX_METADATA_LEN`] bytes.
+    MetadataTooLarge = 502,
+    /// Too many usage charges in the current rate-limit window.
+    RateLimited = 503,
+    /// Usage tiers are not sorted by `up_to`, overlap, or have a non-positive rate.
+    InvalidUsageTiers = 504,
 }
 
 impl Error {
     /// Returns the numeric code for this error (for batch result reporting).
+    /// The code is the enum discriminant; see `docs/errors.md` for the ranges.
     pub const fn to_code(self) -> u32 {
-        match self {
-            Error::NotFound => 404,
-            Error::Unauthorized => 401,
-            Error::IntervalNotElapsed => 1001,
-            Error::NotActive => 1002,
-            Error::InvalidStatusTransition => 400,
-            Error::BelowMinimumTopup => 402,
-            Error::Overflow => 403,
-            Error::InsufficientBalance => 1003,
-            Error::UsageNotEnabled => 1004,
-            Error::InsufficientPrepaidBalance => 1005,
-            Error::InvalidAmount => 1006,
-            Error::Replay => 1007,
-            Error::InvalidRecoveryAmount => 1008,
-        }
+        self as u32
     }
 }
 
+/// Vault-wide aggregates returned by `get_stats`, maintained incrementally.
+#[contracttype]
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct VaultStats {
+    /// Total value locked: sum of all subscriptions' `prepaid_balance`.
+    pub tvl: i128,
+    /// Subscriptions currently stored; purged ones are no longer counted.
+    pub total_subscriptions: u32,
+    pub active: u32,
+    pub paused: u32,
+    pub cancelled: u32,
+    pub insufficient_balance: u32,
+    pub completed: u32,
+    pub pending: u32,
+    pub expired: u32,
+}
+
+/// Window covered by `MerchantStats::charged_this_period`, aligned to
+/// multiples of itself since the epoch.
+pub const MERCHANT_STATS_PERIOD_SECONDS: u64 = 30 * 24 * 60 * 60;
+
+/// Per-merchant counters kept under `DataKeyExt::MerchantStats`, maintained
+/// incrementally (see `merchant_stats.rs`).
+#[contracttype]
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct MerchantCounters {
+    pub active_subscribers: u32,
+    pub total_charged: i128,
+    /// Start of the window `charged_this_period` belongs to.
+    pub period_start: u64,
+    pub charged_this_period: i128,
+}
+
+/// A merchant's aggregate numbers, returned by `get_merchant_stats`. Amounts
+/// are in the vault's default token; charges in other tokens are not counted.
+#[contracttype]
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct MerchantStats {
+    /// Subscriptions currently `Active`.
+    pub active_subscribers: u32,
+    /// Everything credited to the merchant by charges, before refunds.
+    pub total_charged: i128,
+    /// Credited since `period_start`.
+    pub charged_this_period: i128,
+    /// Start of the current [`MERCHANT_STATS_PERIOD_SECONDS`] window.
+    pub period_start: u64,
+    /// Accrued balance that can be withdrawn now.
+    pub available: i128,
+    /// Accrued balance still inside the settlement delay.
+    pub pending: i128,
+}
+
+/// Snapshot of every admin-settable parameter, returned by `get_config`.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ContractConfig {
+    pub admin: Address,
+    /// Token passed to `init`.
+    pub token: Address,
+    /// Minimum top-up for the `init` token.
+    pub min_topup: i128,
+    pub supported_tokens: Vec<Address>,
+    pub max_failed_charges: u32,
+    pub grace_seconds: u64,
+    pub max_usage_charges_per_window: u32,
+    pub usage_window_seconds: u64,
+    pub keeper_reward: i128,
+    pub settlement_delay: u64,
+    pub require_initial_periods: u32,
+    pub staleness_seconds: u64,
+    pub merchant_allowlist_enabled: bool,
+    pub paused: bool,
+    pub limits: Limits,
+    pub oracle_max_age: u64,
+    pub charge_tolerance_seconds: u64,
+    pub referral_terms: ReferralTerms,
+    pub dust_threshold: i128,
+    pub dust_sweep_delay: u64,
+    pub dust_sweep_threshold: i128,
+    pub treasury: Option<Address>,
+}
+
+/// Inclusive bounds on subscription terms, set with `set_limits`. Checked when
+/// a subscription, plan or invite is created and when its amount or interval
+/// changes. Defaults: `1..=DEFAULT_MAX_AMOUNT` and
+/// `MIN_INTERVAL_SECONDS..=MAX_INTERVAL_SECONDS`.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Limits {
+    pub min_amount: i128,
+    pub max_amount: i128,
+    pub min_interval: u64,
+    pub max_interval: u64,
+}
+
 /// Result of charging one subscription in a batch. Used by [`crate::SubscriptionVault::batch_charge`].
 #[contracttype]
 #[derive(Clone, Debug)]
@@ -97,23 +510,158 @@ pub struct BatchChargeResult {
     pub error_code: u32,
 }
 
+/// Most subscriptions one `bulk_create_unfunded` call may create.
+pub const MAX_BULK_CREATE: u32 = 20;
+
+/// One subscription of a `bulk_create_unfunded` batch.
+#[contracttype]
+#[derive(Clone, Debug)]
+pub struct BulkEntry {
+    pub subscriber: Address,
+    pub amount: i128,
+    pub interval_seconds: u64,
+    pub usage_enabled: bool,
+}
+
+/// Most operations one `multicall` may run.
+pub const MAX_MULTICALL_OPS: u32 = 8;
+
+/// `subscription_id` of a `multicall` op meaning "the subscription created by
+/// the latest `CreateSubscription` op of this call".
+pub const MULTICALL_LAST_CREATED: u64 = u64::MAX;
+
+/// Arguments of `create_subscription_with_options`, minus the subscriber
+/// (the `multicall` caller).
+#[contracttype]
+#[derive(Clone, Debug)]
+pub struct CreateSubscriptionOp {
+    pub merchant: Address,
+    pub amount: i128,
+    pub interval_seconds: u64,
+    pub usage_enabled: bool,
+    pub options: SubscriptionOptions,
+}
+
+/// One operation of a `multicall`, run as the call's `caller`.
+#[contracttype]
+#[derive(Clone, Debug)]
+#[allow(clippy::large_enum_variant)]
+pub enum VaultOp {
+    CreateSubscription(CreateSubscriptionOp),
+    /// `(subscription_id, amount)`.
+    DepositFunds(u64, i128),
+    /// `(subscription_id, reason)`.
+    CancelSubscription(u64, u32),
+    /// `subscription_id`.
+    WithdrawSubscriberFunds(u64),
+}
+
+/// What a `multicall` op returned, in op order.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum VaultOpResult {
+    /// New subscription id.
+    Created(u64),
+    /// `prepaid_balance` after the deposit.
+    Deposited(i128),
+    Cancelled,
+    /// Amount refunded.
+    Withdrawn(i128),
+}
+
+/// Outcome of `charge_subscription`, so callers need not re-read the subscription.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ChargeReceipt {
+    /// Collected this call; below `amount` for a partial charge, 0 when no period was charged.
+    pub amount_charged: i128,
+    pub new_prepaid_balance: i128,
+    /// `last_payment_timestamp + interval_seconds` after the call.
+    pub next_charge_at: u64,
+    /// Billing periods paid by this call (0 or 1).
+    pub periods_settled: u32,
+    /// Seats billed by this charge (the subscription's quantity when nothing was charged).
+    pub quantity: u32,
+    pub status: SubscriptionStatus,
+    /// Charge sequence number assigned to this charge (0 when nothing was charged).
+    pub charge_seq: u32,
+    /// Code of the error the charge failed with for lack of funds, 0 otherwise. Such a
+    /// failure is reported here rather than returned, so its dunning state is kept.
+    pub failure_code: u32,
+}
+
+/// Dry-run result of `preview_charge`.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ChargePreview {
+    /// `last_payment_timestamp + interval_seconds`, less the early-charge
+    /// tolerance, has been reached.
+    pub interval_elapsed: bool,
+    /// Billing periods the next charge would settle (0 or 1). Zero when the
+    /// status, trial, scheduled cancellation, or replay protection blocks it.
+    pub periods_due: u32,
+    /// Total that would be debited; below `amount` for a partial charge.
+    pub amount: i128,
+    /// Part of `amount` paid to the calling keeper instead of the merchant.
+    pub fee: i128,
+    /// Available funds cover the charge (with partial charging, any funds at all).
+    pub balance_sufficient: bool,
+    /// The charge would collect less than `amount` and record arrears.
+    pub partial: bool,
+    /// `charge_subscription` would charge a period right now.
+    pub will_succeed: bool,
+}
+
+/// Outcome of `charge_usage`.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct UsageChargeReceipt {
+    pub amount_charged: i128,
+    pub new_prepaid_balance: i128,
+    /// Usage charged so far in the current billing period, including this call.
+    pub usage_charged_this_period: i128,
+    pub status: SubscriptionStatus,
+    /// Charge sequence number assigned to this charge (0 for a repeated `usage_id`).
+    pub charge_seq: u32,
+}
+
 /// Represents the lifecycle state of a subscription.
 ///
 /// # State Machine
 ///
 /// The subscription status follows a defined state machine with specific allowed transitions:
 ///
+/// - **Pending**: Prepaid subscription created without one period's charge in
+///   its balance. Not charged; activates on the first deposit that covers it.
+///   - Can transition to: `Active` (after deposit), `Cancelled`, `Expired`
+///
 /// - **Active**: Subscription is active and charges can be processed.
-///   - Can transition to: `Paused`, `Cancelled`, `InsufficientBalance`
+///   - Can transition to: `Paused`, `Cancelled`, `InsufficientBalance`, `Completed`, `Expired`
 ///
 /// - **Paused**: Subscription is temporarily suspended, no charges are processed.
-///   - Can transition to: `Active`, `Cancelled`
+///   - Can transition to: `Active`, `Cancelled`, `InsufficientBalance` (a usage
+///     charge allowed while paused drained the balance), `Expired`
 ///
 /// - **Cancelled**: Subscription is permanently terminated, no further changes allowed.
 ///   - No outgoing transitions (terminal state)
 ///
 /// - **InsufficientBalance**: Subscription failed due to insufficient funds.
-///   - Can transition to: `Active` (after deposit), `Cancelled`
+///   - Can transition to: `Active` (after deposit), `Cancelled`, `Expired`
+///
+/// - **Completed**: Fixed-length subscription charged its final period.
+///   - No outgoing transitions (terminal state)
+///
+/// - **Expired**: No deposit or successful charge within the admin's
+///   staleness window; set by `expire_stale`. Balance stays withdrawable.
+///   - No outgoing transitions (terminal state)
+///
+/// | From | Allowed targets |
+/// |------|-----------------|
+/// | `Pending` | `Active`, `Cancelled`, `Expired` |
+/// | `Active` | `Paused`, `Cancelled`, `InsufficientBalance`, `Completed`, `Expired` |
+/// | `Paused` | `Active`, `Cancelled`, `InsufficientBalance`, `Expired` |
+/// | `InsufficientBalance` | `Active`, `Cancelled`, `Expired` |
+/// | `Cancelled`, `Completed`, `Expired` | none |
 ///
 /// Invalid transitions (e.g., `Cancelled` -> `Active`) are rejected with
 /// [`Error::InvalidStatusTransition`].
@@ -128,6 +676,52 @@ pub enum SubscriptionStatus {
     Cancelled = 2,
     /// Subscription failed due to insufficient balance for charging.
     InsufficientBalance = 3,
+    /// Fixed-length subscription reached `max_periods` (terminal state).
+    Completed = 4,
+    /// Created without enough prepaid funds for one charge; awaiting a deposit.
+    Pending = 5,
+    /// No activity within the staleness window (terminal state).
+    Expired = 6,
+}
+
+/// Where a subscription's charges are paid from.
+#[contracttype]
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub enum FundingMode {
+    /// Charges debit `prepaid_balance`, funded by deposits into the vault.
+    #[default]
+    Prepaid = 0,
+    /// Charges pull from the subscriber's wallet with `transfer_from`, using an
+    /// allowance granted to this contract. `prepaid_balance` stays zero.
+    DirectDebit = 1,
+    /// Charges use `prepaid_balance` first and pull any remainder by direct debit.
+    Hybrid = 2,
+}
+
+/// Original 8-field subscription record (storage schema v1). Only read, when
+/// an old record is loaded; see `stored_sub.rs`.
+#[contracttype]
+#[derive(Clone, Debug)]
+pub struct SubscriptionV1 {
+    pub subscriber: Address,
+    pub merchant: Address,
+    pub amount: i128,
+    pub interval_seconds: u64,
+    pub last_payment_timestamp: u64,
+    pub status: SubscriptionStatus,
+    pub prepaid_balance: i128,
+    pub usage_enabled: bool,
+}
+
+/// Versioned envelope for records under [`DataKey::Sub`]. The current
+/// [`SubscriptionCore`] and the previous, unsplit [`Subscription`] are written
+/// bare; the envelope lets reads tell older layouts apart (see `stored_sub.rs`).
+#[contracttype]
+#[derive(Clone, Debug)]
+#[allow(clippy::large_enum_variant)]
+pub enum StoredSub {
+    V1(SubscriptionV1),
+    V2(Subscription),
 }
 
 /// Stores subscription details and current state.
@@ -157,55 +751,950 @@ pub struct Subscription {
     pub prepaid_balance: i128,
     /// Whether usage-based billing is enabled for this subscription. ⚠️ Upgrade-sensitive: position 7.
     pub usage_enabled: bool,
+    /// Cumulative amount refunded by the merchant into `prepaid_balance`. ⚠️ Upgrade-sensitive: position 8.
+    pub total_refunded: i128,
+    /// Token contract all transfers for this subscription use. ⚠️ Upgrade-sensitive: position 9.
+    pub token: Address,
+    /// End of the free trial (0 = no trial). Interval charges are rejected before it. ⚠️ Upgrade-sensitive: position 10.
+    pub trial_until: u64,
+    /// Whether `charge_usage` may debit during the trial. ⚠️ Upgrade-sensitive: position 11.
+    pub usage_during_trial: bool,
+    /// Number of billing periods the subscription runs for (0 = unlimited). ⚠️ Upgrade-sensitive: position 12.
+    pub max_periods: u32,
+    /// Interval charges completed so far. ⚠️ Upgrade-sensitive: position 13.
+    pub periods_charged: u32,
+    /// Scheduled cancellation time (0 = none). The first charge attempt at or after it cancels instead of charging. ⚠️ Upgrade-sensitive: position 14.
+    pub cancel_at: u64,
+    /// Ledger timestamp at creation. ⚠️ Upgrade-sensitive: position 15.
+    pub created_at: u64,
+    /// Ledger timestamp of the most recent status change (creation time until the first one). ⚠️ Upgrade-sensitive: position 16.
+    pub status_changed_at: u64,
+    /// Price per usage unit in token base units, used by `charge_usage_units` (0 = not priced). ⚠️ Upgrade-sensitive: position 17.
+    pub usage_rate: i128,
+    /// Usage units charged via `charge_usage_units` in the current billing period; reset by each interval charge. ⚠️ Upgrade-sensitive: position 18.
+    pub period_units_used: u64,
+    /// Subscriber-set ceiling on usage charges per billing period; 0 = unlimited. ⚠️ Upgrade-sensitive: position 19.
+    pub usage_cap_per_period: i128,
+    /// Usage amount charged in the current billing period; reset by each interval charge. ⚠️ Upgrade-sensitive: position 20.
+    pub usage_charged_this_period: i128,
+    /// Consecutive charge attempts that failed for lack of balance; reset by a successful charge or a deposit. ⚠️ Upgrade-sensitive: position 21.
+    pub failed_charge_count: u32,
+    /// When the balance is short, take what is left and record the rest as arrears. ⚠️ Upgrade-sensitive: position 22.
+    pub allow_partial_charge: bool,
+    /// Unpaid shortfall from partial charges, cleared by `settle_arrears`. ⚠️ Upgrade-sensitive: position 23.
+    pub arrears: i128,
+    /// Agreed interval that replaces `interval_seconds` after the next successful charge; 0 = none. ⚠️ Upgrade-sensitive: position 24.
+    pub next_interval_seconds: u64,
+    /// Plan this subscription was created from; 0 = none. ⚠️ Upgrade-sensitive: position 25.
+    pub plan_id: u32,
+    /// Prepaid vault or direct debit from the subscriber's wallet. ⚠️ Upgrade-sensitive: position 26.
+    pub funding_mode: FundingMode,
+    /// Opaque external reference (e.g. customer or invoice id), at most
+    /// [`MAX_METADATA_LEN`] bytes. ⚠️ Upgrade-sensitive: position 27.
+    pub metadata: Option<Bytes>,
+    /// `low_bal` is emitted when a debit takes `prepaid_balance` below this; 0 = off.
+    /// ⚠️ Upgrade-sensitive: position 28.
+    pub low_balance_threshold: i128,
+    /// `REASON_*` code given for the last pause or cancel, including
+    /// `admin_cancel_subscription`; 0 = unspecified. ⚠️ Upgrade-sensitive: position 29.
+    pub reason: u32,
+    /// Start of the current usage rate-limit window. ⚠️ Upgrade-sensitive: position 30.
+    pub usage_window_start: u64,
+    /// Usage charges accepted in the current window. ⚠️ Upgrade-sensitive: position 31.
+    pub usage_window_count: u32,
+    /// Highest nonce accepted by `charge_usage_attested`. ⚠️ Upgrade-sensitive: position 32.
+    pub last_usage_nonce: u64,
+    /// Merchant contract whose `on_charge` is called after each successful
+    /// interval charge. ⚠️ Upgrade-sensitive: position 33.
+    pub charge_callback: Option<Address>,
+    /// Fail the charge when the callback fails, instead of ignoring it.
+    /// ⚠️ Upgrade-sensitive: position 34.
+    pub callback_required: bool,
+    /// Seats billed per interval; each charge is `amount * quantity`. At least 1.
+    /// ⚠️ Upgrade-sensitive: position 35.
+    pub quantity: u32,
+    /// Quantity requested by `update_quantity`, billed from the next interval
+    /// charge on (0 = none). ⚠️ Upgrade-sensitive: position 36.
+    pub pending_quantity: u32,
+    /// Coupon discount applied to interval charges while `discount_periods_left > 0`;
+    /// `Percent(0)` without a coupon. ⚠️ Upgrade-sensitive: position 37.
+    pub discount: CouponKind,
+    /// Interval charges still discounted. ⚠️ Upgrade-sensitive: position 38.
+    pub discount_periods_left: u32,
+    /// Loyalty terms and payment streak. Grouped because a contract type
+    /// allows at most 40 fields. ⚠️ Upgrade-sensitive: position 39.
+    pub loyalty: Loyalty,
+}
+
+/// The half of a stored [`Subscription`] that billing rewrites: balances,
+/// counters and per-period state. Stored under [`DataKey::Sub`] so a charge,
+/// deposit or usage debit writes only these fields (see `stored_sub.rs`).
+///
+/// ⚠️ Upgrade-sensitive: field order and types are serialised as XDR. Moving
+/// a field between the halves changes both layouts.
+#[contracttype]
+#[derive(Clone, Debug)]
+pub struct SubscriptionCore {
+    pub amount: i128,
+    pub interval_seconds: u64,
+    pub last_payment_timestamp: u64,
+    pub status: SubscriptionStatus,
+    pub prepaid_balance: i128,
+    pub periods_charged: u32,
+    pub status_changed_at: u64,
+    pub period_units_used: u64,
+    pub usage_charged_this_period: i128,
+    pub failed_charge_count: u32,
+    pub arrears: i128,
+    pub next_interval_seconds: u64,
+    pub usage_window_start: u64,
+    pub usage_window_count: u32,
+    pub last_usage_nonce: u64,
+    pub quantity: u32,
+    pub pending_quantity: u32,
+    pub discount: CouponKind,
+    pub discount_periods_left: u32,
+    pub loyalty: Loyalty,
+}
+
+/// The half of a stored [`Subscription`] set at creation or by configuration
+/// calls: parties, terms and metadata. Stored under [`DataKeyExt::SubMeta`]
+/// and only rewritten when one of its fields changes.
+///
+/// ⚠️ Upgrade-sensitive: see [`SubscriptionCore`].
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SubscriptionMeta {
+    pub subscriber: Address,
+    pub merchant: Address,
+    pub token: Address,
+    pub usage_enabled: bool,
+    pub total_refunded: i128,
+    pub trial_until: u64,
+    pub usage_during_trial: bool,
+    pub max_periods: u32,
+    pub cancel_at: u64,
+    pub created_at: u64,
+    pub usage_rate: i128,
+    pub usage_cap_per_period: i128,
+    pub allow_partial_charge: bool,
+    pub plan_id: u32,
+    pub funding_mode: FundingMode,
+    pub metadata: Option<Bytes>,
+    pub low_balance_threshold: i128,
+    pub reason: u32,
+    pub charge_callback: Option<Address>,
+    pub callback_required: bool,
+}
+
+/// Reusable merchant pricing. `subscribe_to_plan` snapshots these terms into a
+/// new [`Subscription`], so later plan changes never affect existing subscribers.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Plan {
+    pub merchant: Address,
+    pub amount: i128,
+    pub interval_seconds: u64,
+    pub usage_enabled: bool,
+    /// Price per usage unit for `charge_usage_units` (0 = not priced).
+    pub usage_rate: i128,
+    /// `false` once deactivated; existing subscriptions keep running.
+    pub active: bool,
+}
+
+/// Subscription terms offered by a merchant through `create_invite`, waiting
+/// for the subscriber to accept.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PendingInvite {
+    pub merchant: Address,
+    pub subscriber: Address,
+    pub amount: i128,
+    pub interval_seconds: u64,
+    pub usage_enabled: bool,
+    /// Ledger timestamp from which the invite can no longer be accepted.
+    pub expires_at: u64,
+}
+
+/// Returned by `get_renewal_approval`.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct RenewalApproval {
+    /// Every interval charge needs `approve_next_renewal` first.
+    pub required: bool,
+    /// The next interval charge may go ahead (always true when not required).
+    pub approved: bool,
+    /// Charge number of the next renewal (`periods_charged + 1`).
+    pub renewal: u32,
+    /// When the next renewal is due: `last_payment_timestamp + interval_seconds`.
+    pub due_at: u64,
+}
+
+/// Discount granted by a [`Coupon`] on each discounted charge.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum CouponKind {
+    /// Basis points off the list price (1..=10_000).
+    Percent(u32),
+    /// Fixed amount off the list price; a charge never goes below zero.
+    Fixed(i128),
+}
+
+/// Merchant promotion created by `create_coupon`, keyed by `(merchant, code)`.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Coupon {
+    pub kind: CouponKind,
+    /// Interval charges discounted per redemption.
+    pub duration_periods: u32,
+    /// Redemption limit (0 = unlimited).
+    pub max_redemptions: u32,
+    pub redemptions: u32,
+    /// Ledger timestamp from which the coupon can no longer be redeemed (0 = never).
+    pub expires_at: u64,
+}
+
+/// Loyalty terms set by `set_loyalty_terms`, and the streak that earns them.
+#[contracttype]
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Loyalty {
+    /// Consecutive full charges after which the discount applies (0 = off).
+    pub after_periods: u32,
+    /// Discount in basis points.
+    pub discount_bps: u32,
+    /// Consecutive successful full interval charges; reset by a failed or
+    /// partial charge, an `InsufficientBalance` transition, and cancellation.
+    pub streak: u32,
+}
+
+/// Returned by `get_loyalty_status`.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct LoyaltyStatus {
+    /// Consecutive successful full interval charges.
+    pub streak: u32,
+    /// Streak needed for the discount (0 = no loyalty terms).
+    pub after_periods: u32,
+    pub discount_bps: u32,
+    /// The next interval charge gets the discount.
+    pub active: bool,
+}
+
+/// Admin-maintained registration of a merchant, see `register_merchant`.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct MerchantInfo {
+    pub name: Symbol,
+    pub registered_at: u64,
+    /// `false` once deactivated; blocks new subscriptions only.
+    pub active: bool,
+}
+
+/// Merchant-proposed change to `usage_enabled`, see `propose_usage_toggle`.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct UsageToggle {
+    pub enabled: bool,
+    /// `usage_rate` once accepted; the current rate when disabling.
+    pub usage_rate: i128,
+}
+
+/// Automatic payout of a merchant's earnings, see `set_payout_config`.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PayoutConfig {
+    /// Receives the payouts; need not be the merchant.
+    pub destination: Address,
+    /// Pay out once a charge leaves more than this available, in the charged token.
+    pub threshold: i128,
+    pub enabled: bool,
+}
+
+/// Subscriber's auto top-up, see `set_auto_topup`.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct AutoTopup {
+    pub enabled: bool,
+    /// Top up once a debit leaves `prepaid_balance` below this.
+    pub floor: i128,
+    /// Pulled from the subscriber's wallet per top-up.
+    pub topup_amount: i128,
+}
+
+/// Everything stored about one subscription, returned by `debug_subscription`.
+///
+/// Optional records are a `Vec` of at most one entry, empty when unset: the
+/// SDK cannot convert `Option` of a contract type inside a contract type.
+#[contracttype]
+#[derive(Clone, Debug)]
+pub struct SubscriptionDebug {
+    pub subscription: Subscription,
+    pub stats: SubscriptionStats,
+    pub charge_history: Vec<ChargeHistoryEntry>,
+    pub withdrawable: i128,
+    pub delegate: Option<Address>,
+    pub auto_topup: Vec<AutoTopup>,
+}
+
+/// Everything stored about one merchant, returned by `debug_merchant`.
+/// Optional records are a `Vec` of at most one entry, as in [`SubscriptionDebug`].
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct MerchantDebug {
+    /// Empty for an unregistered merchant.
+    pub info: Vec<MerchantInfo>,
+    /// Balances per supported token, including zero ones.
+    pub balances: Map<Address, MerchantBalances>,
+    pub payout_config: Vec<PayoutConfig>,
+    pub payout_lock: Option<Address>,
+    pub min_topup: Option<i128>,
+    /// The merchant's subscription index, as paged by `get_subscriptions_by_merchant`.
+    pub subscription_ids: Vec<u64>,
+    pub plan_ids: Vec<u32>,
+}
+
+/// Shortest delay between queueing a sensitive admin action and its `eta`.
+pub const MIN_ADMIN_DELAY_SECONDS: u64 = 48 * 60 * 60;
+
+/// Most admin actions that may be queued at once.
+pub const MAX_QUEUED_ADMIN_ACTIONS: u32 = 16;
+
+/// A sensitive admin change that only takes effect through the timelock
+/// (`queue_admin_action`, then `execute_admin_action` after the eta).
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum AdminAction {
+    /// New keeper reward.
+    SetKeeperReward(i128),
+    /// `(token, min_topup)`; also updates the min top-up of a listed token.
+    AddSupportedToken(Address, i128),
+    RemoveSupportedToken(Address),
+    AddPriceOracle(Address),
+    RemovePriceOracle(Address),
+    /// Hash of a previously uploaded WASM.
+    Upgrade(BytesN<32>),
+    /// `(bps, periods)` of the referral program for new subscriptions.
+    SetReferralTerms(u32, u32),
+}
+
+/// Most addresses an [`AdminSet`] may hold.
+pub const MAX_ADMINS: u32 = 10;
+
+/// How long a multisig proposal collects approvals unless the set says otherwise.
+pub const DEFAULT_APPROVAL_WINDOW_SECONDS: u64 = 7 * 24 * 60 * 60;
+
+/// M-of-N admins, see `set_admins`. While present, timelock operations need
+/// `threshold` approvals instead of the single admin's signature.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct AdminSet {
+    pub admins: Vec<Address>,
+    pub threshold: u32,
+    /// Seconds after its proposal a multisig action can still be approved.
+    pub approval_window: u64,
+}
+
+/// Something the [`AdminSet`] approves with `approve_action`.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum MultisigAction {
+    /// Queue an [`AdminAction`] in the timelock with this `eta`.
+    QueueAdminAction(AdminAction, u64),
+    /// Cancel a queued admin action by id.
+    CancelAdminAction(u64),
+    /// Replace the set: `(admins, threshold)`.
+    SetAdmins(Vec<Address>, u32),
+    /// New approval window in seconds.
+    SetApprovalWindow(u64),
+}
+
+/// A [`MultisigAction`] collecting approvals, see `get_admin_proposal`.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct AdminProposal {
+    pub action: MultisigAction,
+    pub approvals: Vec<Address>,
+    /// Last ledger timestamp at which approvals are accepted.
+    pub expires_at: u64,
+}
+
+/// An [`AdminAction`] waiting in the timelock, see `get_queued_admin_actions`.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct QueuedAdminAction {
+    pub id: u64,
+    pub action: AdminAction,
+    /// Earliest ledger timestamp `execute_admin_action` accepts.
+    pub eta: u64,
+    pub queued_at: u64,
+}
+
+/// Merchant earnings credited within one time bucket, withdrawable from
+/// `release_at`.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PendingBucket {
+    pub release_at: u64,
+    pub amount: i128,
+}
+
+/// A merchant's earnings in one token, split by whether the settlement delay
+/// has passed.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct MerchantBalances {
+    /// Withdrawable now.
+    pub available: i128,
+    /// Still inside the settlement delay; usable for refunds only.
+    pub pending: i128,
+}
+
+/// An interval charge that can still be disputed.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ChargeRecord {
+    pub amount: i128,
+    /// End of the settlement window; disputes must be opened before this.
+    pub release_at: u64,
+    pub disputed: bool,
+}
+
+#[contracttype]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ChargeKind {
+    /// Interval charge, including arrears settled on deposit.
+    Periodic = 0,
+    Usage = 1,
+    /// Mid-period seat upgrade billed by `update_quantity_prorated`.
+    Proration = 2,
+    /// Merchant credit from `issue_usage_credit`; its entry's `amount` is
+    /// negative and its `seq` is that of the charge it follows.
+    Credit = 3,
+}
+
+/// One entry of a subscription's charge history.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ChargeHistoryEntry {
+    pub timestamp: u64,
+    pub amount: i128,
+    pub kind: ChargeKind,
+    /// Lifetime charge count after this charge, shared by all charge kinds.
+    pub seq: u32,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum DisputeStatus {
+    /// Amount is frozen, awaiting admin decision.
+    Open = 0,
+    /// Amount went back to the subscriber's prepaid balance.
+    Refunded = 1,
+    /// Amount went to the merchant.
+    Released = 2,
+}
+
+/// A subscriber's challenge of one charge, see `open_dispute`.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Dispute {
+    pub subscription_id: u64,
+    pub period_seq: u32,
+    pub amount: i128,
+    pub opened_at: u64,
+    pub status: DisputeStatus,
+}
+
+/// A merchant's batch of usage, committed as a merkle root of
+/// `(subscription_id, amount)` leaves.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct UsageRoot {
+    /// Merchant that posted the root; leaves only apply to its subscriptions.
+    pub operator: Address,
+    pub root: BytesN<32>,
+    /// Sum of all leaf amounts as declared by the operator.
+    pub total: i128,
+    /// Sum of leaf amounts applied so far; never exceeds `total`.
+    pub applied: i128,
+    pub posted_at: u64,
+}
+
+/// One volume pricing breakpoint for tiered usage billing.
+///
+/// Units numbered `(previous.up_to, up_to]` within a billing period are
+/// priced at `rate`. Tiers are cumulative per period: the 1,001st unit of a
+/// period is priced by the second tier no matter how many calls it took to
+/// get there. Units beyond the last tier's `up_to` use the last tier's rate.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct UsageTier {
+    /// Inclusive upper bound of this tier, in units per billing period.
+    pub up_to: u64,
+    /// Price per unit within this tier, in token base units.
+    pub rate: i128,
+}
+
+/// Lifetime billing statistics for one subscription.
+///
+/// Stored under [`DataKey::SubStats`] rather than on [`Subscription`] so the
+/// main read path stays small. Running totals saturate instead of trapping.
+#[contracttype]
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct SubscriptionStats {
+    /// Sum of all interval charges.
+    pub total_charged: i128,
+    /// Sum of all usage charges.
+    pub total_usage_charged: i128,
+    /// Sum of merchant credits from `issue_usage_credit`; refunds are in
+    /// `Subscription::total_refunded`.
+    pub total_credited: i128,
+    /// Number of successful charges of every kind. Each charge is assigned the
+    /// count after it as its charge sequence number, so the numbers run
+    /// 1, 2, 3, ... without gaps or reuse.
+    pub charge_count: u32,
+    /// Amount of the most recent successful charge.
+    pub last_charge_amount: i128,
+    /// Ledger timestamp of the most recent successful charge (0 = never).
+    pub last_charge_at: u64,
+}
+
+/// Optional terms agreed at creation, passed to
+/// [`crate::SubscriptionVault::create_subscription_with_options`].
+///
+/// Zero / `false` / `None` means "not used", so `SubscriptionOptions::default()`
+/// produces the same subscription as plain `create_subscription`.
+#[contracttype]
+#[derive(Clone, Debug, Default)]
+pub struct SubscriptionOptions {
+    /// Token to bill in; `None` uses the token passed to `init`.
+    pub token: Option<Address>,
+    /// Ledger timestamp at which the free trial ends (0 = no trial).
+    pub trial_until: u64,
+    /// Allow usage charges while the trial is running.
+    pub usage_during_trial: bool,
+    /// Fixed number of billing periods (0 = unlimited).
+    pub max_periods: u32,
+    /// Price per usage unit for `charge_usage_units` (0 = not priced).
+    pub usage_rate: i128,
+    /// Drain the remaining balance on a short charge and record arrears.
+    pub allow_partial_charge: bool,
+    /// Skip duplicate prevention, for merchants selling parallel subscriptions.
+    pub allow_duplicates: bool,
+    /// Deposited from the subscriber as part of creation (0 = none).
+    pub initial_deposit: i128,
+    /// Pay from the prepaid vault (default) or by direct debit.
+    pub funding_mode: FundingMode,
+    /// Opaque external reference stored on the subscription.
+    pub metadata: Option<Bytes>,
+    /// Seats billed per interval (0 = 1).
+    pub quantity: u32,
+    /// Merchant coupon to redeem; fails with `CouponInvalid` if it cannot be.
+    pub coupon: Option<BytesN<16>>,
+    /// Most the subscription may ever charge, interval and usage combined (0 = unlimited).
+    pub lifetime_cap: i128,
+    /// Allowlisted price oracle; when set the subscription is priced in fiat
+    /// and `fiat_amount` replaces `amount` (see [`FiatPricing`]).
+    pub price_oracle: Option<Address>,
+    /// Fiat price per interval at the oracle's precision; used only with `price_oracle`.
+    pub fiat_amount: i128,
+    /// Charge on `billing_anchor + k * interval_seconds`, prorating the first
+    /// charge (0 = charge one interval after the billing clock starts).
+    pub billing_anchor: u64,
+    /// Allow usage charges while the subscription is `Paused`; interval
+    /// charges stay blocked.
+    pub usage_while_paused: bool,
+    /// Earns the admin's referral fee on the first interval charges (see
+    /// [`ReferralTerms`]); must be neither the subscriber nor the merchant.
+    pub referrer: Option<Address>,
+}
+
+/// Admin-set referral program, see `AdminAction::SetReferralTerms`. Either
+/// field 0 turns it off for new subscriptions.
+#[contracttype]
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct ReferralTerms {
+    /// Share of each referred charge, after the keeper reward, in basis points.
+    pub bps: u32,
+    /// Successful interval charges that pay the fee.
+    pub periods: u32,
+}
+
+/// A subscription's referral, with the terms in force when it was created.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Referral {
+    pub referrer: Address,
+    pub bps: u32,
+    /// Interval charges still paying the fee; the record is removed at 0.
+    pub periods_left: u32,
+}
+
+/// Merchant-set bond that subscriptions created with a trial must post.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct TrialBondTerms {
+    /// Bond in the subscription's token base units, pulled at creation.
+    pub bond: i128,
+    /// Share of the bond the merchant keeps if the trial never converts, in
+    /// basis points (at most 10000).
+    pub forfeit_bps: u32,
+}
+
+/// A subscription's trial bond, with the forfeit fixed when it was posted.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct TrialBond {
+    /// Who posted the bond and receives the refund.
+    pub payer: Address,
+    pub amount: i128,
+    pub forfeit_bps: u32,
+}
+
+/// Fixed charge schedule of an anchored subscription (see `anchor.rs`).
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct BillingAnchor {
+    /// Any timestamp on the schedule; charges fall on `anchor + k * interval_seconds`.
+    pub anchor: u64,
+    /// Length of the first, prorated period: from the start of billing to the first
+    /// anchor date after it. Equals `interval_seconds` when billing starts on one.
+    pub first_period_seconds: u64,
+}
+
+/// Fiat price of a subscription. `fiat_amount` uses the oracle's price
+/// precision (its `decimals()`), e.g. `999 * 10^12` for 9.99 at 14 decimals.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct FiatPricing {
+    /// Admin-allowlisted SEP-40 price oracle quoting the token in the fiat currency.
+    pub oracle: Address,
+    pub fiat_amount: i128,
+}
+
+/// SEP-40 asset identifier, the argument of an oracle's `lastprice`.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum OracleAsset {
+    Stellar(Address),
+    Other(Symbol),
+}
+
+/// SEP-40 `lastprice` result: fiat per whole token at the oracle's precision.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PriceData {
+    pub price: i128,
+    pub timestamp: u64,
 }
 
 // Event types
+//
+// Every subscription event is published with topics `(action, subscription_id)`
+// and one of the structs below as data. Balance-affecting events carry the
+// resulting `prepaid_balance`; status-changing events carry `previous_status`
+// and `new_status`, so indexers never need a follow-up read.
+//
+// | Topic | Data |
+// |-------|------|
+// | `sub_new` | [`SubscriptionCreatedEvent`] |
+// | `deposited`, `deposited_on_behalf` | [`FundsDepositedEvent`] |
+// | `charged` | [`SubscriptionChargedEvent`] |
+// | `usage`, `usage_units` | [`UsageChargedEvent`] |
+// | `partial` | [`PartialChargeEvent`] |
+// | `settled` | [`ArrearsSettledEvent`] |
+// | `completed` | [`SubscriptionCompletedEvent`] |
+// | `insufficient_balance`, `activated` | [`StatusChangedEvent`] |
+// | `dunn_end` | [`DunningExhaustedEvent`] |
+// | `paused` | [`SubscriptionPausedEvent`] |
+// | `resumed` | [`SubscriptionResumedEvent`] |
+// | `cancelled` | [`SubscriptionCancelledEvent`] |
+// | `admin_cancelled` | [`AdminCancelledEvent`] |
+// | `purged` | [`SubscriptionPurgedEvent`] |
+// | `snapshot` | [`SubscriptionSnapshotEvent`] |
+// | `dispute_opened` | [`DisputeOpenedEvent`] |
+// | `dispute_resolved` | [`DisputeResolvedEvent`] |
+// | `usage_leaf` | [`UsageLeafAppliedEvent`] |
+// | `refunded` | [`SubscriptionRefundedEvent`] |
+// | `credit` | [`UsageCreditEvent`] |
+// | `subscriber_withdrawn` | [`SubscriberWithdrawnEvent`] |
+// | `low_bal` | [`LowBalanceEvent`] |
+// | `transfer_proposed`, `transferred`, `transfer_cancelled` | [`SubscriptionTransferEvent`] |
+//
+// Configuration events (amount/interval proposals, caps, tiers, metadata,
+// scheduled cancellation) use the same topics with a plain value as data.
+//
+// `low_bal`, `grace`, `insufficient_balance`, `dunn_end` and `charged` get the
+// subscriber's notification address as an extra last topic when one is set
+// (see `notify.rs`).
+
+/// Topic `(sub_new, subscription_id)`.
 #[contracttype]
 #[derive(Clone, Debug)]
 pub struct SubscriptionCreatedEvent {
-    pub subscription_id: u32,
+    pub subscription_id: u64,
     pub subscriber: Address,
+    /// Creator and funder; differs from `subscriber` for a gift subscription.
+    pub payer: Address,
     pub merchant: Address,
     pub amount: i128,
     pub interval_seconds: u64,
+    pub metadata: Option<Bytes>,
 }
 
+/// Topic `(deposited, subscription_id)`, or `(deposited_on_behalf, subscription_id)`
+/// when a third party paid; `payer` equals `subscriber` otherwise.
 #[contracttype]
 #[derive(Clone, Debug)]
 pub struct FundsDepositedEvent {
-    pub subscription_id: u32,
+    pub subscription_id: u64,
     pub subscriber: Address,
+    pub payer: Address,
     pub amount: i128,
+    pub prepaid_balance: i128,
 }
 
+/// Topic `(charged, subscription_id)`. `amount` is what was actually collected.
 #[contracttype]
 #[derive(Clone, Debug)]
 pub struct SubscriptionChargedEvent {
-    pub subscription_id: u32,
+    pub subscription_id: u64,
     pub merchant: Address,
     pub amount: i128,
+    pub prepaid_balance: i128,
+    /// `periods_charged` after this charge, the number to pass to
+    /// `open_dispute`. The topic carries the charge sequence number instead.
+    pub period_seq: u32,
+    /// Part of `amount` paid to the caller instead of the merchant.
+    pub keeper_reward: i128,
+    /// Seats billed by this charge.
+    pub quantity: u32,
+    /// Price before any coupon discount; `amount` is at most this.
+    pub list_amount: i128,
+    /// Part of `amount` credited to the referrer instead of the merchant.
+    pub referral_fee: i128,
+}
+
+/// Topic `(usage, subscription_id)` for `charge_usage` (`units` = 0), or
+/// `(usage_units, subscription_id)` for `charge_usage_units`.
+#[contracttype]
+#[derive(Clone, Debug)]
+pub struct UsageChargedEvent {
+    pub subscription_id: u64,
+    pub units: u64,
+    pub amount: i128,
+    pub prepaid_balance: i128,
+}
+
+/// Topic `(partial, subscription_id)`, after the matching `charged` event.
+#[contracttype]
+#[derive(Clone, Debug)]
+pub struct PartialChargeEvent {
+    pub subscription_id: u64,
+    pub amount: i128,
+    pub shortfall: i128,
+    pub arrears: i128,
+    pub prepaid_balance: i128,
+    pub previous_status: SubscriptionStatus,
+    pub new_status: SubscriptionStatus,
+}
+
+/// Topic `(settled, subscription_id)`.
+#[contracttype]
+#[derive(Clone, Debug)]
+pub struct ArrearsSettledEvent {
+    pub subscription_id: u64,
+    pub amount: i128,
+    pub arrears: i128,
+    pub prepaid_balance: i128,
+}
+
+/// Topic `(completed, subscription_id)`, after the final `charged` event.
+#[contracttype]
+#[derive(Clone, Debug)]
+pub struct SubscriptionCompletedEvent {
+    pub subscription_id: u64,
+    pub periods_charged: u32,
+    pub prepaid_balance: i128,
+    pub previous_status: SubscriptionStatus,
+    pub new_status: SubscriptionStatus,
+}
+
+/// Topic `(insufficient_balance, subscription_id)`: a failed charge or a usage
+/// debit moved the subscription to `InsufficientBalance`. Topic
+/// `(activated, subscription_id)`: a deposit moved it from `Pending` to `Active`.
+#[contracttype]
+#[derive(Clone, Debug)]
+pub struct StatusChangedEvent {
+    pub subscription_id: u64,
+    pub prepaid_balance: i128,
+    pub previous_status: SubscriptionStatus,
+    pub new_status: SubscriptionStatus,
+    pub timestamp: u64,
+}
+
+/// Topic `(dunn_end, subscription_id)`: cancelled after `MaxFailedCharges`.
+#[contracttype]
+#[derive(Clone, Debug)]
+pub struct DunningExhaustedEvent {
+    pub subscription_id: u64,
+    pub failed_charge_count: u32,
+    pub prepaid_balance: i128,
+    pub previous_status: SubscriptionStatus,
+    pub new_status: SubscriptionStatus,
+    pub timestamp: u64,
 }
 
+/// Topic `(cancelled, subscription_id)`. `refund_amount` is the balance left
+/// for the subscriber to withdraw.
 #[contracttype]
 #[derive(Clone, Debug)]
 pub struct SubscriptionCancelledEvent {
-    pub subscription_id: u32,
+    pub subscription_id: u64,
     pub authorizer: Address,
+    pub reason: u32,
     pub refund_amount: i128,
+    pub previous_status: SubscriptionStatus,
+    pub new_status: SubscriptionStatus,
+    pub timestamp: u64,
+}
+
+/// Topic `(admin_cancelled, subscription_id)`. The prepaid balance stays with
+/// the subscriber.
+#[contracttype]
+#[derive(Clone, Debug)]
+pub struct AdminCancelledEvent {
+    pub subscription_id: u64,
+    pub admin: Address,
+    pub reason_code: u32,
+    pub prepaid_balance: i128,
+    pub previous_status: SubscriptionStatus,
+    pub new_status: SubscriptionStatus,
+    pub timestamp: u64,
+}
+
+/// Topic `(snapshot, subscription_id)`. The full current record, published by
+/// `snapshot` and after every status change, for indexers to resynchronize from.
+#[contracttype]
+#[derive(Clone, Debug)]
+pub struct SubscriptionSnapshotEvent {
+    pub subscription_id: u64,
+    pub subscription: Subscription,
+    /// Ledger sequence the snapshot was taken at, to order it against other events.
+    pub ledger: u32,
+    pub timestamp: u64,
 }
 
+/// Topic `(purged, subscription_id)`. Final snapshot of a subscription whose
+/// storage was deleted; after this the record exists only in the event stream.
+#[contracttype]
+#[derive(Clone, Debug)]
+pub struct SubscriptionPurgedEvent {
+    pub subscription_id: u64,
+    pub subscription: Subscription,
+    pub stats: SubscriptionStats,
+    pub timestamp: u64,
+}
+
+/// Topic `(dispute_opened, subscription_id)`.
+#[contracttype]
+#[derive(Clone, Debug)]
+pub struct DisputeOpenedEvent {
+    pub dispute_id: u32,
+    pub subscription_id: u64,
+    pub period_seq: u32,
+    pub merchant: Address,
+    pub amount: i128,
+}
+
+/// Topic `(dispute_resolved, subscription_id)`.
+#[contracttype]
+#[derive(Clone, Debug)]
+pub struct DisputeResolvedEvent {
+    pub dispute_id: u32,
+    pub subscription_id: u64,
+    pub amount: i128,
+    /// `true` if refunded to the subscriber, `false` if released to the merchant.
+    pub refunded: bool,
+    pub prepaid_balance: i128,
+}
+
+/// Topic `(usage_leaf, subscription_id)`. `charged` is less than `amount`
+/// when the shortfall went to arrears.
+#[contracttype]
+#[derive(Clone, Debug)]
+pub struct UsageLeafAppliedEvent {
+    pub root_id: u32,
+    pub subscription_id: u64,
+    pub amount: i128,
+    pub charged: i128,
+    pub prepaid_balance: i128,
+}
+
+/// Topic `(paused, subscription_id)`.
 #[contracttype]
 #[derive(Clone, Debug)]
 pub struct SubscriptionPausedEvent {
-    pub subscription_id: u32,
+    pub subscription_id: u64,
     pub authorizer: Address,
+    pub reason: u32,
+    pub prepaid_balance: i128,
+    pub previous_status: SubscriptionStatus,
+    pub new_status: SubscriptionStatus,
+    pub timestamp: u64,
 }
 
+/// Topic `(resumed, subscription_id)`.
 #[contracttype]
 #[derive(Clone, Debug)]
 pub struct SubscriptionResumedEvent {
-    pub subscription_id: u32,
+    pub subscription_id: u64,
     pub authorizer: Address,
+    pub prepaid_balance: i128,
+    pub previous_status: SubscriptionStatus,
+    pub new_status: SubscriptionStatus,
+    pub timestamp: u64,
+}
+
+/// Topic `(low_bal, subscription_id)`: a debit took `prepaid_balance` from at
+/// or above `threshold` to below it. Emitted once per crossing.
+#[contracttype]
+#[derive(Clone, Debug)]
+pub struct LowBalanceEvent {
+    pub subscription_id: u64,
+    pub prepaid_balance: i128,
+    pub threshold: i128,
+}
+
+/// Topics `(transfer_proposed | transferred | transfer_cancelled, subscription_id)`.
+/// `merchant` is included so merchants can follow transfers of their subscriptions.
+#[contracttype]
+#[derive(Clone, Debug)]
+pub struct SubscriptionTransferEvent {
+    pub subscription_id: u64,
+    pub merchant: Address,
+    pub from: Address,
+    pub to: Address,
+    pub prepaid_balance: i128,
+}
+
+/// Topic `(subscriber_withdrawn, subscription_id)`.
+#[contracttype]
+#[derive(Clone, Debug)]
+pub struct SubscriberWithdrawnEvent {
+    pub subscription_id: u64,
+    pub subscriber: Address,
+    pub amount: i128,
+    pub prepaid_balance: i128,
+}
+
+/// Topic `(merchant_rotated, old_merchant)`.
+#[contracttype]
+#[derive(Clone, Debug)]
+pub struct MerchantRotatedEvent {
+    pub old_merchant: Address,
+    pub new_merchant: Address,
+    pub timestamp: u64,
 }
 
 #[contracttype]
@@ -215,11 +1704,33 @@ pub struct MerchantWithdrawalEvent {
     pub amount: i128,
 }
 
+/// Topic `(credit, subscription_id)`: a merchant credited accrued earnings
+/// back into a subscription to correct over-metered usage.
+#[contracttype]
+#[derive(Clone, Debug)]
+pub struct UsageCreditEvent {
+    pub subscription_id: u64,
+    pub merchant: Address,
+    pub amount: i128,
+    pub prepaid_balance: i128,
+}
+
+/// Topic `(refunded, subscription_id)`: a merchant refunded accrued earnings
+/// back into a subscription.
+#[contracttype]
+#[derive(Clone, Debug)]
+pub struct SubscriptionRefundedEvent {
+    pub subscription_id: u64,
+    pub merchant: Address,
+    pub amount: i128,
+    pub prepaid_balance: i128,
+}
+
 /// Emitted when a merchant-initiated one-off charge is applied to a subscription.
 #[contracttype]
 #[derive(Clone, Debug)]
 pub struct OneOffChargedEvent {
-    pub subscription_id: u32,
+    pub subscription_id: u64,
     pub merchant: Address,
     pub amount: i128,
 }
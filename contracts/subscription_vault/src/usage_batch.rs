@@ -0,0 +1,146 @@
+//! Aggregated usage settlement: settle_usage_batch, apply_usage_leaf.
+//!
+//! **PRs that only change batched usage settlement should edit this file only.**
+//!
+//! A merchant's metering service posts one merkle root per batch, covering
+//! `(subscription_id, amount)` leaves and their grand total. Anyone can then
+//! apply a leaf with its proof; the debit follows the normal usage rules.
+//!
+//! Hashing (sha256, big-endian integers):
+//! * leaf = `H(0x00 || subscription_id: u64 || amount: i128)`
+//! * node = `H(0x01 || min(a, b) || max(a, b))`, i.e. pairs are sorted, so a
+//!   proof is just the list of sibling hashes from leaf to root.
+//!
+//! Roots and applied-leaf markers grow with volume, so they live in
+//! persistent storage rather than the instance entry.
+
+use crate::admin::require_not_paused;
+use crate::charge_core::debit_usage;
+use crate::stats::load_subscription;
+use crate::ttl::bump_persistent;
+use crate::types::{DataKey, Error, UsageLeafAppliedEvent, UsageRoot};
+use soroban_sdk::{Address, Bytes, BytesN, Env, Symbol, Vec};
+
+/// Merchant posts a usage root for its subscriptions. Returns the root id.
+pub fn do_settle_usage_batch(
+    env: &Env,
+    operator: Address,
+    root: BytesN<32>,
+    total: i128,
+) -> Result<u32, Error> {
+    operator.require_auth();
+    require_not_paused(env)?;
+    if total <= 0 {
+        return Err(Error::InvalidAmount);
+    }
+
+    let root_id: u32 = env
+        .storage()
+        .instance()
+        .get(&DataKey::NextUsageRootId)
+        .unwrap_or(0);
+    let next = root_id.checked_add(1).ok_or(Error::Overflow)?;
+    env.storage()
+        .instance()
+        .set(&DataKey::NextUsageRootId, &next);
+
+    let record = UsageRoot {
+        operator,
+        root,
+        total,
+        applied: 0,
+        posted_at: env.ledger().timestamp(),
+    };
+    env.storage()
+        .persistent()
+        .set(&DataKey::UsageRoot(root_id), &record);
+    bump_persistent(env, &DataKey::UsageRoot(root_id));
+    env.events()
+        .publish((Symbol::new(env, "usage_root"), root_id), record);
+    Ok(root_id)
+}
+
+/// Apply one leaf of a posted root. Permissionless; each leaf applies once.
+pub fn do_apply_usage_leaf(
+    env: &Env,
+    root_id: u32,
+    proof: Vec<BytesN<32>>,
+    subscription_id: u64,
+    amount: i128,
+) -> Result<(), Error> {
+    require_not_paused(env)?;
+    let mut record = get_usage_root(env, root_id)?;
+    let leaf = leaf_hash(env, subscription_id, amount);
+    let applied_key = DataKey::UsageLeafApplied(root_id, leaf.clone());
+    if env.storage().persistent().has(&applied_key) {
+        return Err(Error::Replay);
+    }
+    if !verify_proof(env, &record.root, leaf, &proof) {
+        return Err(Error::InvalidMerkleProof);
+    }
+    record.applied = record.applied.checked_add(amount).ok_or(Error::Overflow)?;
+    if record.applied > record.total {
+        return Err(Error::InvalidMerkleProof);
+    }
+
+    let sub = load_subscription(env, subscription_id)?;
+    if sub.merchant != record.operator {
+        return Err(Error::Unauthorized);
+    }
+    let (receipt, pull) = debit_usage(env, subscription_id, sub, amount, true)?;
+
+    env.storage().persistent().set(&applied_key, &true);
+    bump_persistent(env, &applied_key);
+    env.storage()
+        .persistent()
+        .set(&DataKey::UsageRoot(root_id), &record);
+    bump_persistent(env, &DataKey::UsageRoot(root_id));
+    pull.execute(env);
+    env.events().publish(
+        (
+            Symbol::new(env, "usage_leaf"),
+            subscription_id,
+            receipt.charge_seq,
+        ),
+        UsageLeafAppliedEvent {
+            root_id,
+            subscription_id,
+            amount,
+            charged: receipt.amount_charged,
+            prepaid_balance: receipt.new_prepaid_balance,
+        },
+    );
+    Ok(())
+}
+
+pub fn get_usage_root(env: &Env, root_id: u32) -> Result<UsageRoot, Error> {
+    env.storage()
+        .persistent()
+        .get(&DataKey::UsageRoot(root_id))
+        .ok_or(Error::NotFound)
+}
+
+pub fn leaf_hash(env: &Env, subscription_id: u64, amount: i128) -> BytesN<32> {
+    let mut data = Bytes::new(env);
+    data.push_back(0);
+    data.extend_from_array(&subscription_id.to_be_bytes());
+    data.extend_from_array(&amount.to_be_bytes());
+    env.crypto().sha256(&data).to_bytes()
+}
+
+pub fn node_hash(env: &Env, a: &BytesN<32>, b: &BytesN<32>) -> BytesN<32> {
+    let (lo, hi) = if a <= b { (a, b) } else { (b, a) };
+    let mut data = Bytes::new(env);
+    data.push_back(1);
+    data.extend_from_array(&lo.to_array());
+    data.extend_from_array(&hi.to_array());
+    env.crypto().sha256(&data).to_bytes()
+}
+
+fn verify_proof(env: &Env, root: &BytesN<32>, leaf: BytesN<32>, proof: &Vec<BytesN<32>>) -> bool {
+    let mut node = leaf;
+    for sibling in proof.iter() {
+        node = node_hash(env, &node, &sibling);
+    }
+    node == *root
+}
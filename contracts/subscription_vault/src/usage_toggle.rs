@@ -0,0 +1,131 @@
+//! Usage toggle: turning `usage_enabled` on or off after creation, e.g. to
+//! add a metered add-on to a flat-rate customer.
+//!
+//! **PRs that only change the usage toggle should edit this file only.**
+//!
+//! Same two-step flow as amount changes: the merchant proposes, the subscriber
+//! accepts. Until then usage charges follow the current flag.
+
+use crate::queries::get_subscription;
+use crate::state_machine::get_allowed_transitions;
+use crate::stats::{load_subscription, save_subscription};
+use crate::types::{DataKeyExt, Error, UsageToggle};
+use soroban_sdk::{Address, Env, Symbol};
+
+pub fn get_pending(env: &Env, subscription_id: u64) -> Option<UsageToggle> {
+    crate::stored_sub::get_entry(env, &DataKeyExt::PendingUsageToggle(subscription_id))
+}
+
+pub fn remove(env: &Env, subscription_id: u64) {
+    crate::stored_sub::remove_entry(env, &DataKeyExt::PendingUsageToggle(subscription_id));
+}
+
+/// Merchant proposes enabling usage at `usage_rate`, or disabling it
+/// (`usage_rate` is then ignored). Replaces any earlier pending proposal.
+pub fn do_propose_usage_toggle(
+    env: &Env,
+    subscription_id: u64,
+    merchant: Address,
+    enabled: bool,
+    usage_rate: i128,
+) -> Result<(), Error> {
+    merchant.require_auth();
+
+    let sub = get_subscription(env, subscription_id)?;
+    if merchant != sub.merchant {
+        return Err(Error::Unauthorized);
+    }
+    if get_allowed_transitions(&sub.status).is_empty() {
+        return Err(Error::InvalidStatusTransition);
+    }
+    if enabled && usage_rate < 0 {
+        return Err(Error::InvalidAmount);
+    }
+
+    let toggle = UsageToggle {
+        enabled,
+        usage_rate: if enabled { usage_rate } else { sub.usage_rate },
+    };
+    crate::stored_sub::set_entry(
+        env,
+        &DataKeyExt::PendingUsageToggle(subscription_id),
+        &toggle,
+    );
+    env.events().publish(
+        (Symbol::new(env, "usage_toggle_proposed"), subscription_id),
+        toggle,
+    );
+    Ok(())
+}
+
+/// Subscriber accepts the pending toggle, which applies at once. Disabling
+/// clears this period's usage counters.
+pub fn do_accept_usage_toggle(
+    env: &Env,
+    subscription_id: u64,
+    subscriber: Address,
+) -> Result<(), Error> {
+    subscriber.require_auth();
+
+    let mut sub = load_subscription(env, subscription_id)?;
+    if subscriber != sub.subscriber {
+        return Err(Error::Unauthorized);
+    }
+    if get_allowed_transitions(&sub.status).is_empty() {
+        return Err(Error::InvalidStatusTransition);
+    }
+    let toggle = get_pending(env, subscription_id).ok_or(Error::NotFound)?;
+
+    sub.usage_enabled = toggle.enabled;
+    sub.usage_rate = toggle.usage_rate;
+    if !toggle.enabled {
+        sub.period_units_used = 0;
+        sub.usage_charged_this_period = 0;
+    }
+    save_subscription(env, subscription_id, &mut sub)?;
+    remove(env, subscription_id);
+    env.events().publish(
+        (Symbol::new(env, "usage_toggle_accepted"), subscription_id),
+        toggle,
+    );
+    Ok(())
+}
+
+/// Drop the pending toggle: the subscriber rejects it or the merchant withdraws it.
+pub fn do_clear_usage_toggle(
+    env: &Env,
+    subscription_id: u64,
+    caller: Address,
+    rejected: bool,
+) -> Result<(), Error> {
+    caller.require_auth();
+
+    let sub = get_subscription(env, subscription_id)?;
+    let expected = if rejected {
+        &sub.subscriber
+    } else {
+        &sub.merchant
+    };
+    if caller != *expected {
+        return Err(Error::Unauthorized);
+    }
+    let toggle = get_pending(env, subscription_id).ok_or(Error::NotFound)?;
+
+    remove(env, subscription_id);
+    let topic = if rejected {
+        "usage_toggle_rejected"
+    } else {
+        "usage_toggle_withdrawn"
+    };
+    env.events()
+        .publish((Symbol::new(env, topic), subscription_id), toggle);
+    Ok(())
+}
+
+pub fn get_pending_usage_toggle(
+    env: &Env,
+    subscription_id: u64,
+) -> Result<Option<UsageToggle>, Error> {
+    get_subscription(env, subscription_id)?;
+    Ok(get_pending(env, subscription_id))
+}
@@ -0,0 +1,105 @@
+//! Viewer role: support staff granted read access to combined diagnostic
+//! views of a subscription or merchant.
+//!
+//! **PRs that only change the viewer role or debug views should edit this file only.**
+//!
+//! The debug views only gather what the public views already return, in one
+//! call; gating them keeps the aggregates for support tooling rather than
+//! making them another public surface to keep stable.
+
+use crate::admin::require_admin;
+use crate::types::{DataKey, DataKeyExt, Error, MerchantDebug, SubscriptionDebug};
+use soroban_sdk::{Address, Env, IntoVal, Map, Symbol, TryFromVal, Val, Vec};
+
+pub fn is_viewer(env: &Env, address: &Address) -> bool {
+    env.storage()
+        .instance()
+        .has(&DataKeyExt::Viewer(address.clone()))
+}
+
+/// Admin grants or revokes the viewer role.
+pub fn do_set_viewer(
+    env: &Env,
+    admin: Address,
+    viewer: Address,
+    enabled: bool,
+) -> Result<(), Error> {
+    admin.require_auth();
+    if admin != require_admin(env)? {
+        return Err(Error::Unauthorized);
+    }
+    let key = DataKeyExt::Viewer(viewer.clone());
+    let name = if enabled {
+        env.storage().instance().set(&key, &true);
+        "viewer_added"
+    } else {
+        env.storage().instance().remove(&key);
+        "viewer_removed"
+    };
+    env.events()
+        .publish((Symbol::new(env, name), viewer), admin);
+    Ok(())
+}
+
+/// `value` as the zero-or-one entry `Vec` the debug structs use for options.
+fn optional<T>(env: &Env, value: Option<T>) -> Vec<T>
+where
+    T: IntoVal<Env, Val> + TryFromVal<Env, Val>,
+{
+    let mut entries = Vec::new(env);
+    if let Some(value) = value {
+        entries.push_back(value);
+    }
+    entries
+}
+
+fn require_viewer(env: &Env, viewer: &Address) -> Result<(), Error> {
+    viewer.require_auth();
+    if !is_viewer(env, viewer) {
+        return Err(Error::Unauthorized);
+    }
+    Ok(())
+}
+
+pub fn debug_subscription(
+    env: &Env,
+    viewer: Address,
+    subscription_id: u64,
+) -> Result<SubscriptionDebug, Error> {
+    require_viewer(env, &viewer)?;
+    let subscription = crate::queries::get_subscription(env, subscription_id)?;
+    Ok(SubscriptionDebug {
+        withdrawable: crate::queries::compute_withdrawable(&subscription),
+        subscription,
+        stats: crate::queries::get_subscription_stats(env, subscription_id)?,
+        charge_history: crate::history::get_charge_history(env, subscription_id),
+        delegate: crate::delegate::get(env, subscription_id),
+        auto_topup: optional(env, crate::auto_topup::get(env, subscription_id)),
+    })
+}
+
+pub fn debug_merchant(
+    env: &Env,
+    viewer: Address,
+    merchant: Address,
+) -> Result<MerchantDebug, Error> {
+    require_viewer(env, &viewer)?;
+    let mut balances = Map::new(env);
+    for token in crate::admin::get_supported_tokens(env).iter() {
+        let token_balances = crate::merchant::get_merchant_balances(env, &merchant, &token);
+        balances.set(token, token_balances);
+    }
+    Ok(MerchantDebug {
+        info: optional(env, crate::merchant::get_merchant_info(env, &merchant).ok()),
+        balances,
+        payout_config: optional(env, crate::merchant::get_payout_config(env, &merchant)),
+        payout_lock: crate::merchant::get_payout_lock(env, &merchant),
+        min_topup: crate::merchant::get_merchant_min_topup(env, &merchant),
+        subscription_ids: env
+            .storage()
+            .instance()
+            .get(&DataKey::MerchantSubs(merchant.clone()))
+            .unwrap_or(Vec::new(env)),
+        plan_ids: crate::plan::get_merchant_plans(env, merchant),
+    })
+}